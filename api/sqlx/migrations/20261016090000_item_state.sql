@@ -0,0 +1,10 @@
+-- Add migration script here
+ALTER TABLE lists_content
+	ADD COLUMN checked BOOLEAN NOT NULL DEFAULT false,
+	ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+UPDATE lists_content
+	SET position = ordered.position
+	FROM (SELECT id, (row_number() OVER (PARTITION BY list ORDER BY id) - 1) AS position
+	      FROM lists_content) AS ordered
+	WHERE lists_content.id = ordered.id;
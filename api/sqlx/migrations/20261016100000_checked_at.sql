@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE lists_content ADD COLUMN checked_at TIMESTAMPTZ;
+
+UPDATE lists_content SET checked_at = now() WHERE checked;
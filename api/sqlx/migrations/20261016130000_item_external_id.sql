@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE lists_content ADD COLUMN external_id UUID NOT NULL UNIQUE DEFAULT uuid_generate_v4();
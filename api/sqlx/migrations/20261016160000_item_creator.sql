@@ -0,0 +1,12 @@
+-- Add migration script here
+ALTER TABLE lists_content ADD COLUMN creator uuid REFERENCES accounts(id);
+
+UPDATE lists_content SET creator = COALESCE(
+	(SELECT history.creator FROM history
+		WHERE history.list = lists_content.list AND history.name = lists_content.name::citext
+		ORDER BY history.last_used DESC
+		LIMIT 1),
+	(SELECT owner FROM lists WHERE lists.id = lists_content.list)
+);
+
+ALTER TABLE lists_content ALTER COLUMN creator SET NOT NULL;
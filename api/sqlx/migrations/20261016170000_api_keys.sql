@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE api_keys (
+	id uuid PRIMARY KEY DEFAULT uuid_generate_v4(),
+	account uuid NOT NULL REFERENCES accounts(id),
+	name TEXT NOT NULL,
+	scope TEXT NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT now()
+);
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE pantry_content ADD COLUMN unit TEXT;
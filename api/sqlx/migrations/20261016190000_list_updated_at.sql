@@ -0,0 +1,28 @@
+-- Add migration script here
+ALTER TABLE lists ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now();
+
+CREATE FUNCTION touch_list() RETURNS trigger AS $$
+BEGIN
+	IF TG_OP = 'DELETE' THEN
+		UPDATE lists SET updated_at = now() WHERE id = OLD.list;
+		RETURN OLD;
+	END IF;
+	UPDATE lists SET updated_at = now() WHERE id = NEW.list;
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER lists_content_touch_list
+	AFTER INSERT OR UPDATE OR DELETE ON lists_content
+	FOR EACH ROW EXECUTE FUNCTION touch_list();
+
+CREATE FUNCTION touch_updated_at() RETURNS trigger AS $$
+BEGIN
+	NEW.updated_at = now();
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER lists_touch_updated_at
+	BEFORE UPDATE ON lists
+	FOR EACH ROW EXECUTE FUNCTION touch_updated_at();
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE lists ADD COLUMN allowed_units TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE lists_content ADD COLUMN unit TEXT;
@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE share_links (
+    token UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    list UUID NOT NULL REFERENCES lists(id),
+    readonly BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
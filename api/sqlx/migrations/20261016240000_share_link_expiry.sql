@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE share_links ADD COLUMN expires_at TIMESTAMPTZ;
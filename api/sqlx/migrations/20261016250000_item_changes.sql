@@ -0,0 +1,72 @@
+-- Add migration script here
+ALTER TABLE lists_content ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now();
+
+CREATE TRIGGER lists_content_touch_updated_at
+	BEFORE UPDATE ON lists_content
+	FOR EACH ROW EXECUTE FUNCTION touch_updated_at();
+
+-- Every change to the items of a list gets the next value of the list counter. Taking it locks
+-- the list row until the change commits, so changes of a list commit in the order of their
+-- counter, and a reader seeing `change_seq = n` also sees every change up to n.
+ALTER TABLE lists ADD COLUMN change_seq BIGINT NOT NULL DEFAULT 0;
+-- Highest counter of the deleted items that are no longer remembered
+ALTER TABLE lists ADD COLUMN pruned_seq BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE lists_content ADD COLUMN change_seq BIGINT NOT NULL DEFAULT 0;
+
+CREATE INDEX lists_content_changes ON lists_content (list, change_seq);
+
+CREATE FUNCTION next_change_seq(list_id UUID) RETURNS BIGINT AS $$
+	UPDATE lists SET change_seq = change_seq + 1 WHERE id = list_id RETURNING change_seq;
+$$ LANGUAGE sql;
+
+-- Tombstones of deleted items, so that clients syncing a list can remove them
+CREATE TABLE deleted_items (
+	id INTEGER NOT NULL,
+	external_id UUID NOT NULL,
+	list UUID NOT NULL REFERENCES lists(id),
+	change_seq BIGINT NOT NULL,
+	deleted_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX deleted_items_list ON deleted_items (list, change_seq);
+
+CREATE FUNCTION record_deleted_item() RETURNS trigger AS $$
+DECLARE
+	pruned BIGINT;
+BEGIN
+	IF OLD.list IS NOT NULL THEN
+		-- Tombstones are only kept for 30 days, clients syncing from further back must read the
+		-- whole list again
+		WITH expired AS (
+			DELETE FROM deleted_items
+				WHERE list = OLD.list AND deleted_at < now() - interval '30 days'
+				RETURNING change_seq
+		)
+		SELECT max(change_seq) INTO pruned FROM expired;
+		IF pruned IS NOT NULL THEN
+			UPDATE lists SET pruned_seq = GREATEST(pruned_seq, pruned) WHERE id = OLD.list;
+		END IF;
+
+		INSERT INTO deleted_items (id, external_id, list, change_seq)
+			VALUES (OLD.id, OLD.external_id, OLD.list, next_change_seq(OLD.list));
+	END IF;
+	RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER lists_content_record_deleted
+	AFTER DELETE ON lists_content
+	FOR EACH ROW EXECUTE FUNCTION record_deleted_item();
+
+CREATE FUNCTION stamp_item_change() RETURNS trigger AS $$
+BEGIN
+	IF NEW.list IS NOT NULL THEN
+		NEW.change_seq = next_change_seq(NEW.list);
+	END IF;
+	RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER lists_content_stamp_change
+	BEFORE INSERT OR UPDATE ON lists_content
+	FOR EACH ROW EXECUTE FUNCTION stamp_item_change();
@@ -0,0 +1,19 @@
+-- Add migration script here
+ALTER TABLE pantry_content
+	ALTER COLUMN target TYPE NUMERIC,
+	ALTER COLUMN amount TYPE NUMERIC,
+	ALTER COLUMN min TYPE NUMERIC;
+
+-- Like convert_to_integer, returns NULL for amounts that are not finite numbers ("a handful")
+CREATE FUNCTION convert_to_numeric(v_input text) RETURNS NUMERIC AS $$
+DECLARE v_value NUMERIC;
+BEGIN
+	v_value := v_input::NUMERIC;
+	IF v_value IN ('NaN', 'Infinity', '-Infinity') THEN
+		RETURN NULL;
+	END IF;
+	RETURN v_value;
+EXCEPTION WHEN OTHERS THEN
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
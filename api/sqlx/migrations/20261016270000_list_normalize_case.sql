@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE lists ADD COLUMN normalize_case TEXT NOT NULL DEFAULT 'none'
+	CHECK (normalize_case IN ('none', 'lower', 'title_case'));
@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE share_contacts (
+	owner UUID NOT NULL REFERENCES accounts(id),
+	account UUID NOT NULL REFERENCES accounts(id),
+	PRIMARY KEY (owner, account)
+);
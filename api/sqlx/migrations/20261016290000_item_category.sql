@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE lists_content ADD COLUMN category TEXT;
+ALTER TABLE lists ADD COLUMN aisle_order TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,28 @@
+-- Add migration script here
+-- Usernames must be unique once normalized with NFKC and lowercased, so that lookalike names are
+-- rejected. lower() is not a full Unicode case fold ("ß" and "ss" still differ), PostgreSQL has
+-- none before casefold() in version 18.
+--
+-- Accounts whose names already collide are not renamed behind their owners' backs, who would no
+-- longer be able to log in with the name they know. The migration fails instead, listing them, so
+-- that they can be renamed before running it again.
+DO $$
+DECLARE colliding TEXT;
+BEGIN
+	SELECT string_agg(format('%L (%s)', name, id), ', ' ORDER BY name_key, name)
+		INTO colliding
+		FROM (SELECT id, name, lower(normalize(name::text, NFKC)) AS name_key,
+				count(*) OVER (PARTITION BY lower(normalize(name::text, NFKC))) AS sharing
+			FROM accounts) keyed
+		WHERE sharing > 1;
+
+	IF colliding IS NOT NULL THEN
+		RAISE EXCEPTION 'Usernames collide once normalized and lowercased: %', colliding
+			USING HINT = 'Rename all but one account of each group of lookalike names, then migrate again.';
+	END IF;
+END;
+$$;
+
+ALTER TABLE accounts ADD COLUMN name_key TEXT NOT NULL
+	GENERATED ALWAYS AS (lower(normalize(name::text, NFKC))) STORED;
+ALTER TABLE accounts ADD UNIQUE(name_key);
@@ -0,0 +1,10 @@
+-- Add migration script here
+-- A pantry holds a single item of each name, ignoring case. Items whose names already collide
+-- keep working: all of them but the oldest get their id appended to their name.
+UPDATE pantry_content SET name = name || ' (' || item || ')'
+	WHERE EXISTS(SELECT 1 FROM pantry_content other
+	             WHERE other.list = pantry_content.list
+	                 AND lower(other.name) = lower(pantry_content.name)
+	                 AND other.item < pantry_content.item);
+
+CREATE UNIQUE INDEX pantry_content_name ON pantry_content (list, lower(name));
@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE lists_content ADD COLUMN created_at timestamptz;
+-- The creation time of existing items is unknown, their last update is the closest value
+UPDATE lists_content SET created_at = updated_at;
+ALTER TABLE lists_content ALTER COLUMN created_at SET NOT NULL;
+ALTER TABLE lists_content ALTER COLUMN created_at SET DEFAULT now();
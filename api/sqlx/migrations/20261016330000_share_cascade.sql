@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Deleting an account drops the shares and contacts involving it. Its lists, the items it created
+-- and its history still reference it without cascading, so those have to be reassigned or deleted
+-- before the account can be.
+ALTER TABLE list_sharing DROP CONSTRAINT list_sharing_shared_fkey;
+ALTER TABLE list_sharing ADD CONSTRAINT list_sharing_shared_fkey
+	FOREIGN KEY (shared) REFERENCES accounts(id) ON DELETE CASCADE;
+
+ALTER TABLE share_contacts DROP CONSTRAINT share_contacts_owner_fkey;
+ALTER TABLE share_contacts ADD CONSTRAINT share_contacts_owner_fkey
+	FOREIGN KEY (owner) REFERENCES accounts(id) ON DELETE CASCADE;
+ALTER TABLE share_contacts DROP CONSTRAINT share_contacts_account_fkey;
+ALTER TABLE share_contacts ADD CONSTRAINT share_contacts_account_fkey
+	FOREIGN KEY (account) REFERENCES accounts(id) ON DELETE CASCADE;
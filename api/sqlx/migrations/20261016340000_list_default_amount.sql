@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE lists ADD COLUMN default_amount TEXT;
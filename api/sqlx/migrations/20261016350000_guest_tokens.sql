@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE guest_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    list UUID NOT NULL REFERENCES lists(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
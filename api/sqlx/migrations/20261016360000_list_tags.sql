@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE list_tags (
+    list UUID NOT NULL REFERENCES lists(id),
+    tag TEXT NOT NULL,
+    PRIMARY KEY (list, tag)
+);
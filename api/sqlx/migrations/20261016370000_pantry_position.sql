@@ -0,0 +1,9 @@
+-- Add migration script here
+ALTER TABLE pantry_content
+	ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+UPDATE pantry_content
+	SET position = ordered.position
+	FROM (SELECT item, (row_number() OVER (PARTITION BY list ORDER BY item) - 1) AS position
+	      FROM pantry_content) AS ordered
+	WHERE pantry_content.item = ordered.item;
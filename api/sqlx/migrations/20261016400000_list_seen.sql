@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE list_seen (
+    account UUID NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    list UUID NOT NULL REFERENCES lists(id),
+    seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (account, list)
+);
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE lists_content ADD COLUMN maybe BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,9 @@
+-- Add migration script here
+-- Lists created concurrently could share a name, suffix the later ones with their ID so the index can
+-- be built: a counter could collide with a list already named like "Groceries (1)"
+UPDATE lists SET name = lists.name || ' (' || lists.id || ')'
+	FROM (SELECT id, row_number() OVER (PARTITION BY owner, lower(name) ORDER BY created_at, id) - 1 AS n
+	      FROM lists) duplicates
+	WHERE lists.id = duplicates.id AND duplicates.n > 0;
+
+CREATE UNIQUE INDEX lists_owner_name ON lists (owner, lower(name));
@@ -1,28 +1,147 @@
 use std::{sync::Arc, time::Duration};
 
 use axum::{
-    extract::{self, FromRequestParts},
-    http::request::Parts,
-    routing::{get, post},
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, HeaderValue},
+    routing::{delete, get, post},
     Json, Router,
 };
 use axum_extra::{
-    headers::{authorization::Bearer, Authorization},
+    headers::{authorization::Bearer, Authorization, Cookie},
     TypedHeader,
 };
-use jwt_simple::prelude::{Claims, MACLike, NoCustomClaims};
+use chrono::{DateTime, Utc};
+use jwt_simple::prelude::{Claims, JWTClaims, MACLike};
 use kabalist_types::{
-    GetAccountNameResponse, LoginRequest, LoginResponse, RecoverPasswordRequest,
-    RecoverPasswordResponse, RecoveryInfoResponse, RegisterRequest, RegisterResponse,
+    AccountExportResponse, AffectedResponse, CreateApiKeyRequest, CreateApiKeyResponse,
+    CreateGuestTokenResponse, ExportedHistoryEntry, ExportedList, GetAccountNameResponse,
+    GetPublicListsResponse, GetSharedListsResponse, LoginProfile, LoginRequest, LoginResponse,
+    PublicList, RecoverPasswordRequest, RecoverPasswordResponse, RecoveryInfoResponse,
+    RegisterRequest, RegisterResponse, RevokeGuestTokenResponse, SharedList, TokenScope,
+    ValidateTokenRequest, ValidateTokenResponse,
 };
+use serde::{Deserialize, Serialize};
 use tokio_stream::StreamExt;
 use uuid::Uuid;
 
-use crate::{ok_response::*, ErrResponse, Error, KabalistState, OkResponse, Rsp, State};
+use crate::{
+    is_owner,
+    list::{fetch_lists, WriteCaller},
+    ok_response::*,
+    ErrResponse, Error, KabalistState, OkResponse, Path, Rsp, State,
+};
+
+/// Name of the cookie carrying the token when `session_cookie` is enabled
+const SESSION_COOKIE: &str = "kabalist_token";
 
+/// Account making the request. Guest tokens are not accounts and are rejected, see [`ListUser`]
 #[derive(Debug)]
 pub(crate) struct User {
     pub id: Uuid,
+    pub scope: TokenScope,
+}
+
+/// Caller of a route reading a single list, which guest tokens can also use as `check_list` only
+/// lets them read their own list
+#[derive(Debug)]
+pub(crate) struct ListUser(pub User);
+
+impl User {
+    fn from_claims(claims: JWTClaims<TokenClaims>) -> Self {
+        User {
+            /* We control the subject, so we are sure that we set it to an uuid */
+            id: claims.subject.unwrap().parse().unwrap(),
+            scope: claims.custom.scope.unwrap_or_default(),
+        }
+    }
+
+    /// Rejects tokens that are not allowed to modify anything
+    pub(crate) fn require_write(&self) -> Result<(), Error> {
+        match self.scope {
+            TokenScope::Full => Ok(()),
+            TokenScope::ReadLists => Err(Error::InsufficientScope),
+        }
+    }
+}
+
+/// Claims added to API keys, tokens from login don't have any
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct TokenClaims {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scope: Option<TokenScope>,
+    /// Set on guest tokens, which are bound to this list and have their own id as subject
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    guest_list: Option<Uuid>,
+}
+
+fn verify_token(state: &KabalistState, token: &str) -> Result<JWTClaims<TokenClaims>, Error> {
+    Ok(state
+        .config
+        .jwt_secret
+        .0
+        .verify_token::<TokenClaims>(token, None)?)
+}
+
+/// API keys and guest tokens carry their id as the JWT id, and are only valid as long as they are
+/// in the database
+async fn check_api_key(
+    state: &KabalistState,
+    claims: &JWTClaims<TokenClaims>,
+) -> Result<(), Error> {
+    let Some(key) = &claims.jwt_id else {
+        return Ok(());
+    };
+    let key: Uuid = key.parse().map_err(|_| Error::InvalidToken)?;
+    /* We control the subject, so we are sure that we set it to an uuid */
+    let account: Uuid = claims.subject.as_ref().unwrap().parse().unwrap();
+
+    let exists = match claims.custom.guest_list {
+        Some(list) => sqlx::query!(
+            r#"SELECT EXISTS(SELECT 1 FROM guest_tokens WHERE id = $1 AND list = $2) as "exists!""#,
+            key,
+            list
+        )
+        .fetch_one(&state.pool)
+        .await?
+        .exists,
+        None => sqlx::query!(
+            r#"SELECT EXISTS(SELECT 1 FROM api_keys WHERE id = $1 AND account = $2) as "exists!""#,
+            key,
+            account
+        )
+        .fetch_one(&state.pool)
+        .await?
+        .exists,
+    };
+
+    if exists {
+        Ok(())
+    } else {
+        Err(Error::InvalidToken)
+    }
+}
+
+/// Claims of the token given with the request, either as the bearer token or in the session cookie
+async fn request_claims(
+    parts: &mut Parts,
+    state: &Arc<KabalistState>,
+) -> Result<JWTClaims<TokenClaims>, Error> {
+    let token = match TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state).await {
+        Ok(TypedHeader(Authorization(bearer))) => bearer.token().to_owned(),
+        Err(_) if state.config.session_cookie => {
+            TypedHeader::<Cookie>::from_request_parts(parts, state)
+                .await
+                .ok()
+                .and_then(|TypedHeader(cookie)| cookie.get(SESSION_COOKIE).map(str::to_owned))
+                .ok_or(Error::MissingAuthorization)?
+        }
+        Err(_) => return Err(Error::MissingAuthorization),
+    };
+
+    let claims = verify_token(state, &token)?;
+    check_api_key(state, &claims).await?;
+
+    Ok(claims)
 }
 
 impl FromRequestParts<Arc<KabalistState>> for User {
@@ -32,30 +151,45 @@ impl FromRequestParts<Arc<KabalistState>> for User {
         parts: &mut Parts,
         state: &Arc<KabalistState>,
     ) -> Result<Self, Self::Rejection> {
-        let TypedHeader(Authorization(bearer)) =
-            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
-                .await
-                .map_err(|_| Error::MissingAuthorization)?;
+        let claims = request_claims(parts, state).await?;
+        if claims.custom.guest_list.is_some() {
+            return Err(Error::InsufficientScope);
+        }
 
-        let claims = state
-            .config
-            .jwt_secret
-            .0
-            .verify_token::<NoCustomClaims>(bearer.token(), None)?;
+        let user = User::from_claims(claims);
+        if let Some(caller) = parts.extensions.get::<WriteCaller>() {
+            caller.record(user.id);
+        }
 
-        Ok(User {
-            /* We control the subject, so we are sure that we set it to an uuid */
-            id: claims.subject.unwrap().parse().unwrap(),
-        })
+        Ok(user)
+    }
+}
+
+impl FromRequestParts<Arc<KabalistState>> for ListUser {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<KabalistState>,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(ListUser(User::from_claims(
+            request_claims(parts, state).await?,
+        )))
     }
 }
 
 pub(crate) fn router() -> Router<Arc<KabalistState>> {
     Router::new()
         .route("/login", post(login))
+        .route("/validate", post(validate_token))
+        .route("/api-keys", post(create_api_key))
+        .route("/shares", delete(leave_shares))
         .route("/register/{id}", post(register))
         .route("/recover/{id}", get(recovery_info).post(recover_password))
         .route("/{id}/name", get(get_account_name))
+        .route("/{id}/shared-lists", get(get_shared_lists))
+        .route("/public-lists", get(get_public_lists))
+        .route("/export", get(export_account))
 }
 
 /// Generate a JWT in order to use the other routes
@@ -70,26 +204,203 @@ pub(crate) fn router() -> Router<Arc<KabalistState>> {
     request_body = LoginRequest,
 )]
 #[tracing::instrument(skip(state))]
-async fn login(state: State, Json(request): Json<LoginRequest>) -> Rsp<LoginResponse> {
+async fn login(
+    state: State,
+    Json(request): Json<LoginRequest>,
+) -> Result<(HeaderMap, Json<OkLoginResponse>), Error> {
     let mut rsp = sqlx::query!(
-        "SELECT id FROM accounts WHERE name = $1::text::citext AND password = crypt($2, password)",
+        r#"SELECT id, name::text as "name!" FROM accounts
+               WHERE name_key = lower(normalize($1, NFKC)) AND password = crypt($2, password)"#,
         request.username,
         request.password.0,
     )
     .fetch(&state.0.pool);
 
-    let id = match rsp.next().await {
+    let (id, username) = match rsp.next().await {
         None => return Err(Error::UnknownAccount),
         Some(Err(e)) => return Err(e.into()),
-        Some(Ok(id)) => id.id,
+        Some(Ok(row)) => (row.id, row.name),
     };
+    // Release the connection before fetching the lists
+    drop(rsp);
 
     let mut claims = Claims::create(Duration::from_millis(state.0.config.exp as _).into());
     claims.subject = Some(id.to_string());
 
     let token = state.0.config.jwt_secret.0.authenticate(claims)?;
 
-    OkResponse::ok(LoginResponse { token })
+    let mut headers = HeaderMap::new();
+    if state.0.config.session_cookie {
+        let cookie = format!(
+            "{SESSION_COOKIE}={token}; Max-Age={}; Path=/api; Secure; HttpOnly; SameSite=Strict",
+            state.0.config.exp / 1000
+        );
+        headers.insert(
+            header::SET_COOKIE,
+            HeaderValue::from_str(&cookie).map_err(|_| Error::Internal)?,
+        );
+    }
+
+    let profile = match request.full {
+        true => Some(LoginProfile {
+            id,
+            username,
+            lists: fetch_lists(&state.0.pool, id, &Default::default()).await?,
+        }),
+        false => None,
+    };
+
+    Ok((headers, OkResponse::ok(LoginResponse { token, profile })?))
+}
+
+/// Check a JWT, either given in the body or as the bearer token
+#[utoipa::path(
+    post,
+    path = "/api/account/validate",
+    responses(
+        (status = 200, description = "Token Status", body = OkValidateTokenResponse),
+        (status = 400, description = "Missing Token", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = ValidateTokenRequest,
+)]
+#[tracing::instrument(skip(state, bearer))]
+async fn validate_token(
+    state: State,
+    bearer: Option<TypedHeader<Authorization<Bearer>>>,
+    request: Option<Json<ValidateTokenRequest>>,
+) -> Rsp<ValidateTokenResponse> {
+    let token = match (&request, &bearer) {
+        (Some(Json(request)), _) => request.token.as_str(),
+        (None, Some(TypedHeader(Authorization(bearer)))) => bearer.token(),
+        (None, None) => return Err(Error::MissingAuthorization),
+    };
+
+    let claims = match verify_token(&state.0, token) {
+        Ok(claims) => match check_api_key(&state.0, &claims).await {
+            Ok(()) => Some(claims),
+            Err(Error::InvalidToken) => None,
+            Err(e) => return Err(e),
+        },
+        Err(_) => None,
+    };
+
+    let response = match claims {
+        Some(claims) => ValidateTokenResponse {
+            valid: true,
+            // The subject of guest tokens is not an account
+            account: match claims.custom.guest_list {
+                Some(_) => None,
+                None => claims.subject.and_then(|subject| subject.parse().ok()),
+            },
+            expires_at: claims
+                .expires_at
+                .and_then(|exp| DateTime::<Utc>::from_timestamp(exp.as_secs() as i64, 0)),
+            scope: Some(claims.custom.scope.unwrap_or_default()),
+        },
+        None => ValidateTokenResponse {
+            valid: false,
+            account: None,
+            expires_at: None,
+            scope: None,
+        },
+    };
+
+    OkResponse::ok(response)
+}
+
+/// Generate a token with a limited scope, for use by integrations
+#[utoipa::path(
+    post,
+    path = "/api/account/api-keys",
+    responses(
+        (status = 200, description = "API Key", body = OkCreateApiKeyResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = CreateApiKeyRequest,
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn create_api_key(
+    state: State,
+    user: User,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Rsp<CreateApiKeyResponse> {
+    // A scoped key must not be able to mint keys with more rights than itself
+    user.require_write()?;
+
+    let id = sqlx::query!(
+        "INSERT INTO api_keys (account, name, scope) VALUES ($1, $2, $3) RETURNING id",
+        user.id,
+        request.name,
+        request.scope.as_str(),
+    )
+    .fetch_one(&state.0.pool)
+    .await?
+    .id;
+
+    let claims = Claims::with_custom_claims(
+        TokenClaims {
+            scope: Some(request.scope),
+            guest_list: None,
+        },
+        Duration::from_millis(state.0.config.exp as _).into(),
+    )
+    .with_subject(user.id)
+    .with_jwt_id(id);
+
+    let token = state.0.config.jwt_secret.0.authenticate(claims)?;
+
+    OkResponse::ok(CreateApiKeyResponse { id, token })
+}
+
+/// Stop having access to every list that was shared with the account
+#[utoipa::path(
+    delete,
+    path = "/api/account/shares",
+    responses(
+        (status = 200, description = "Number of shares removed", body = OkAffectedResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn leave_shares(state: State, user: User) -> Rsp<AffectedResponse> {
+    user.require_write()?;
+
+    let removed = sqlx::query!("DELETE FROM list_sharing WHERE shared = $1", user.id)
+        .execute(&state.0.pool)
+        .await?
+        .rows_affected();
+
+    OkResponse::ok(AffectedResponse { affected: removed })
+}
+
+/// Inserts a new account, returning its ID. The username is kept as given for display, but must
+/// be unique once normalized (NFKC and lowercase) so that lookalike names are rejected
+pub(crate) async fn create_account(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    req: &RegisterRequest,
+) -> Result<Uuid, Error> {
+    let account = sqlx::query!(
+        r#"INSERT INTO accounts (id, name, password)
+               VALUES (uuid_generate_v4(), $1::text::citext, crypt($2, gen_salt('bf')))
+               ON CONFLICT DO NOTHING
+               RETURNING id"#,
+        req.username,
+        req.password
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or(Error::UsernameTaken)?;
+
+    Ok(account.id)
 }
 
 #[utoipa::path(
@@ -97,7 +408,9 @@ async fn login(state: State, Json(request): Json<LoginRequest>) -> Rsp<LoginResp
     path = "/api/account/register/{id}",
     responses(
         (status = 200, description = "Register Information", body = OkRegisterResponse),
+        (status = 403, description = "Registration Disabled", body = ErrResponse),
         (status = 404, description = "Unknown Account", body = ErrResponse),
+        (status = 409, description = "Username Taken", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
     params(
@@ -108,9 +421,13 @@ async fn login(state: State, Json(request): Json<LoginRequest>) -> Rsp<LoginResp
 #[tracing::instrument(skip(state))]
 async fn register(
     state: State,
-    extract::Path(id): extract::Path<Uuid>,
+    Path(id): Path<Uuid>,
     Json(req): Json<RegisterRequest>,
 ) -> Rsp<RegisterResponse> {
+    if !state.0.config.registration_enabled {
+        return Err(Error::RegistrationDisabled);
+    }
+
     let mut tx = state.0.pool.begin().await?;
 
     let mut is_registered =
@@ -122,14 +439,7 @@ async fn register(
     }
     drop(is_registered);
 
-    sqlx::query!(
-        r#"INSERT INTO accounts (id, name, password)
-               VALUES (uuid_generate_v4(), $1::text::citext, crypt($2, gen_salt('bf')))"#,
-        req.username,
-        req.password
-    )
-    .execute(&mut *tx)
-    .await?;
+    create_account(&mut tx, &req).await?;
 
     sqlx::query!("DELETE FROM registrations WHERE id = $1", id)
         .execute(&mut *tx)
@@ -153,10 +463,7 @@ async fn register(
     ),
 )]
 #[tracing::instrument(skip(state))]
-async fn recovery_info(
-    state: State,
-    extract::Path(id): extract::Path<Uuid>,
-) -> Rsp<RecoveryInfoResponse> {
+async fn recovery_info(state: State, Path(id): Path<Uuid>) -> Rsp<RecoveryInfoResponse> {
     let username = sqlx::query!(
         r#"SELECT accounts.name::text
                FROM password_reset,accounts
@@ -189,7 +496,7 @@ async fn recovery_info(
 )]
 async fn recover_password(
     state: State,
-    extract::Path(id): extract::Path<Uuid>,
+    Path(id): Path<Uuid>,
     Json(request): Json<RecoverPasswordRequest>,
 ) -> Rsp<RecoverPasswordResponse> {
     let mut tx = state.0.pool.begin().await?;
@@ -236,7 +543,7 @@ async fn recover_password(
 async fn get_account_name(
     state: State,
     _user: User,
-    extract::Path(id): extract::Path<Uuid>,
+    Path(id): Path<Uuid>,
 ) -> Rsp<GetAccountNameResponse> {
     let name = sqlx::query!("SELECT name::text FROM accounts WHERE id = $1", id)
         .fetch_one(&state.0.pool)
@@ -248,3 +555,710 @@ async fn get_account_name(
         None => Err(Error::AccountNotFound),
     }
 }
+
+/// Lists owned by the caller that are already shared with the account
+#[utoipa::path(
+    get,
+    path = "/api/account/{id}/shared-lists",
+    responses(
+        (status = 200, description = "Shared Lists", body = OkGetSharedListsResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "Account ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn get_shared_lists(
+    state: State,
+    user: User,
+    Path(id): Path<Uuid>,
+) -> Rsp<GetSharedListsResponse> {
+    let lists = sqlx::query!(
+        r#"SELECT lists.id, lists.name, list_sharing.readonly
+               FROM lists
+               JOIN list_sharing ON list_sharing.list = lists.id
+               WHERE lists.owner = $1 AND list_sharing.shared = $2
+               ORDER BY lists.name"#,
+        user.id,
+        id
+    )
+    .fetch_all(&state.0.pool)
+    .await?
+    .into_iter()
+    .map(|row| SharedList {
+        id: row.id,
+        name: row.name,
+        readonly: row.readonly,
+    })
+    .collect();
+
+    OkResponse::ok(GetSharedListsResponse { lists })
+}
+
+/// Lists owned by the caller that anyone can read through their public page
+#[utoipa::path(
+    get,
+    path = "/api/account/public-lists",
+    responses(
+        (status = 200, description = "Public Lists", body = OkGetPublicListsResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn get_public_lists(state: State, user: User) -> Rsp<GetPublicListsResponse> {
+    let lists = sqlx::query!(
+        "SELECT id, name FROM lists WHERE owner = $1 AND pub ORDER BY name",
+        user.id
+    )
+    .fetch_all(&state.0.pool)
+    .await?
+    .into_iter()
+    .map(|row| PublicList {
+        url: format!("/api/list/{}/public", row.id),
+        id: row.id,
+        name: row.name,
+    })
+    .collect();
+
+    OkResponse::ok(GetPublicListsResponse { lists })
+}
+
+/// Everything stored for the caller: their lists with items, pantry and shares, the lists shared
+/// with them, and their history
+#[utoipa::path(
+    get,
+    path = "/api/account/export",
+    responses(
+        (status = 200, description = "Account Data", body = OkAccountExportResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn export_account(state: State, user: User) -> Rsp<AccountExportResponse> {
+    let db = &state.0.pool;
+
+    let username = sqlx::query!(
+        r#"SELECT name::text as "name!" FROM accounts WHERE id = $1"#,
+        user.id
+    )
+    .fetch_one(db)
+    .await?
+    .name;
+
+    let owned = sqlx::query!(
+        r#"SELECT id, name, description, pub as "public", archived, created_at
+               FROM lists
+               WHERE owner = $1
+               ORDER BY name"#,
+        user.id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut lists = Vec::with_capacity(owned.len());
+    for row in owned {
+        lists.push(ExportedList {
+            items: crate::list::fetch_list(db, user.id, row.id, false, None, None, None)
+                .await?
+                .items,
+            pantry: crate::pantry::pantry_items(db, row.id, false).await?,
+            shared_with: crate::share::list_shares(db, row.id).await?,
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            public: row.public.unwrap_or(false),
+            archived: row.archived,
+            created_at: row.created_at,
+        });
+    }
+
+    let shared_with_me = sqlx::query!(
+        "SELECT list, readonly FROM list_sharing WHERE shared = $1",
+        user.id
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| (row.list, row.readonly))
+    .collect();
+
+    let history = sqlx::query!(
+        r#"SELECT list, name::text as "name!", amount FROM history
+               WHERE creator = $1
+               ORDER BY list, last_used DESC"#,
+        user.id
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| ExportedHistoryEntry {
+        list: row.list,
+        name: row.name,
+        amount: row.amount,
+    })
+    .collect();
+
+    OkResponse::ok(AccountExportResponse {
+        id: user.id,
+        username,
+        lists,
+        shared_with_me,
+        history,
+    })
+}
+
+/// Generate a token that can only read this list, without giving access to an account
+#[utoipa::path(
+    post,
+    path = "/api/list/{id}/guest-token",
+    responses(
+        (status = 200, description = "Guest Token", body = OkCreateGuestTokenResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn create_guest_token(
+    state: State,
+    user: User,
+    Path(list): Path<Uuid>,
+) -> Rsp<CreateGuestTokenResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, list).await?;
+
+    let id = sqlx::query!(
+        "INSERT INTO guest_tokens (list) VALUES ($1) RETURNING id",
+        list
+    )
+    .fetch_one(&state.0.pool)
+    .await?
+    .id;
+
+    // The guest is its own principal, so it gets nothing from the owner besides the list
+    let claims = Claims::with_custom_claims(
+        TokenClaims {
+            scope: Some(TokenScope::ReadLists),
+            guest_list: Some(list),
+        },
+        Duration::from_millis(state.0.config.exp as _).into(),
+    )
+    .with_subject(id)
+    .with_jwt_id(id);
+
+    let token = state.0.config.jwt_secret.0.authenticate(claims)?;
+
+    OkResponse::ok(CreateGuestTokenResponse { id, token })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/list/{id}/guest-token/{token}",
+    responses(
+        (status = 200, description = "Guest token revoked", body = OkRevokeGuestTokenResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 404, description = "No such guest token", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("token" = Uuid, Path, description = "Guest token ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn revoke_guest_token(
+    state: State,
+    user: User,
+    Path((list, token)): Path<(Uuid, Uuid)>,
+) -> Rsp<RevokeGuestTokenResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, list).await?;
+
+    let deleted = sqlx::query!(
+        "DELETE FROM guest_tokens WHERE list = $1 AND id = $2",
+        list,
+        token
+    )
+    .execute(&state.0.pool)
+    .await?
+    .rows_affected();
+
+    if deleted == 0 {
+        return Err(Error::NotFound);
+    }
+
+    OkResponse::ok(RevokeGuestTokenResponse {})
+}
+
+#[cfg(test)]
+mod tests {
+    use kabalist_client::{Client, RspData};
+    use kabalist_types::{GetListsResponse, SecretString};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::tests::{account, assert_api_error, client, spawn, state, PASSWORD};
+
+    #[sqlx::test(migrations = false)]
+    async fn validate(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let id = account(&state, "user").await;
+        let token = kabalist_client::login(&url, "user", PASSWORD)
+            .await
+            .unwrap()
+            .token;
+
+        let valid = kabalist_client::validate_token(&url, &token).await.unwrap();
+        assert!(valid.valid);
+        assert_eq!(valid.account, Some(id));
+        assert!(valid.expires_at.unwrap() > Utc::now());
+        assert_eq!(valid.scope, Some(TokenScope::Full));
+
+        let invalid = kabalist_client::validate_token(&url, "not-a-token")
+            .await
+            .unwrap();
+        assert_eq!(
+            invalid,
+            ValidateTokenResponse {
+                valid: false,
+                account: None,
+                expires_at: None,
+                scope: None,
+            }
+        );
+
+        // The token in the body takes precedence over the bearer token
+        let request = || reqwest::Client::new().post(format!("{}/api/account/validate", url));
+        let from_body: RspData<ValidateTokenResponse> = request()
+            .bearer_auth("not-a-token")
+            .json(&ValidateTokenRequest { token })
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(Result::from(from_body).unwrap().account, Some(id));
+
+        let missing: RspData<ValidateTokenResponse> =
+            request().send().await.unwrap().json().await.unwrap();
+        assert_eq!(
+            Result::from(missing).unwrap_err().code,
+            Error::MissingAuthorization as usize
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn export(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (friend_id, friend) = client(&state, &url, "friend").await;
+        let (owner_id, owner) = client(&state, &url, "owner").await;
+
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        owner.add(&list, "Eggs", Some("6")).await.unwrap();
+        owner.add(&list, "Milk", None).await.unwrap();
+        owner
+            .add_to_pantry(list, "Water".into(), 6.0)
+            .await
+            .unwrap();
+        owner.share(&list, &friend_id, true).await.unwrap();
+        let friend_list = friend.create_list("Hardware").await.unwrap().id;
+        friend.add(&friend_list, "Nails", None).await.unwrap();
+        friend.share(&friend_list, &owner_id, false).await.unwrap();
+
+        let export = owner.export_account().await.unwrap();
+        assert_eq!(export.id, owner_id);
+        assert_eq!(export.username, "owner");
+        // Only the lists of the caller are exported, the other ones only as shares
+        assert_eq!(export.lists.len(), 1);
+        let exported = &export.lists[0];
+        assert_eq!(exported.id, list);
+        assert_eq!(exported.name, "Groceries");
+        assert_eq!(
+            exported
+                .items
+                .iter()
+                .map(|item| (item.name.as_str(), item.amount.as_deref()))
+                .collect::<Vec<_>>(),
+            [("Eggs", Some("6")), ("Milk", None)]
+        );
+        assert_eq!(exported.pantry.len(), 1);
+        assert_eq!(exported.pantry[0].name, "Water");
+        assert_eq!(exported.shared_with.get(&friend_id), Some(&true));
+        assert_eq!(export.shared_with_me.get(&friend_list), Some(&false));
+        let mut history: Vec<_> = export
+            .history
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        history.sort_unstable();
+        assert_eq!(history, ["Eggs", "Milk"]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn api_key_scope(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (id, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        owner.add(&list, "Eggs", None).await.unwrap();
+
+        let key = owner
+            .create_api_key("dashboard".into(), TokenScope::ReadLists)
+            .await
+            .unwrap();
+        let validated = kabalist_client::validate_token(&url, &key.token)
+            .await
+            .unwrap();
+        assert_eq!(validated.account, Some(id));
+        assert_eq!(validated.scope, Some(TokenScope::ReadLists));
+
+        let reader = kabalist_client::Client::new(url.clone(), key.token.clone());
+        assert_eq!(reader.read(&list).await.unwrap().items.len(), 1);
+        assert_api_error(
+            reader.add(&list, "Milk", None).await,
+            Error::InsufficientScope,
+        );
+        assert_api_error(
+            reader
+                .create_api_key("escalate".into(), TokenScope::Full)
+                .await,
+            Error::InsufficientScope,
+        );
+
+        // Keys are revoked by removing them from the database
+        sqlx::query("DELETE FROM api_keys WHERE id = $1")
+            .bind(key.id)
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        assert_api_error(reader.read(&list).await, Error::InvalidToken);
+        let validated = kabalist_client::validate_token(&url, &key.token)
+            .await
+            .unwrap();
+        assert!(!validated.valid);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn leave_all_shares(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (friend, friend_client) = client(&state, &url, "friend").await;
+        let (other, other_client) = client(&state, &url, "other").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+
+        let groceries = owner.create_list("Groceries").await.unwrap().id;
+        let hardware = owner.create_list("Hardware").await.unwrap().id;
+        owner.share(&groceries, &friend, false).await.unwrap();
+        owner.share(&hardware, &friend, true).await.unwrap();
+        owner.share(&groceries, &other, true).await.unwrap();
+
+        assert_eq!(friend_client.leave_shares().await.unwrap().affected, 2);
+        assert!(friend_client.lists().await.unwrap().results.is_empty());
+        assert_eq!(friend_client.leave_shares().await.unwrap().affected, 0);
+
+        // Other members and the owner keep their access
+        assert_eq!(other_client.lists().await.unwrap().results.len(), 1);
+        assert_eq!(owner.lists().await.unwrap().results.len(), 2);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn shared_lists(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (friend, friend_client) = client(&state, &url, "friend").await;
+        let (other, other_client) = client(&state, &url, "other").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+
+        let groceries = owner.create_list("Groceries").await.unwrap().id;
+        let hardware = owner.create_list("Hardware").await.unwrap().id;
+        owner.create_list("Private").await.unwrap();
+        owner.share(&groceries, &friend, false).await.unwrap();
+        owner.share(&hardware, &friend, true).await.unwrap();
+
+        // A list shared with the account by someone else is not the caller's business
+        let party = other_client.create_list("Party").await.unwrap().id;
+        other_client.share(&party, &friend, false).await.unwrap();
+
+        assert_eq!(
+            owner.shared_lists(&friend).await.unwrap().lists,
+            vec![
+                SharedList {
+                    id: groceries,
+                    name: "Groceries".into(),
+                    readonly: false,
+                },
+                SharedList {
+                    id: hardware,
+                    name: "Hardware".into(),
+                    readonly: true,
+                },
+            ]
+        );
+        assert!(owner.shared_lists(&other).await.unwrap().lists.is_empty());
+        assert!(friend_client
+            .shared_lists(&friend)
+            .await
+            .unwrap()
+            .lists
+            .is_empty());
+    }
+
+    /// Logs in as `username` without the client, returning the session cookie if one was set
+    async fn session_cookie(url: &str, username: &str) -> Option<String> {
+        let response = reqwest::Client::new()
+            .post(format!("{}/api/account/login", url))
+            .json(&LoginRequest {
+                username: username.into(),
+                password: SecretString(PASSWORD.into()),
+                full: false,
+            })
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        let cookie = response.headers().get(header::SET_COOKIE)?;
+        Some(cookie.to_str().unwrap().to_owned())
+    }
+
+    /// Lists the lists of the account owning the cookie
+    async fn lists_with_cookie(
+        url: &str,
+        cookie: &str,
+    ) -> kabalist_client::Result<GetListsResponse> {
+        let response: RspData<GetListsResponse> = reqwest::Client::new()
+            .get(format!("{}/api/list", url))
+            .header(header::COOKIE, cookie)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        Result::from(response).map_err(Into::into)
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn cookie_session(db: PgPool) {
+        let disabled = spawn(state(db.clone()).await).await;
+        let mut state = state(db).await;
+        Arc::get_mut(&mut state).unwrap().config.session_cookie = true;
+        let url = spawn(state.clone()).await;
+        account(&state, "user").await;
+
+        assert_eq!(session_cookie(&disabled, "user").await, None);
+        let set_cookie = session_cookie(&url, "user").await.unwrap();
+        for attribute in ["Secure", "HttpOnly", "SameSite=Strict", "Path=/api"] {
+            assert!(
+                set_cookie.split("; ").any(|part| part == attribute),
+                "{}",
+                set_cookie
+            );
+        }
+
+        let cookie = set_cookie.split(';').next().unwrap();
+        assert!(cookie.starts_with("kabalist_token="));
+        assert!(lists_with_cookie(&url, cookie).await.is_ok());
+        assert_api_error(
+            lists_with_cookie(&url, "kabalist_token=not-a-token").await,
+            Error::InvalidToken,
+        );
+        // The cookie is only accepted when the setting is enabled
+        assert_api_error(
+            lists_with_cookie(&disabled, cookie).await,
+            Error::MissingAuthorization,
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn disabled_registrations(db: PgPool) {
+        let enabled = spawn(state(db.clone()).await).await;
+        let mut state = state(db).await;
+        Arc::get_mut(&mut state)
+            .unwrap()
+            .config
+            .registration_enabled = false;
+        let disabled = spawn(state.clone()).await;
+
+        let registration = Uuid::new_v4();
+        sqlx::query("INSERT INTO registrations (id) VALUES ($1)")
+            .bind(registration)
+            .execute(&state.pool)
+            .await
+            .unwrap();
+
+        assert_api_error(
+            kabalist_client::register(&disabled, registration, "user", PASSWORD).await,
+            Error::RegistrationDisabled,
+        );
+        // The registration is still there once they are enabled again
+        kabalist_client::register(&enabled, registration, "user", PASSWORD)
+            .await
+            .unwrap();
+        assert!(kabalist_client::login(&enabled, "user", PASSWORD)
+            .await
+            .is_ok());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn public_lists(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (owner_id, owner) = client(&state, &url, "owner").await;
+        let (_, friend) = client(&state, &url, "friend").await;
+
+        let groceries = owner.create_list("Groceries").await.unwrap().id;
+        owner.set_public(&groceries).await.unwrap();
+        owner.create_list("Hardware").await.unwrap();
+        // Public lists shared with the caller are not theirs to audit
+        let shared = friend.create_list("Shared").await.unwrap().id;
+        friend.set_public(&shared).await.unwrap();
+        friend.share(&shared, &owner_id, false).await.unwrap();
+
+        assert_eq!(
+            owner.public_lists().await.unwrap().lists,
+            [PublicList {
+                id: groceries,
+                name: "Groceries".into(),
+                url: format!("/api/list/{}/public", groceries),
+            }]
+        );
+
+        owner.remove_public(&groceries).await.unwrap();
+        assert!(owner.public_lists().await.unwrap().lists.is_empty());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn usernames_collide_once_normalized(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let alice = account(&state, "Alice").await;
+        let zoe = account(&state, "Zoé").await;
+
+        // Lower case, fullwidth A, and decomposed é
+        for taken in ["alice", "ALICE", "\u{ff21}lice", "Zoe\u{301}", "zoé"] {
+            let mut tx = state.pool.begin().await.unwrap();
+            let result = create_account(
+                &mut tx,
+                &RegisterRequest {
+                    username: taken.into(),
+                    password: PASSWORD.into(),
+                },
+            )
+            .await;
+            assert!(matches!(result, Err(Error::UsernameTaken)), "{}", taken);
+        }
+
+        for (name, id) in [("aLiCe", alice), ("ZOe\u{301}", zoe), ("zoé", zoe)] {
+            let token = kabalist_client::login(&url, name, PASSWORD)
+                .await
+                .unwrap()
+                .token;
+            let user = Client::new(url.clone(), token);
+            assert_eq!(user.search_account(name).await.unwrap().id, id);
+        }
+
+        let (_, bob) = client(&state, &url, "bob").await;
+        assert_eq!(bob.search_account("ALICE").await.unwrap().id, alice);
+        assert_api_error(bob.search_account("carol").await, Error::UnknownAccount);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn full_login(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (id, owner) = client(&state, &url, "Alice").await;
+        owner.create_list("Groceries").await.unwrap();
+
+        let plain = kabalist_client::login(&url, "alice", PASSWORD)
+            .await
+            .unwrap();
+        assert_eq!(plain.profile, None);
+
+        let full = kabalist_client::login_full(&url, "alice", PASSWORD)
+            .await
+            .unwrap();
+        let profile = full.profile.unwrap();
+        assert_eq!(profile.id, id);
+        assert_eq!(profile.username, "Alice");
+        assert_eq!(profile.lists, owner.lists().await.unwrap());
+
+        // The token is as usable as the one of a plain login
+        let user = Client::new(url.clone(), full.token);
+        assert_eq!(user.lists().await.unwrap(), profile.lists);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn guests_only_read_their_list(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        let other = owner.create_list("Hardware").await.unwrap().id;
+        owner.add(&list, "Eggs", None).await.unwrap();
+
+        owner.add_to_pantry(list, "Rice".into(), 2.0).await.unwrap();
+
+        let created = owner.create_guest_token(&list).await.unwrap();
+        let guest = Client::new(url.clone(), created.token);
+
+        let content = guest.read(&list).await.unwrap();
+        assert_eq!(content.items.len(), 1);
+        assert!(content.readonly);
+        let changes = guest.list_changes(&list, None).await.unwrap();
+        assert_eq!(changes.items.len(), 1);
+        assert!(!changes.items[0].editable);
+        assert_api_error(guest.read(&other).await, Error::NoSuchList);
+        assert_api_error(
+            guest.add(&list, "Milk", None).await,
+            Error::InsufficientScope,
+        );
+
+        // The guest is not an account
+        assert_api_error(guest.export_account().await, Error::InsufficientScope);
+        assert_api_error(
+            guest.search_account("owner").await,
+            Error::InsufficientScope,
+        );
+        assert_api_error(guest.lists().await, Error::InsufficientScope);
+
+        // The pantry of the list can be read, but not changed
+        assert_eq!(guest.pantry(list).await.unwrap().items[0].name, "Rice");
+        assert_eq!(guest.pantry_status(list).await.unwrap().total_items, 1);
+        assert_eq!(guest.preview_refill(list).await.unwrap().added.len(), 1);
+        assert_api_error(guest.refill_pantry(list).await, Error::InsufficientScope);
+        assert_api_error(
+            guest.add_to_pantry(list, "Salt".into(), 1.0).await,
+            Error::InsufficientScope,
+        );
+
+        owner.revoke_guest_token(&list, &created.id).await.unwrap();
+        assert_api_error(guest.read(&list).await, Error::InvalidToken);
+        assert_api_error(guest.pantry(list).await, Error::InvalidToken);
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{request::Parts, HeaderName},
+    routing::post,
+    Json, Router,
+};
+use kabalist_types::{AdminRegisterResponse, RegisterRequest};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    account::create_account, ok_response::*, ErrResponse, Error, KabalistState, OkResponse, Rsp,
+    State,
+};
+
+pub(crate) const ADMIN_KEY_HEADER: HeaderName = HeaderName::from_static("x-admin-key");
+
+/// Request authenticated by the admin key of the configuration
+#[derive(Debug)]
+pub(crate) struct Admin;
+
+/// Compares in constant time, so that the key can't be guessed from the response times. The keys
+/// are hashed first, so that the comparison doesn't stop early on their lengths either
+fn keys_match(expected: &str, provided: &[u8]) -> bool {
+    let expected = Sha256::digest(expected.as_bytes());
+    let provided = Sha256::digest(provided);
+    expected
+        .iter()
+        .zip(provided.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+impl FromRequestParts<Arc<KabalistState>> for Admin {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<KabalistState>,
+    ) -> Result<Self, Self::Rejection> {
+        // The admin routes don't exist unless a key is configured. An empty key, as given by an
+        // empty variable, counts as unset rather than letting an empty header in
+        let expected = state
+            .config
+            .admin_key
+            .as_deref()
+            .filter(|key| !key.is_empty())
+            .ok_or(Error::NotFound)?;
+
+        match parts.headers.get(ADMIN_KEY_HEADER) {
+            Some(key) if keys_match(expected, key.as_bytes()) => Ok(Admin),
+            _ => Err(Error::InvalidAdminKey),
+        }
+    }
+}
+
+pub(crate) fn router() -> Router<Arc<KabalistState>> {
+    Router::new().route("/register", post(admin_register))
+}
+
+/// Create an account without a registration, to bootstrap an instance
+#[utoipa::path(
+    post,
+    path = "/api/admin/register",
+    responses(
+        (status = 200, description = "Account Created", body = OkAdminRegisterResponse),
+        (status = 403, description = "Invalid Admin Key", body = ErrResponse),
+        (status = 404, description = "Admin Routes Disabled", body = ErrResponse),
+        (status = 409, description = "Username Taken", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = RegisterRequest,
+    security(
+        ("admin_key" = [])
+    )
+)]
+#[tracing::instrument(skip(state, req))]
+pub(crate) async fn admin_register(
+    state: State,
+    _admin: Admin,
+    Json(req): Json<RegisterRequest>,
+) -> Rsp<AdminRegisterResponse> {
+    let mut tx = state.0.pool.begin().await?;
+    let id = create_account(&mut tx, &req).await?;
+    tx.commit().await?;
+
+    OkResponse::ok(AdminRegisterResponse { id })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use kabalist_client::RspData;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::tests::{assert_api_error, spawn, state};
+
+    /// Creates `username` through the admin route, sending `key` if set
+    async fn register_with_key(
+        url: &str,
+        key: Option<&str>,
+        username: &str,
+    ) -> kabalist_client::Result<AdminRegisterResponse> {
+        let mut request = reqwest::Client::new()
+            .post(format!("{}/api/admin/register", url))
+            .json(&RegisterRequest {
+                username: username.into(),
+                password: "hunter2".into(),
+            });
+        if let Some(key) = key {
+            request = request.header(ADMIN_KEY_HEADER, key);
+        }
+        let response: RspData<AdminRegisterResponse> =
+            request.send().await.unwrap().json().await.unwrap();
+
+        Result::from(response).map_err(Into::into)
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn admin_key(db: PgPool) {
+        let disabled = spawn(state(db.clone()).await).await;
+        let mut empty = state(db.clone()).await;
+        Arc::get_mut(&mut empty).unwrap().config.admin_key = Some(String::new());
+        let empty = spawn(empty).await;
+        let mut state = state(db).await;
+        Arc::get_mut(&mut state).unwrap().config.admin_key = Some("secret".into());
+        let url = spawn(state.clone()).await;
+
+        assert_api_error(
+            register_with_key(&disabled, Some("secret"), "admin").await,
+            Error::NotFound,
+        );
+        assert_api_error(
+            register_with_key(&empty, Some(""), "admin").await,
+            Error::NotFound,
+        );
+        assert_api_error(
+            register_with_key(&url, None, "admin").await,
+            Error::InvalidAdminKey,
+        );
+        assert_api_error(
+            register_with_key(&url, Some("secreT"), "admin").await,
+            Error::InvalidAdminKey,
+        );
+
+        let created = register_with_key(&url, Some("secret"), "admin")
+            .await
+            .unwrap();
+        let id: Uuid = sqlx::query_scalar("SELECT id FROM accounts WHERE name = 'admin'")
+            .fetch_one(&state.pool)
+            .await
+            .unwrap();
+        assert_eq!(id, created.id);
+        assert!(kabalist_client::login(&url, "admin", "hunter2")
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn compare_keys() {
+        assert!(keys_match("secret", b"secret"));
+        assert!(!keys_match("secret", b"secrex"));
+        assert!(!keys_match("secret", b"secret2"));
+        assert!(!keys_match("secret", b""));
+        assert!(!keys_match("secret", b"secretsecret"));
+    }
+}
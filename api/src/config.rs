@@ -60,6 +60,9 @@ impl<'de> Deserialize<'de> for Base64 {
     }
 }
 
+pub(crate) const BOOTSTRAP_CSS_URL: &str =
+    "https://cdn.jsdelivr.net/npm/bootstrap@5.1.0/dist/css/bootstrap.min.css";
+
 #[derive(Deserialize, Debug, Serialize)]
 pub(crate) struct Config {
     pub(crate) database_url: String,
@@ -70,6 +73,27 @@ pub(crate) struct Config {
     pub(crate) cors_allow_origin: String,
     #[cfg(feature = "frontend")]
     pub(crate) frontend: Option<std::path::PathBuf>,
+    /// Title of the public list pages
+    pub(crate) public_title: String,
+    /// Stylesheet linked by the public list pages, no stylesheet is linked if empty
+    pub(crate) public_css_url: String,
+    /// CSS included directly in the public list pages
+    pub(crate) public_inline_css: Option<String>,
+    /// Maximum number of connections in the database pool
+    pub(crate) db_max_connections: u32,
+    /// Seconds to wait for a pooled connection before answering with 503
+    pub(crate) db_acquire_timeout: u64,
+    /// Seconds before an idle connection is closed, 0 keeps them open
+    pub(crate) db_idle_timeout: u64,
+    /// Maximum number of lists an account can own, unlimited if unset
+    pub(crate) max_lists_per_user: Option<i64>,
+    /// Also hand out the token as a `Secure`, `HttpOnly` cookie on login, and accept it in place
+    /// of the `Authorization` header
+    pub(crate) session_cookie: bool,
+    /// Allow accounts to be created through registrations, the admin route always works
+    pub(crate) registration_enabled: bool,
+    /// Key expected in the `X-Admin-Key` header by the admin routes, which are disabled if unset
+    pub(crate) admin_key: Option<String>,
 }
 
 impl Default for Config {
@@ -83,6 +107,16 @@ impl Default for Config {
             #[cfg(feature = "frontend")]
             frontend: None,
             cors_allow_origin: "*".into(),
+            public_title: "KabaList".into(),
+            public_css_url: BOOTSTRAP_CSS_URL.into(),
+            public_inline_css: None,
+            db_max_connections: 10,
+            db_acquire_timeout: 30,
+            db_idle_timeout: 600,
+            max_lists_per_user: None,
+            session_cookie: false,
+            registration_enabled: true,
+            admin_key: None,
         }
     }
 }
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use axum::{
+    routing::{delete, get},
+    Json, Router,
+};
+use kabalist_types::{
+    AddContactRequest, AddContactResponse, Contact, DeleteContactResponse, GetContactsResponse,
+};
+use uuid::Uuid;
+
+use crate::{
+    account::User, ok_response::*, ErrResponse, Error, KabalistState, OkResponse, Path, Rsp, State,
+};
+
+pub(crate) fn router() -> Router<Arc<KabalistState>> {
+    Router::new()
+        .route("/", get(get_contacts).post(add_contact))
+        .route("/{id}", delete(delete_contact))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/contacts",
+    responses(
+        (status = 200, description = "Accounts pinned for sharing", body = OkGetContactsResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn get_contacts(state: State, user: User) -> Rsp<GetContactsResponse> {
+    let contacts = sqlx::query!(
+        r#"SELECT share_contacts.account, accounts.name::text as "username!"
+               FROM share_contacts
+               JOIN accounts ON accounts.id = share_contacts.account
+               WHERE share_contacts.owner = $1
+               ORDER BY accounts.name"#,
+        user.id
+    )
+    .fetch_all(&state.0.pool)
+    .await?;
+
+    OkResponse::ok(GetContactsResponse {
+        contacts: contacts
+            .into_iter()
+            .map(|row| Contact {
+                account: row.account,
+                username: row.username,
+            })
+            .collect(),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/contacts",
+    responses(
+        (status = 200, description = "Pinned account", body = OkAddContactResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 404, description = "Unknown Account", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = AddContactRequest,
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn add_contact(
+    state: State,
+    user: User,
+    Json(request): Json<AddContactRequest>,
+) -> Rsp<AddContactResponse> {
+    user.require_write()?;
+
+    let username = sqlx::query!(
+        r#"SELECT name::text as "name!" FROM accounts WHERE id = $1"#,
+        request.account
+    )
+    .fetch_optional(&state.0.pool)
+    .await?
+    .ok_or(Error::AccountNotFound)?
+    .name;
+
+    sqlx::query!(
+        "INSERT INTO share_contacts (owner, account) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        user.id,
+        request.account
+    )
+    .execute(&state.0.pool)
+    .await?;
+
+    OkResponse::ok(AddContactResponse {
+        contact: Contact {
+            account: request.account,
+            username,
+        },
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/contacts/{id}",
+    responses(
+        (status = 200, description = "Account unpinned", body = OkDeleteContactResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "Account ID of the contact"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn delete_contact(
+    state: State,
+    user: User,
+    Path(id): Path<Uuid>,
+) -> Rsp<DeleteContactResponse> {
+    user.require_write()?;
+
+    sqlx::query!(
+        "DELETE FROM share_contacts WHERE owner = $1 AND account = $2",
+        user.id,
+        id
+    )
+    .execute(&state.0.pool)
+    .await?;
+
+    OkResponse::ok(DeleteContactResponse {})
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::tests::{assert_api_error, client, spawn, state};
+
+    #[sqlx::test(migrations = false)]
+    async fn contacts(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (bob, _) = client(&state, &url, "bob").await;
+        let (alice, alice_client) = client(&state, &url, "alice").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+
+        let added = owner.add_contact(bob).await.unwrap().contact;
+        assert_eq!(
+            added,
+            Contact {
+                account: bob,
+                username: "bob".into(),
+            }
+        );
+        owner.add_contact(alice).await.unwrap();
+        // Pinning an account twice keeps a single contact
+        owner.add_contact(bob).await.unwrap();
+
+        let contacts: Vec<_> = owner
+            .contacts()
+            .await
+            .unwrap()
+            .contacts
+            .into_iter()
+            .map(|contact| (contact.account, contact.username))
+            .collect();
+        assert_eq!(
+            contacts,
+            [(alice, "alice".to_string()), (bob, "bob".to_string())]
+        );
+        // Contacts are private to their owner
+        assert!(alice_client.contacts().await.unwrap().contacts.is_empty());
+
+        assert_api_error(
+            owner.add_contact(Uuid::new_v4()).await,
+            Error::AccountNotFound,
+        );
+
+        owner.delete_contact(&alice).await.unwrap();
+        let contacts = owner.contacts().await.unwrap().contacts;
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].account, bob);
+    }
+}
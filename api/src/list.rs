@@ -1,35 +1,381 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryInto,
+    sync::{Arc, OnceLock},
+    time::SystemTime,
+};
 
 use axum::{
-    extract,
-    http::StatusCode,
+    extract::{self, DefaultBodyLimit, FromRequestParts, RawPathParams},
+    http::{header, StatusCode},
+    middleware::Next,
     response::IntoResponse,
-    routing::{get, patch, post, put},
+    routing::{delete, get, patch, post, put},
     Json, Router,
 };
+use axum_extra::{
+    headers::{IfModifiedSince, LastModified},
+    TypedHeader,
+};
+use chrono::{DateTime, Utc};
 use kabalist_types::{
-    AddToListRequest, AddToListResponse, CreateListRequest, CreateListResponse, DeleteItemResponse,
-    DeleteListResponse, GetListsResponse, Item, ListInfo, ListStatus, ReadListResponse,
-    RemovePublicResponse, SetPublicResponse, UpdateItemRequest, UpdateItemResponse,
+    normalize_item_name, AddToListRequest, AddToListResponse, AffectedResponse,
+    ArchiveListResponse, BulkUpdateRequest, CaseMode, CreateListRequest, CreateListResponse,
+    DeleteItemResponse, DeleteListResponse, DeletedItem, GetListsResponse, ImportError,
+    ImportErrorReason, ImportResponse, Item, ItemFields, ItemSort, ItemStatus, ListChangesResponse,
+    ListDetail, ListInfo, ListSort, ListStatus, ListTagsResponse, MinimalItem,
+    MinimalReadListResponse, MultiReadRequest, MultiReadResponse, PositionRequest,
+    PositionResponse, ReadListResponse, ReceiptItem, ReceiptResponse, RemovePublicResponse,
+    SearchItemsResponse, SetPublicResponse, ToPantryRequest, ToPantryResponse,
+    UnarchiveListResponse, UnseenList, UnseenResponse, UpdateItemRequest, UpdateItemResponse,
+    UpdateListRequest, UpdateListResponse, MAX_IMPORT_ERRORS,
 };
-use maud::Markup;
-use tokio_stream::StreamExt;
+use serde::Deserialize;
+use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::{
-    check_list, is_owner, ok_response::*, ErrResponse, Error, KabalistState, OkResponse, Rsp,
-    State, User,
+    account, check_item_id, check_list, config::BOOTSTRAP_CSS_URL, created, is_owner,
+    ok_response::*, share, Created, ErrResponse, Error, KabalistState, ListUser, OkResponse, Path,
+    Rsp, State, User,
+};
+
+/// Maximum number of characters in a list name
+const MAX_LIST_NAME_LENGTH: usize = 100;
+/// Maximum number of characters in a tag
+const MAX_TAG_LENGTH: usize = 32;
+
+/// Tags are compared without case, so they are stored in lower case
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// Spacing between the positions of appended items, or of items moved to an end of the list, leaving
+/// room to later place an item between two others without renumbering the list
+pub(crate) const POSITION_GAP: i32 = 1024;
+
+/// Table ordered by a `position` column within each list, see [`move_to`]
+#[derive(Clone, Copy)]
+pub(crate) struct Positioned {
+    pub table: &'static str,
+    /// Column identifying the rows of `table`
+    pub id: &'static str,
+    /// Returned when the item or the reference is not in the list
+    pub not_found: Error,
+}
+
+const LIST_ITEMS: Positioned = Positioned {
+    table: "lists_content",
+    id: "id",
+    not_found: Error::ItemNotFound,
 };
 
+/// Moves `item` next to the item referenced by `request`, returning its new position. Tables
+/// can't be bound as parameters, so the queries are built from the static names in `table`
+pub(crate) async fn move_to(
+    db: &PgPool,
+    table: Positioned,
+    list: Uuid,
+    item: i32,
+    request: PositionRequest,
+) -> Result<i32, Error> {
+    let (reference, before) = match (request.before, request.after) {
+        (Some(before), None) => (before, true),
+        (None, Some(after)) => (after, false),
+        _ => return Err(Error::InvalidPosition),
+    };
+    if reference == item {
+        return Err(Error::InvalidPosition);
+    }
+
+    let Positioned {
+        table,
+        id,
+        not_found,
+    } = table;
+
+    let mut tx = db.begin().await?;
+
+    let reference: i32 = sqlx::query_scalar(&format!(
+        "SELECT position FROM {table} WHERE list = $1 AND {id} = $2"
+    ))
+    .bind(list)
+    .bind(reference)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(not_found)?;
+
+    // The item is placed between `low` and `high`, which are the positions of the reference and
+    // of its neighbour on the requested side
+    let (low, high) = if before {
+        let previous: Option<i32> = sqlx::query_scalar(&format!(
+            "SELECT MAX(position) FROM {table} WHERE list = $1 AND position < $2 AND {id} <> $3"
+        ))
+        .bind(list)
+        .bind(reference)
+        .bind(item)
+        .fetch_one(&mut *tx)
+        .await?;
+        (previous, Some(reference))
+    } else {
+        let next: Option<i32> = sqlx::query_scalar(&format!(
+            "SELECT MIN(position) FROM {table} WHERE list = $1 AND position > $2 AND {id} <> $3"
+        ))
+        .bind(list)
+        .bind(reference)
+        .bind(item)
+        .fetch_one(&mut *tx)
+        .await?;
+        (Some(reference), next)
+    };
+
+    let position = match (low, high) {
+        (None, Some(high)) => high - POSITION_GAP,
+        (Some(low), None) => low + POSITION_GAP,
+        (Some(low), Some(high)) if high - low > 1 => low + (high - low) / 2,
+        (Some(low), Some(high)) => {
+            // No room left between the two neighbours, push everything after them further
+            sqlx::query(&format!(
+                "UPDATE {table} SET position = position + $1
+                     WHERE list = $2 AND position >= $3 AND {id} <> $4"
+            ))
+            .bind(POSITION_GAP)
+            .bind(list)
+            .bind(high)
+            .bind(item)
+            .execute(&mut *tx)
+            .await?;
+            low + (high + POSITION_GAP - low) / 2
+        }
+        (None, None) => unreachable!("the reference item bounds the position"),
+    };
+
+    let position: i32 = sqlx::query_scalar(&format!(
+        "UPDATE {table} SET position = $1 WHERE list = $2 AND {id} = $3 RETURNING position"
+    ))
+    .bind(position)
+    .bind(list)
+    .bind(item)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(not_found)?;
+
+    tx.commit().await?;
+
+    Ok(position)
+}
+
+/// Checks that `unit` is accepted by the list, lists without allowed units accept anything
+pub(crate) async fn check_unit(db: &PgPool, list: Uuid, unit: &str) -> Result<(), Error> {
+    let allowed = sqlx::query!("SELECT allowed_units FROM lists WHERE id = $1", list)
+        .fetch_one(db)
+        .await?
+        .allowed_units;
+
+    if allowed.is_empty() || allowed.iter().any(|u| u == unit) {
+        Ok(())
+    } else {
+        Err(Error::UnitNotAllowed)
+    }
+}
+
+/// Rejects blank amounts, and missing ones, when the list requires items to have an amount.
+///
+/// With `use_default`, the default amount of the list stands in for a missing amount, as it does
+/// when adding an item
+pub(crate) async fn check_amount(
+    db: &PgPool,
+    list: Uuid,
+    amount: Option<&str>,
+    use_default: bool,
+) -> Result<(), Error> {
+    let rules = sqlx::query!(
+        "SELECT require_amount, default_amount FROM lists WHERE id = $1",
+        list
+    )
+    .fetch_one(db)
+    .await?;
+
+    let amount = match use_default {
+        true => amount.or(rules.default_amount.as_deref()),
+        false => amount,
+    };
+
+    if rules.require_amount && amount.is_none_or(|amount| amount.trim().is_empty()) {
+        Err(Error::AmountRequired)
+    } else {
+        Ok(())
+    }
+}
+
+/// Normalizes an item name, and applies the capitalization chosen for the list
+pub(crate) async fn item_name(db: &PgPool, list: Uuid, name: &str) -> Result<String, Error> {
+    apply_item_name(list_case_mode(db, list).await?, name)
+}
+
+async fn list_case_mode(db: &PgPool, list: Uuid) -> Result<CaseMode, Error> {
+    let mode = sqlx::query!("SELECT normalize_case FROM lists WHERE id = $1", list)
+        .fetch_one(db)
+        .await?
+        .normalize_case;
+
+    // The column is constrained to the known modes
+    Ok(CaseMode::from_name(&mode).unwrap_or_default())
+}
+
+/// Only allow links that can't run code when opened from the public page
+pub(crate) fn check_url(url: &str) -> Result<(), Error> {
+    let url = url.to_ascii_lowercase();
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(Error::InvalidUrl)
+    }
+}
+
+/// Normalizes an item name and applies `case_mode`, rejecting names left empty because they only
+/// had blanks and control characters
+fn apply_item_name(case_mode: CaseMode, name: &str) -> Result<String, Error> {
+    let name = normalize_item_name(name);
+    if name.is_empty() {
+        return Err(Error::InvalidItemName);
+    }
+
+    Ok(case_mode.apply(&name))
+}
+
+/// An item in a path, either by its integer id or by its external id
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ItemRef {
+    Id(i32),
+    External(Uuid),
+}
+
+impl<'de> Deserialize<'de> for ItemRef {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        if let Ok(id) = s.parse() {
+            return Ok(ItemRef::Id(id));
+        }
+
+        s.parse()
+            .map(ItemRef::External)
+            .map_err(|_| serde::de::Error::custom("expected an item id or external id"))
+    }
+}
+
+impl ItemRef {
+    /// Rejects integer ids that can't refer to an item
+    fn check(self) -> Result<(), Error> {
+        match self {
+            ItemRef::Id(id) => check_item_id(id),
+            ItemRef::External(_) => Ok(()),
+        }
+    }
+
+    /// Finds the integer id of the item, failing if it is not part of `list`
+    async fn resolve(self, db: &sqlx::PgPool, list: Uuid) -> Result<i32, Error> {
+        let item = match self {
+            ItemRef::Id(id) => sqlx::query!(
+                "SELECT id FROM lists_content WHERE list = $1 AND id = $2",
+                list,
+                id
+            )
+            .fetch_optional(db)
+            .await?
+            .map(|row| row.id),
+            ItemRef::External(external_id) => sqlx::query!(
+                "SELECT id FROM lists_content WHERE list = $1 AND external_id = $2",
+                list,
+                external_id
+            )
+            .fetch_optional(db)
+            .await?
+            .map(|row| row.id),
+        };
+
+        item.ok_or(Error::ItemNotFound)
+    }
+}
+
 pub(crate) fn router() -> Router<Arc<KabalistState>> {
     Router::new()
         .route("/", post(create_list).get(list_lists))
-        .route("/{id}", get(read_list).post(add_list).delete(delete_list))
+        .route(
+            "/{id}",
+            get(read_list)
+                .post(add_list)
+                .patch(update_list)
+                .delete(delete_list),
+        )
+        .route("/{id}/info", get(list_info))
+        .route(
+            "/{id}/import-text",
+            post(import_text).layer(DefaultBodyLimit::max(crate::BULK_BODY_LIMIT)),
+        )
+        .route("/{id}/check-all", post(check_all))
+        .route("/{id}/uncheck-all", post(uncheck_all))
+        .route("/{id}/compact", post(compact_positions))
+        .route(
+            "/{id}/items",
+            patch(bulk_update).layer(DefaultBodyLimit::max(crate::BULK_BODY_LIMIT)),
+        )
+        .route("/{id}/receipt", get(receipt))
+        .route("/{id}/changes", get(list_changes))
+        .route("/{id}/search", get(search_items))
         .route("/{id}/{item}", patch(update_item).delete(delete_item))
+        .route("/{id}/{item}/position", post(move_item))
+        .route("/{id}/{item}/to-pantry", post(item_to_pantry))
         .route(
             "/{id}/public",
             put(set_public).delete(remove_public).get(get_public_list),
         )
+        .route("/{id}/archive", put(archive_list).delete(unarchive_list))
+        .route("/{id}/tags/{tag}", put(add_tag).delete(remove_tag))
+        .route("/{id}/share-many", post(share::share_many))
+        .route("/{id}/shares/cleanup", post(share::cleanup_shares))
+        .route("/{id}/links", post(share::create_link))
+        .route("/{id}/links/{token}", delete(share::revoke_link))
+        .route("/{id}/guest-token", post(account::create_guest_token))
+        .route(
+            "/{id}/guest-token/{token}",
+            delete(account::revoke_guest_token),
+        )
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub(crate) struct ListListsQuery {
+    status: Option<ListStatus>,
+    archived: Option<bool>,
+    sort: Option<ListSort>,
+    modified_since: Option<DateTime<Utc>>,
+    tag: Option<String>,
+}
+
+/// Builds the response of the list overviews, with `order` following `sort`
+pub(crate) fn sorted_lists(
+    lists: impl Iterator<Item = (Uuid, ListInfo)>,
+    sort: ListSort,
+) -> GetListsResponse {
+    let mut lists: Vec<_> = lists.collect();
+    match sort {
+        ListSort::Created => lists
+            .sort_by(|(a_id, a), (b_id, b)| a.created_at.cmp(&b.created_at).then(a_id.cmp(b_id))),
+        ListSort::Name => lists.sort_by(|(a_id, a), (b_id, b)| {
+            a.name
+                .to_lowercase()
+                .cmp(&b.name.to_lowercase())
+                .then(a_id.cmp(b_id))
+        }),
+    }
+
+    GetListsResponse {
+        order: lists.iter().map(|&(id, _)| id).collect(),
+        results: lists.into_iter().collect(),
+    }
 }
 
 #[utoipa::path(
@@ -40,32 +386,85 @@ pub(crate) fn router() -> Router<Arc<KabalistState>> {
         (status = 400, description = "Invalid request", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
+    params(
+        ("status" = Option<ListStatus>, Query, description = "Only return lists with this status"),
+        ("archived" = Option<bool>, Query, description = "Also return archived lists"),
+        ("sort" = Option<ListSort>, Query, description = "Order of the lists, by creation date by default"),
+        ("modified_since" = Option<DateTime<Utc>>, Query, description = "Only return lists modified after this time"),
+        ("tag" = Option<String>, Query, description = "Only return lists with this tag"),
+    ),
     security(
         ("token" = [])
     )
 )]
 #[tracing::instrument(skip(state))]
-pub(crate) async fn list_lists(state: State, user: User) -> Rsp<GetListsResponse> {
-    let results_owned = sqlx::query!(
-        r#"
-        SELECT name, id, pub, owner
-        FROM lists WHERE owner = $1"#,
-        user.id
-    )
-    .fetch_all(&state.0.pool)
-    .await?;
-    let results_shared = sqlx::query!(
-        r#"SELECT name, id, readonly, pub, owner
-               FROM lists, list_sharing
-               WHERE (lists.id = list_sharing.list)
-                   AND shared = $1 "#,
-        user.id
-    )
-    .fetch_all(&state.0.pool)
-    .await?;
+pub(crate) async fn list_lists(
+    state: State,
+    user: User,
+    extract::Query(query): extract::Query<ListListsQuery>,
+) -> Rsp<GetListsResponse> {
+    OkResponse::ok(fetch_lists(&state.0.pool, user.id, &query).await?)
+}
+
+/// Lists accessible by `account`, filtered as requested by `query`
+pub(crate) async fn fetch_lists(
+    db: &PgPool,
+    account: Uuid,
+    query: &ListListsQuery,
+) -> Result<GetListsResponse, Error> {
+    let status = query.status;
+    let archived = query.archived.unwrap_or(false);
+    let tag = query.tag.as_deref().map(normalize_tag);
+
+    let results_owned = match status {
+        None | Some(ListStatus::Owned) => {
+            sqlx::query!(
+                r#"
+                SELECT name, id, pub, owner, description, archived, created_at, require_amount,
+                       ARRAY(SELECT tag FROM list_tags WHERE list = lists.id ORDER BY tag) as "tags!"
+                FROM lists
+                WHERE owner = $1
+                    AND (NOT archived OR $2)
+                    AND ($3::timestamptz IS NULL OR updated_at > $3)
+                    AND ($4::text IS NULL
+                         OR EXISTS(SELECT 1 FROM list_tags WHERE list = lists.id AND tag = $4))"#,
+                account,
+                archived,
+                query.modified_since,
+                tag,
+            )
+            .fetch_all(db)
+            .await?
+        }
+        Some(_) => Vec::new(),
+    };
+    let results_shared = match status {
+        Some(ListStatus::Owned) => Vec::new(),
+        _ => {
+            sqlx::query!(
+                r#"SELECT name, id, readonly, pub, owner, description, archived, created_at,
+                          require_amount,
+                          ARRAY(SELECT tag FROM list_tags WHERE list = lists.id ORDER BY tag) as "tags!"
+                       FROM lists, list_sharing
+                       WHERE (lists.id = list_sharing.list)
+                           AND shared = $1
+                           AND (NOT archived OR $2)
+                           AND ($3::timestamptz IS NULL OR updated_at > $3)
+                           AND ($4::text IS NULL
+                                OR EXISTS(SELECT 1 FROM list_tags
+                                              WHERE list = lists.id AND tag = $4))"#,
+                account,
+                archived,
+                query.modified_since,
+                tag,
+            )
+            .fetch_all(db)
+            .await?
+        }
+    };
 
-    OkResponse::ok(GetListsResponse {
-        results: results_owned
+    Ok(sorted_lists(
+        results_owned
             .into_iter()
             .map(|row| {
                 (
@@ -75,6 +474,11 @@ pub(crate) async fn list_lists(state: State, user: User) -> Rsp<GetListsResponse
                         status: ListStatus::Owned,
                         public: row.r#pub.unwrap_or(false),
                         owner: row.owner,
+                        description: row.description,
+                        archived: row.archived,
+                        created_at: row.created_at,
+                        tags: row.tags,
+                        require_amount: row.require_amount,
                     },
                 )
             })
@@ -90,18 +494,24 @@ pub(crate) async fn list_lists(state: State, user: User) -> Rsp<GetListsResponse
                         },
                         public: row.r#pub.unwrap_or(false),
                         owner: row.owner,
+                        description: row.description,
+                        archived: row.archived,
+                        created_at: row.created_at,
+                        tags: row.tags,
+                        require_amount: row.require_amount,
                     },
                 )
             }))
-            .collect(),
-    })
+            .filter(|(_, info)| status.is_none_or(|status| status == info.status)),
+        query.sort.unwrap_or_default(),
+    ))
 }
 
 #[utoipa::path(
     post,
     path = "/api/list",
     responses(
-        (status = 200, description = "List ID", body = OkCreateListResponse),
+        (status = 201, description = "List ID", body = OkCreateListResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
@@ -115,11 +525,25 @@ pub(crate) async fn create_list(
     state: State,
     user: User,
     Json(list): Json<CreateListRequest>,
-) -> Rsp<CreateListResponse> {
+) -> Created<CreateListResponse> {
+    user.require_write()?;
+    let name = list.name.trim();
+    if name.is_empty() {
+        return Err(Error::InvalidListName);
+    }
+    if name.chars().count() > MAX_LIST_NAME_LENGTH {
+        return Err(Error::ListNameTooLong);
+    }
+    let description = list
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty());
+
     match sqlx::query!(
-        "SELECT COUNT(*) FROM lists WHERE owner = $1 AND name = $2",
+        "SELECT COUNT(*) FROM lists WHERE owner = $1 AND lower(name) = lower($2)",
         user.id,
-        list.name
+        name
     )
     .fetch_one(&state.0.pool)
     .await?
@@ -129,25 +553,63 @@ pub(crate) async fn create_list(
         _ => return Err(Error::ListAlreadyExists),
     }
 
+    let mut tx = state.0.pool.begin().await?;
+
+    if let Some(max_lists) = state.0.config.max_lists_per_user {
+        // Concurrent creations by the same account wait on its row until the commit, so that they
+        // can't both count the lists before either inserts and go over the limit
+        sqlx::query!(
+            "SELECT id FROM accounts WHERE id = $1 FOR NO KEY UPDATE",
+            user.id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let owned = sqlx::query!(
+            r#"SELECT COUNT(*) as "count!" FROM lists WHERE owner = $1"#,
+            user.id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .count;
+        if owned >= max_lists {
+            return Err(Error::ListLimitReached);
+        }
+    }
+
+    // The check above can race with another request creating the same list, the unique index on
+    // the names then makes one of the inserts do nothing
     let list_id = sqlx::query!(
-        "INSERT INTO lists (id, owner, name) VALUES (uuid_generate_v4(), $1, $2) RETURNING id",
+        r#"INSERT INTO lists (id, owner, name, description)
+               VALUES (uuid_generate_v4(), $1, $2, $3)
+               ON CONFLICT (owner, lower(name)) DO NOTHING
+               RETURNING id"#,
         user.id,
-        list.name
+        name,
+        description,
     )
-    .fetch_one(&state.0.pool)
-    .await?;
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(Error::ListAlreadyExists)?;
+    tx.commit().await?;
+    // The route has no list in its path, so `seen_after_write` can't do it
+    mark_seen(&state.0.pool, user.id, list_id.id).await;
 
-    OkResponse::ok(CreateListResponse { id: list_id.id })
+    created(
+        format!("/api/list/{}", list_id.id),
+        CreateListResponse { id: list_id.id },
+    )
 }
 
 #[utoipa::path(
-    get,
+    patch,
     path = "/api/list/{id}",
     responses(
-        (status = 200, description = "List Content", body = OkReadListResponse),
+        (status = 200, description = "List Updated", body = OkUpdateListResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
+    request_body = UpdateListRequest,
     params(
         ("id" = Uuid, Path, description = "List ID"),
     ),
@@ -156,215 +618,472 @@ pub(crate) async fn create_list(
     )
 )]
 #[tracing::instrument(skip(state))]
-pub(crate) async fn read_list(
+pub(crate) async fn update_list(
     state: State,
     user: User,
-    extract::Path(id): extract::Path<Uuid>,
-) -> Rsp<ReadListResponse> {
-    check_list(&state.0.pool, user.id, id, false).await?;
+    Path(id): Path<Uuid>,
+    Json(update): Json<UpdateListRequest>,
+) -> Rsp<UpdateListResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
 
-    let items = sqlx::query!(
-        "SELECT id, name, amount FROM lists_content WHERE list = $1",
+    let trimmed = |values: Vec<String>| {
+        values
+            .iter()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>()
+    };
+    let allowed_units = update.allowed_units.map(trimmed);
+    let aisle_order = update.aisle_order.map(trimmed);
+
+    sqlx::query!(
+        r#"UPDATE lists
+               SET description = NULLIF(TRIM(COALESCE($1, description)), ''),
+                   allowed_units = COALESCE($2, allowed_units),
+                   normalize_case = COALESCE($3, normalize_case),
+                   aisle_order = COALESCE($4, aisle_order),
+                   default_amount = NULLIF(TRIM(COALESCE($5, default_amount)), ''),
+                   require_amount = COALESCE($6, require_amount)
+               WHERE id = $7"#,
+        update.description,
+        allowed_units.as_deref(),
+        update.normalize_case.as_ref().map(CaseMode::as_str),
+        aisle_order.as_deref(),
+        update.default_amount,
+        update.require_amount,
         id
     )
-    .fetch_all(&state.0.pool)
+    .execute(&state.0.pool)
     .await?;
 
-    let mut readonly_result = sqlx::query!(
-        "SELECT readonly FROM list_sharing WHERE list = $1 AND shared = $2",
-        id,
-        user.id,
-    )
-    .fetch(&state.0.pool);
+    OkResponse::ok(UpdateListResponse {})
+}
 
-    let readonly = match readonly_result.next().await {
-        Some(Ok(v)) => v.readonly,
-        Some(Err(e)) => return Err(e.into()),
-        None => false,
-    };
+#[derive(Deserialize, Debug)]
+pub(crate) struct ReadListQuery {
+    group: Option<bool>,
+    sort: Option<ItemSort>,
+    fields: Option<ItemFields>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    status: Option<ItemStatus>,
+}
 
-    OkResponse::ok(ReadListResponse {
-        items: items
-            .into_iter()
-            .map(|row| Item {
-                id: row.id,
-                name: row.name,
-                amount: row.amount,
-            })
-            .collect(),
-        readonly,
-    })
+/// Sorts items by the position of their category in `aisles`, then by name
+fn sort_by_aisle(items: &mut [Item], aisles: &[String]) {
+    items.sort_by_cached_key(|item| {
+        let category = item.category.as_deref().unwrap_or("");
+        let aisle = aisles.iter().position(|aisle| aisle == category);
+        (
+            aisle.unwrap_or(aisles.len()),
+            // Only used for the categories not in `aisles`, which all share the same index
+            aisle.map_or_else(|| category.to_lowercase(), |_| String::new()),
+            item.name.to_lowercase(),
+        )
+    });
+}
+
+/// Merges items with the same name and unit into the first one of them.
+///
+/// Amounts are summed if they are all integers, and listed otherwise. The merged item is only
+/// checked if all the items are, and takes the least settled status of them.
+fn group_items(items: impl Iterator<Item = Item>) -> Vec<Item> {
+    let mut groups: Vec<(Item, Vec<String>)> = Vec::new();
+    let mut by_name: HashMap<_, usize> = HashMap::new();
+
+    for item in items {
+        let key = (item.name.clone(), item.unit.clone());
+        match by_name.get(&key) {
+            Some(&index) => {
+                let (group, amounts) = &mut groups[index];
+                group.checked &= item.checked;
+                group.status = group.status.min(item.status);
+                if group.url.is_none() {
+                    group.url = item.url;
+                }
+                amounts.extend(item.amount);
+            }
+            None => {
+                by_name.insert(key, groups.len());
+                let amounts = item.amount.iter().cloned().collect();
+                groups.push((item, amounts));
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(mut item, amounts)| {
+            if !amounts.is_empty() {
+                let total = amounts
+                    .iter()
+                    .map(|amount| amount.trim().parse::<i64>())
+                    .sum::<Result<i64, _>>();
+                item.amount = Some(match total {
+                    Ok(total) => total.to_string(),
+                    Err(_) => amounts.join(", "),
+                });
+            }
+            item
+        })
+        .collect()
 }
 
 #[utoipa::path(
-    post,
+    get,
     path = "/api/list/{id}",
     responses(
-        (status = 200, description = "New Item", body = OkAddToListResponse),
+        (status = 200, description = "List Content, an `OkMinimalReadListResponse` with minimal fields", body = OkReadListResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
-    request_body = AddToListRequest,
     params(
         ("id" = Uuid, Path, description = "List ID"),
+        ("group" = Option<bool>, Query, description = "Merge items with the same name"),
+        ("sort" = Option<ItemSort>, Query, description = "Order of the items"),
+        ("fields" = Option<ItemFields>, Query, description = "Fields of the items, all of them by default"),
+        ("from" = Option<DateTime<Utc>>, Query, description = "Only items added at or after this time"),
+        ("to" = Option<DateTime<Utc>>, Query, description = "Only items added at or before this time"),
+        ("status" = Option<ItemStatus>, Query, description = "Only items with this status, before grouping"),
     ),
     security(
         ("token" = [])
     )
 )]
 #[tracing::instrument(skip(state))]
-pub(crate) async fn add_list(
+pub(crate) async fn read_list(
     state: State,
-    user: User,
-    extract::Path(id): extract::Path<Uuid>,
-    Json(item): Json<AddToListRequest>,
-) -> Rsp<AddToListResponse> {
-    check_list(&state.0.pool, user.id, id, true).await?;
-
-    let mut tx = state.0.pool.begin().await?;
-
-    let item_id = sqlx::query!(
-        "INSERT INTO lists_content (list, name, amount) VALUES ($1, $2, $3) RETURNING id",
-        id,
-        item.name,
-        item.amount
-    )
-    .fetch_one(&mut *tx)
-    .await?;
+    ListUser(user): ListUser,
+    Path(id): Path<Uuid>,
+    extract::Query(query): extract::Query<ReadListQuery>,
+) -> Result<axum::response::Response, Error> {
+    check_list(&state.0.pool, user.id, id, false).await?;
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(Error::InvalidRange);
+        }
+    }
+    mark_seen(&state.0.pool, user.id, id).await;
 
-    sqlx::query!(
-        r#"INSERT INTO history (list, creator, name, last_used)
-               VALUES ($1, $2, $3::text::citext, now())
-               ON CONFLICT (list, creator, name) DO
-               UPDATE SET last_used = now()"#,
-        id,
+    let mut list = fetch_list(
+        &state.0.pool,
         user.id,
-        item.name
+        id,
+        query.group.unwrap_or(false),
+        query.from,
+        query.to,
+        query.status,
     )
-    .execute(&mut *tx)
     .await?;
 
-    tx.commit().await?;
+    if query.sort == Some(ItemSort::Aisle) {
+        let aisles = sqlx::query!("SELECT aisle_order FROM lists WHERE id = $1", id)
+            .fetch_one(&state.0.pool)
+            .await?
+            .aisle_order;
+        sort_by_aisle(&mut list.items, &aisles);
+    }
 
-    OkResponse::ok(AddToListResponse { id: item_id.id })
+    match query.fields.unwrap_or_default() {
+        ItemFields::Full => OkResponse::ok(list).map(IntoResponse::into_response),
+        ItemFields::Minimal => OkResponse::ok(MinimalReadListResponse {
+            items: list.items.into_iter().map(MinimalItem::from).collect(),
+            readonly: list.readonly,
+            public: list.public,
+            share_count: list.share_count,
+            checked_count: list.checked_count,
+            total_count: list.total_count,
+        })
+        .map(IntoResponse::into_response),
+    }
 }
 
-#[utoipa::path(
-    patch,
-    path = "/api/list/{id}/{item}",
-    responses(
-        (status = 200, description = "Update Item", body = OkUpdateItemResponse),
-        (status = 400, description = "Invalid request", body = ErrResponse),
-        (status = 500, description = "Internal Error", body = ErrResponse),
-    ),
-    request_body = UpdateItemRequest,
-    params(
-        ("id" = Uuid, Path, description = "List ID"),
-        ("item" = i32, Path, description = "Item ID"),
-    ),
-    security(
-        ("token" = [])
+/// Records that `account` read the list, for [`unseen_lists`]
+pub(crate) async fn mark_seen(db: &PgPool, account: Uuid, list: Uuid) {
+    // Guest tokens are not accounts, so nothing is recorded for them, nor for a list that was just
+    // deleted. This only feeds the unseen lists, a failure here must not fail the request
+    if let Err(e) = sqlx::query!(
+        r#"INSERT INTO list_seen (account, list, seen_at)
+               SELECT $1, $2, now()
+                   WHERE EXISTS(SELECT 1 FROM accounts WHERE id = $1)
+                       AND EXISTS(SELECT 1 FROM lists WHERE id = $2)
+               ON CONFLICT (account, list) DO UPDATE SET seen_at = now()"#,
+        account,
+        list,
     )
-)]
-#[tracing::instrument(skip(state))]
-pub(crate) async fn update_item(
-    state: State,
-    user: User,
-    extract::Path((list, item)): extract::Path<(Uuid, i32)>,
-    Json(update): Json<UpdateItemRequest>,
-) -> Rsp<UpdateItemResponse> {
-    check_list(&state.0.pool, user.id, list, true).await?;
+    .execute(db)
+    .await
+    {
+        tracing::warn!("Could not record that {} read {}: {:?}", account, list, e);
+    }
+}
 
-    let mut tx = state.0.pool.begin().await?;
+/// Account behind a write to a list, filled in by the [`User`] extractor of the handler so that
+/// [`seen_after_write`] does not have to authenticate the request a second time
+#[derive(Clone, Default)]
+pub(crate) struct WriteCaller(Arc<OnceLock<Uuid>>);
 
-    if let Some(name) = &update.name {
-        sqlx::query!(
-            "UPDATE lists_content SET name = $1 WHERE list = $2 AND id = $3",
-            name,
-            list,
-            item
-        )
-        .execute(&mut *tx)
-        .await?;
+impl WriteCaller {
+    pub(crate) fn record(&self, account: Uuid) {
+        let _ = self.0.set(account);
     }
+}
 
-    if let Some(amount) = &update.amount {
-        sqlx::query!(
-            "UPDATE lists_content SET amount = $1 WHERE list = $2 AND id = $3",
-            amount,
-            list,
-            item
-        )
-        .execute(&mut *tx)
-        .await?;
+/// Marks the list in the path as seen by the caller after each of their successful writes, as the
+/// list changing because of them is nothing new to them
+pub(crate) async fn seen_after_write(
+    state: State,
+    request: extract::Request,
+    next: Next,
+) -> axum::response::Response {
+    if request.method().is_safe() {
+        return next.run(request).await;
     }
 
-    tx.commit().await?;
+    let (mut parts, body) = request.into_parts();
+    let list = RawPathParams::from_request_parts(&mut parts, &state.0)
+        .await
+        .ok()
+        .and_then(|params| {
+            params
+                .iter()
+                .find(|(name, _)| *name == "id")
+                .and_then(|(_, id)| id.parse::<Uuid>().ok())
+        });
+    let caller = WriteCaller::default();
+    parts.extensions.insert(caller.clone());
 
-    OkResponse::ok(UpdateItemResponse {})
+    let response = next.run(extract::Request::from_parts(parts, body)).await;
+    if let (Some(&account), Some(list)) = (caller.0.get(), list) {
+        if response.status().is_success() {
+            mark_seen(&state.0.pool, account, list).await;
+        }
+    }
+
+    response
 }
 
+/// Lists the caller can read that changed since they last read them, or that they never read.
+/// Archived lists are left out
 #[utoipa::path(
-    delete,
-    path = "/api/list/{id}/{item}",
+    get,
+    path = "/api/unseen",
     responses(
-        (status = 200, description = "Delete Item", body = OkDeleteItemResponse),
+        (status = 200, description = "Changed lists", body = OkUnseenResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
-    params(
-        ("id" = Uuid, Path, description = "List ID"),
-        ("item" = i32, Path, description = "Item ID"),
-    ),
     security(
         ("token" = [])
     )
 )]
-pub(crate) async fn delete_item(
-    state: State,
-    user: User,
-    extract::Path((list, item)): extract::Path<(Uuid, i32)>,
-) -> Rsp<DeleteItemResponse> {
-    check_list(&state.0.pool, user.id, list, true).await?;
-
-    let mut tx = state.0.pool.begin().await?;
-
-    sqlx::query!(
-        "UPDATE pantry_content
-        SET amount = amount +
-            (SELECT
-                COALESCE(convert_to_integer(lists_content.amount), 0) as added
-            FROM lists_content
-            WHERE lists_content.list = $1 AND lists_content.id = $2)
-        WHERE
-            pantry_content.item =
-                (SELECT lists_content.from_pantry
-                 FROM lists_content
-                 WHERE lists_content.list = $1 AND lists_content.id = $2)",
-        list,
-        item
+#[tracing::instrument(skip(state))]
+pub(crate) async fn unseen_lists(state: State, user: User) -> Rsp<UnseenResponse> {
+    let lists = sqlx::query!(
+        r#"SELECT lists.id, lists.name, lists.updated_at, list_seen.seen_at as "seen_at?"
+               FROM lists
+               LEFT JOIN list_seen ON list_seen.list = lists.id AND list_seen.account = $1
+               WHERE NOT lists.archived
+                   AND (lists.owner = $1
+                        OR EXISTS(SELECT 1 FROM list_sharing
+                                      WHERE list = lists.id AND shared = $1))
+                   AND (list_seen.seen_at IS NULL OR lists.updated_at > list_seen.seen_at)
+               ORDER BY lists.updated_at DESC, lists.id"#,
+        user.id
     )
-    .execute(&mut *tx)
+    .fetch_all(&state.0.pool)
+    .await?
+    .into_iter()
+    .map(|row| UnseenList {
+        id: row.id,
+        name: row.name,
+        updated_at: row.updated_at,
+        seen_at: row.seen_at,
+    })
+    .collect();
+
+    OkResponse::ok(UnseenResponse { lists })
+}
+
+/// Whether `account` only has read access to the list
+async fn list_readonly(db: &PgPool, account: Uuid, id: Uuid) -> Result<bool, Error> {
+    Ok(sqlx::query!(
+        r#"SELECT owner <> $2
+                  AND (EXISTS(SELECT 1 FROM list_sharing
+                                  WHERE list = $1 AND shared = $2 AND readonly)
+                       OR EXISTS(SELECT 1 FROM guest_tokens
+                                     WHERE list = $1 AND id = $2)) as "readonly!"
+               FROM lists
+               WHERE id = $1"#,
+        id,
+        account,
+    )
+    .fetch_one(db)
+    .await?
+    .readonly)
+}
+
+/// Whether an item can be edited by someone having this access to its list. Items all share the
+/// access of their list for now, rules for single items would be added here
+fn item_editable(list_readonly: bool) -> bool {
+    !list_readonly
+}
+
+/// Status of an item from its flags.
+///
+/// Checked items keep their `maybe` flag, so that unchecking them puts them back where they were
+fn item_status(checked: bool, maybe: bool) -> ItemStatus {
+    match (checked, maybe) {
+        (true, _) => ItemStatus::Checked,
+        (false, true) => ItemStatus::Maybe,
+        (false, false) => ItemStatus::Active,
+    }
+}
+
+/// Reads the content of a list as seen by `account`, the caller is responsible for the access
+/// checks.
+///
+/// Only items added between `from` and `to` and with the given `status` are returned, the
+/// counts always cover the whole list
+pub(crate) async fn fetch_list(
+    db: &PgPool,
+    account: Uuid,
+    id: Uuid,
+    group: bool,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    status: Option<ItemStatus>,
+) -> Result<ReadListResponse, Error> {
+    let (checked, maybe) = match status {
+        None => (None, None),
+        Some(ItemStatus::Active) => (Some(false), Some(false)),
+        Some(ItemStatus::Maybe) => (Some(false), Some(true)),
+        Some(ItemStatus::Checked) => (Some(true), None),
+    };
+
+    let items = sqlx::query!(
+        r#"SELECT lists_content.id, external_id, lists_content.name, amount, unit, category,
+                  checked, maybe, position, url, creator, accounts.name::text as "creator_name!"
+               FROM lists_content
+               JOIN accounts ON accounts.id = lists_content.creator
+               WHERE list = $1
+                   AND ($2::timestamptz IS NULL OR lists_content.created_at >= $2)
+                   AND ($3::timestamptz IS NULL OR lists_content.created_at <= $3)
+                   AND ($4::bool IS NULL OR checked = $4)
+                   AND ($5::bool IS NULL OR maybe = $5)
+               ORDER BY position, lists_content.id"#,
+        id,
+        from,
+        to,
+        checked,
+        maybe,
+    )
+    .fetch_all(db)
     .await?;
 
-    sqlx::query!(
-        "DELETE FROM lists_content WHERE list = $1 AND id = $2",
-        list,
-        item
+    let sharing = sqlx::query!(
+        r#"SELECT pub, owner,
+                  (SELECT COUNT(*) FROM list_sharing WHERE list = $1) as "share_count!",
+                  COALESCE((SELECT readonly FROM list_sharing WHERE list = $1 AND shared = $2),
+                           EXISTS(SELECT 1 FROM guest_tokens WHERE list = $1 AND id = $2)) as readonly,
+                  (SELECT COUNT(*) FROM lists_content WHERE list = $1) as "total_count!",
+                  (SELECT COUNT(*) FROM lists_content WHERE list = $1 AND checked) as "checked_count!"
+               FROM lists
+               WHERE id = $1"#,
+        id,
+        account,
     )
-    .execute(&mut *tx)
+    .fetch_one(db)
     .await?;
 
-    tx.commit().await?;
+    // The owner can always write, even if a share row exists for them
+    let readonly = sharing.owner != account && sharing.readonly.unwrap_or(false);
 
-    OkResponse::ok(DeleteItemResponse {})
+    let items = items.into_iter().map(|row| Item {
+        id: row.id,
+        external_id: row.external_id,
+        name: row.name,
+        amount: row.amount,
+        unit: row.unit,
+        category: row.category,
+        checked: row.checked,
+        status: item_status(row.checked, row.maybe),
+        position: row.position,
+        url: row.url,
+        added_by: row.creator,
+        added_by_name: row.creator_name,
+        editable: item_editable(readonly),
+    });
+
+    Ok(ReadListResponse {
+        items: if group {
+            group_items(items)
+        } else {
+            items.collect()
+        },
+        readonly,
+        public: sharing.r#pub.unwrap_or(false),
+        share_count: sharing.share_count,
+        checked_count: sharing.checked_count,
+        total_count: sharing.total_count,
+    })
 }
 
+/// Upper bound on the number of lists fetched by a single [`read_lists`] call
+const MAX_MULTI_READ: usize = 50;
+
 #[utoipa::path(
-    delete,
-    path = "/api/list/{id}",
+    post,
+    path = "/api/lists/read",
+    request_body = MultiReadRequest,
     responses(
-        (status = 200, description = "List Deleted", body = OkDeleteListResponse),
+        (status = 200, description = "Content of the accessible lists", body = OkMultiReadResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn read_lists(
+    state: State,
+    ListUser(user): ListUser,
+    Json(req): Json<MultiReadRequest>,
+) -> Rsp<MultiReadResponse> {
+    if req.ids.len() > MAX_MULTI_READ {
+        return Err(Error::TooManyLists);
+    }
+
+    let mut lists = BTreeMap::new();
+    for id in req.ids {
+        if lists.contains_key(&id) {
+            continue;
+        }
+
+        match check_list(&state.0.pool, user.id, id, false).await {
+            Ok(()) => (),
+            // Lists that can't be read are left out of the response
+            Err(Error::NoSuchList) => continue,
+            Err(e) => return Err(e),
+        }
+
+        lists.insert(
+            id,
+            fetch_list(&state.0.pool, user.id, id, req.group, None, None, None).await?,
+        );
+        mark_seen(&state.0.pool, user.id, id).await;
+    }
+
+    OkResponse::ok(MultiReadResponse { lists })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/list/{id}/info",
+    responses(
+        (status = 200, description = "List Metadata", body = OkListDetail),
         (status = 400, description = "Invalid request", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
@@ -376,164 +1095,3903 @@ pub(crate) async fn delete_item(
     )
 )]
 #[tracing::instrument(skip(state))]
-pub(crate) async fn delete_list(
+pub(crate) async fn list_info(
     state: State,
-    user: User,
-    extract::Path(id): extract::Path<Uuid>,
-) -> Rsp<DeleteListResponse> {
-    is_owner(&state.0.pool, user.id, id).await?;
-    let mut tx = state.0.pool.begin().await?;
+    ListUser(user): ListUser,
+    Path(id): Path<Uuid>,
+) -> Rsp<ListDetail> {
+    check_list(&state.0.pool, user.id, id, false).await?;
 
-    sqlx::query!("DELETE FROM list_sharing WHERE list = $1", id)
-        .execute(&mut *tx)
-        .await?;
-    sqlx::query!("DELETE FROM lists_content WHERE list = $1", id)
-        .execute(&mut *tx)
-        .await?;
-    sqlx::query!("DELETE FROM history WHERE list = $1", id)
-        .execute(&mut *tx)
-        .await?;
-    sqlx::query!("DELETE FROM lists WHERE id = $1", id)
-        .execute(&mut *tx)
-        .await?;
+    let info = sqlx::query!(
+        r#"SELECT lists.name, lists.owner, lists.pub, lists.description, lists.allowed_units,
+                  lists.normalize_case, lists.aisle_order, lists.default_amount,
+                  lists.require_amount, accounts.name::text as "owner_name!",
+                  (SELECT COUNT(*) FROM lists_content WHERE list = lists.id) as "item_count!",
+                  COALESCE((SELECT readonly FROM list_sharing
+                                WHERE list = lists.id AND shared = $2),
+                           EXISTS(SELECT 1 FROM guest_tokens
+                                      WHERE list = lists.id AND id = $2)) as readonly
+               FROM lists, accounts
+               WHERE lists.id = $1 AND accounts.id = lists.owner"#,
+        id,
+        user.id,
+    )
+    .fetch_one(&state.0.pool)
+    .await?;
 
-    tx.commit().await?;
+    let status = if info.owner == user.id {
+        ListStatus::Owned
+    } else if info.readonly.unwrap_or(false) {
+        ListStatus::SharedRead
+    } else {
+        ListStatus::SharedWrite
+    };
 
-    OkResponse::ok(DeleteListResponse {})
+    OkResponse::ok(ListDetail {
+        id,
+        name: info.name,
+        status,
+        owner: info.owner,
+        owner_name: info.owner_name,
+        public: info.r#pub.unwrap_or(false),
+        item_count: info.item_count,
+        description: info.description,
+        allowed_units: info.allowed_units,
+        normalize_case: CaseMode::from_name(&info.normalize_case).unwrap_or_default(),
+        aisle_order: info.aisle_order,
+        default_amount: info.default_amount,
+        require_amount: info.require_amount,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct AddListQuery {
+    warn_duplicate: Option<bool>,
 }
 
 #[utoipa::path(
-    put,
-    path = "/api/list/{id}/public",
+    post,
+    path = "/api/list/{id}",
     responses(
-        (status = 200, description = "Sucess", body = OkSetPublicResponse),
+        (status = 201, description = "New Item", body = OkAddToListResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
+    request_body = AddToListRequest,
     params(
         ("id" = Uuid, Path, description = "List ID"),
+        ("warn_duplicate" = Option<bool>, Query, description = "Return the unchecked item with the same name, if any"),
     ),
     security(
         ("token" = [])
     )
 )]
 #[tracing::instrument(skip(state))]
-async fn set_public(
+pub(crate) async fn add_list(
     state: State,
-    extract::Path(id): extract::Path<Uuid>,
     user: User,
-) -> Rsp<SetPublicResponse> {
-    is_owner(&state.0.pool, user.id, id).await?;
+    Path(id): Path<Uuid>,
+    extract::Query(query): extract::Query<AddListQuery>,
+    Json(item): Json<AddToListRequest>,
+) -> Created<AddToListResponse> {
+    user.require_write()?;
+    check_list(&state.0.pool, user.id, id, true).await?;
+    if let Some(url) = &item.url {
+        check_url(url)?;
+    }
+    let unit = item
+        .unit
+        .as_deref()
+        .map(str::trim)
+        .filter(|unit| !unit.is_empty());
+    if let Some(unit) = unit {
+        check_unit(&state.0.pool, id, unit).await?;
+    }
+    check_amount(&state.0.pool, id, item.amount.as_deref(), true).await?;
+    let name = item_name(&state.0.pool, id, &item.name).await?;
+    let category = item
+        .category
+        .as_deref()
+        .map(str::trim)
+        .filter(|category| !category.is_empty());
 
-    sqlx::query!("UPDATE lists SET pub = true WHERE id = $1", id)
-        .execute(&state.0.pool)
-        .await?;
+    let mut tx = state.0.pool.begin().await?;
+
+    let merged = if item.merge_duplicates.unwrap_or(false) {
+        sqlx::query!(
+            r#"UPDATE lists_content
+                   SET amount = COALESCE($3, amount),
+                       url = COALESCE($4, url),
+                       unit = COALESCE($5, unit),
+                       category = COALESCE($6, category)
+                   WHERE id = (SELECT id FROM lists_content
+                                   WHERE list = $1 AND name = $2
+                                       AND from_pantry IS NOT NULL AND NOT checked
+                                   ORDER BY position, id
+                                   LIMIT 1)
+                   RETURNING id"#,
+            id,
+            name,
+            item.amount,
+            item.url,
+            unit,
+            category,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(|row| row.id)
+    } else {
+        None
+    };
+
+    let (item_id, duplicate_of) = match merged {
+        Some(item_id) => (item_id, None),
+        None => {
+            let duplicate_of = if query.warn_duplicate.unwrap_or(false) {
+                sqlx::query!(
+                    r#"SELECT id FROM lists_content
+                           WHERE list = $1 AND name = $2 AND NOT checked
+                           ORDER BY position, id
+                           LIMIT 1"#,
+                    id,
+                    name,
+                )
+                .fetch_optional(&mut *tx)
+                .await?
+                .map(|row| row.id)
+            } else {
+                None
+            };
+
+            if let Some(position) = item.position {
+                // When the position is taken, the items from there on are pushed by a whole gap,
+                // keeping the room between them
+                sqlx::query!(
+                    r#"UPDATE lists_content SET position = position + $3
+                           WHERE list = $1 AND position >= $2
+                               AND EXISTS (SELECT 1 FROM lists_content WHERE list = $1 AND position = $2)"#,
+                    id,
+                    position,
+                    POSITION_GAP,
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            let inserted = sqlx::query!(
+                r#"INSERT INTO lists_content (list, name, amount, position, url, creator, unit, category)
+                       VALUES ($1, $2,
+                           COALESCE($3, (SELECT default_amount FROM lists WHERE id = $1)),
+                           COALESCE($4,
+                               (SELECT COALESCE(MAX(position) + $7, 0) FROM lists_content WHERE list = $1)),
+                           $5, $6, $8, $9)
+                       RETURNING id"#,
+                id,
+                name,
+                item.amount,
+                item.position,
+                item.url,
+                user.id,
+                POSITION_GAP,
+                unit,
+                category,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            (inserted.id, duplicate_of)
+        }
+    };
+
+    tx.commit().await?;
+
+    // History only feeds suggestions, a failure here must not lose the item
+    if let Err(e) = sqlx::query!(
+        r#"INSERT INTO history (list, creator, name, amount, last_used)
+               VALUES ($1, $2, $3::text::citext, $4, now())
+               ON CONFLICT (list, creator, name) DO
+               UPDATE SET last_used = now(), amount = EXCLUDED.amount"#,
+        id,
+        user.id,
+        name,
+        item.amount,
+    )
+    .execute(&state.0.pool)
+    .await
+    {
+        tracing::warn!("Could not record history for {:?}: {:?}", name, e);
+    }
 
-    OkResponse::ok(SetPublicResponse {})
+    let location = format!("/api/list/{}/{}", id, item_id);
+    let response = AddToListResponse {
+        id: item_id,
+        duplicate_of,
+    };
+    if merged.is_some() {
+        // Nothing was created, but still point to the item that was updated
+        Ok((
+            StatusCode::OK,
+            [(header::LOCATION, location)],
+            OkResponse::ok(response)?,
+        ))
+    } else {
+        created(location, response)
+    }
 }
 
 #[utoipa::path(
-    delete,
-    path = "/api/list/{id}/public",
+    patch,
+    path = "/api/list/{id}/{item}",
     responses(
-        (status = 200, description = "Sucess", body = OkRemovePublicResponse),
+        (status = 200, description = "Update Item", body = OkUpdateItemResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
+    request_body = UpdateItemRequest,
     params(
         ("id" = Uuid, Path, description = "List ID"),
+        ("item" = String, Path, description = "Item ID or external ID"),
     ),
     security(
         ("token" = [])
     )
 )]
 #[tracing::instrument(skip(state))]
-async fn remove_public(
+pub(crate) async fn update_item(
     state: State,
-    extract::Path(id): extract::Path<Uuid>,
     user: User,
-) -> Rsp<RemovePublicResponse> {
-    is_owner(&state.0.pool, user.id, id).await?;
+    Path((list, item)): Path<(Uuid, ItemRef)>,
+    Json(update): Json<UpdateItemRequest>,
+) -> Rsp<UpdateItemResponse> {
+    user.require_write()?;
+    item.check()?;
+    check_list(&state.0.pool, user.id, list, true).await?;
+    let item = item.resolve(&state.0.pool, list).await?;
+    let url = match update.url.as_deref() {
+        Some("") => Some(None),
+        Some(url) => {
+            check_url(url)?;
+            Some(Some(url))
+        }
+        None => None,
+    };
+    let unit = match update.unit.as_deref().map(str::trim) {
+        Some("") => Some(None),
+        Some(unit) => {
+            check_unit(&state.0.pool, list, unit).await?;
+            Some(Some(unit))
+        }
+        None => None,
+    };
+    if let Some(amount) = &update.amount {
+        check_amount(&state.0.pool, list, Some(amount), false).await?;
+    }
 
-    sqlx::query!("UPDATE lists SET pub = false WHERE id = $1", id)
-        .execute(&state.0.pool)
+    let name = match update.name.as_deref() {
+        Some(name) => Some(item_name(&state.0.pool, list, name).await?),
+        None => None,
+    };
+    let category = update
+        .category
+        .as_deref()
+        .map(str::trim)
+        .map(|category| (!category.is_empty()).then_some(category));
+
+    let mut tx = state.0.pool.begin().await?;
+
+    if let Some(name) = name {
+        sqlx::query!(
+            "UPDATE lists_content SET name = $1 WHERE list = $2 AND id = $3",
+            name,
+            list,
+            item
+        )
+        .execute(&mut *tx)
         .await?;
+    }
 
-    OkResponse::ok(RemovePublicResponse {})
-}
+    if let Some(amount) = &update.amount {
+        sqlx::query!(
+            "UPDATE lists_content SET amount = $1 WHERE list = $2 AND id = $3",
+            amount,
+            list,
+            item
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
 
-enum PublicError {
-    NotFound,
-    InternalError,
-}
+    if let Some(unit) = unit {
+        sqlx::query!(
+            "UPDATE lists_content SET unit = $1 WHERE list = $2 AND id = $3",
+            unit,
+            list,
+            item
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
 
-impl From<sqlx::Error> for PublicError {
-    fn from(e: sqlx::Error) -> Self {
-        tracing::error!("SQLX error: {:?}", e);
-        Self::InternalError
+    if let Some(category) = category {
+        sqlx::query!(
+            "UPDATE lists_content SET category = $1 WHERE list = $2 AND id = $3",
+            category,
+            list,
+            item
+        )
+        .execute(&mut *tx)
+        .await?;
     }
-}
 
-impl IntoResponse for PublicError {
-    fn into_response(self) -> axum::response::Response {
-        match self {
-            PublicError::NotFound => (StatusCode::NOT_FOUND, "List not found").into_response(),
-            PublicError::InternalError => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response()
-            }
-        }
+    if let Some(url) = url {
+        sqlx::query!(
+            "UPDATE lists_content SET url = $1 WHERE list = $2 AND id = $3",
+            url,
+            list,
+            item
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let (checked, maybe) = match update.status {
+        Some(ItemStatus::Active) => (Some(false), Some(false)),
+        Some(ItemStatus::Maybe) => (Some(false), Some(true)),
+        Some(ItemStatus::Checked) => (Some(true), None),
+        None => (update.checked, None),
+    };
+
+    if let Some(checked) = checked {
+        sqlx::query!(
+            r#"UPDATE lists_content
+                   SET checked = $1,
+                       checked_at = CASE WHEN NOT $1 THEN NULL
+                                         WHEN checked THEN checked_at
+                                         ELSE now() END,
+                       maybe = COALESCE($4, maybe)
+                   WHERE list = $2 AND id = $3"#,
+            checked,
+            list,
+            item,
+            maybe,
+        )
+        .execute(&mut *tx)
+        .await?;
     }
+
+    tx.commit().await?;
+
+    OkResponse::ok(UpdateItemResponse {})
 }
 
 #[utoipa::path(
-    get,
-    path = "/api/list/{id}/public",
+    patch,
+    path = "/api/list/{id}/items",
     responses(
-        (status = 200, description = "List", body = String, content_type = "text/html"),
-        (status = 404, description = "List Not Found", body = String),
-        (status = 500, description = "Internal Error", body = String),
+        (status = 200, description = "Number of updated items", body = OkAffectedResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
     ),
+    request_body = BulkUpdateRequest,
     params(
         ("id" = Uuid, Path, description = "List ID"),
     ),
+    security(
+        ("token" = [])
+    )
 )]
 #[tracing::instrument(skip(state))]
-async fn get_public_list(
+pub(crate) async fn bulk_update(
     state: State,
-    extract::Path(id): extract::Path<Uuid>,
-) -> Result<Markup, PublicError> {
-    let pb = sqlx::query!("SELECT pub FROM lists WHERE id = $1", id)
-        .fetch_one(&state.0.pool)
-        .await?;
+    user: User,
+    Path(list): Path<Uuid>,
+    Json(request): Json<BulkUpdateRequest>,
+) -> Rsp<AffectedResponse> {
+    user.require_write()?;
+    check_list(&state.0.pool, user.id, list, true).await?;
 
-    if !pb.r#pub.unwrap_or(false) {
-        return Err(PublicError::NotFound);
+    if let Some(amount) = request
+        .updates
+        .iter()
+        .filter_map(|update| update.amount.as_deref())
+        .find(|amount| amount.trim().is_empty())
+    {
+        check_amount(&state.0.pool, list, Some(amount), false).await?;
     }
 
-    let contents = sqlx::query!("SELECT name,amount FROM lists_content WHERE list = $1", id)
-        .fetch_all(&state.0.pool)
-        .await?;
+    let mut tx = state.0.pool.begin().await?;
 
-    Ok(maud::html! {
-        (maud::DOCTYPE)
-        html {
-            head {
-                meta charset="utf-8";
-                meta name="viewport" content="width=device-width, initial-scale=1";
-                link href="https://cdn.jsdelivr.net/npm/bootstrap@5.1.0/dist/css/bootstrap.min.css"
-                     integrity="sha384-KyZXEAg3QhqLMpG8r+8fhAXLRk2vvoC2f3B09zVXn8CA5QIVfZOJ3BCsw2P0p/We"
-                     rel="stylesheet" crossorigin="anonymous";
-            }
-            body {
-                ul .list-group.container.py-3 {
-                    @for item in contents {
-                        li .list-group-item.d-flex.gap-3.py-3 {
-                            (item.name)
-                            @if let Some(amount) = item.amount { (format!(" ({amount})")) }
-                        }
-                    }
+    let case_mode = list_case_mode(&state.0.pool, list).await?;
+
+    let mut updated = 0;
+    for update in request.updates {
+        updated += sqlx::query!(
+            r#"UPDATE lists_content
+                   SET name = COALESCE($1, name),
+                       amount = COALESCE($2, amount),
+                       checked = COALESCE($3, checked),
+                       checked_at = CASE WHEN $3 IS NULL THEN checked_at
+                                         WHEN NOT $3 THEN NULL
+                                         WHEN checked THEN checked_at
+                                         ELSE now() END,
+                       position = COALESCE($4, position)
+                   WHERE list = $5 AND id = $6"#,
+            update
+                .name
+                .as_deref()
+                .map(|name| apply_item_name(case_mode, name))
+                .transpose()?,
+            update.amount,
+            update.checked,
+            update.position,
+            list,
+            update.id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+
+    tx.commit().await?;
+
+    OkResponse::ok(AffectedResponse { affected: updated })
+}
+
+/// Maximum number of items created by a single [`import_text`] call
+const MAX_IMPORT_LINES: usize = 500;
+
+/// Separator between the name and the amount of an imported line when none is given
+const DEFAULT_IMPORT_DELIMITER: &str = " - ";
+
+/// Parses a line of a plain text list into a name and an optional amount.
+///
+/// Blank lines give `None` and a leading `-` or `*` bullet is removed. The amount follows the last
+/// `delimiter` of the line, so that `Flour - 500g` gives `Flour` and `500g` with the default
+/// delimiter. A bare bullet gives an empty name, which is reported by [`import_text`].
+fn parse_text_line<'a>(line: &'a str, delimiter: &str) -> Option<(&'a str, Option<&'a str>)> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    let line = match line.strip_prefix(&['-', '*'][..]) {
+        Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => rest.trim_start(),
+        _ => line,
+    };
+
+    let (name, amount) = match line.rsplit_once(delimiter) {
+        Some((name, amount)) => (name.trim(), Some(amount.trim())),
+        None => (line, None),
+    };
+
+    Some((name, amount.filter(|amount| !amount.is_empty())))
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ImportTextQuery {
+    delimiter: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/list/{id}/import-text",
+    responses(
+        (status = 200, description = "Imported and skipped lines", body = OkImportResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body(content = String, description = "One item per line, as `name` or `name - amount`", content_type = "text/plain"),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("delimiter" = Option<String>, Query, description = "Separator between the name and the amount, ` - ` by default"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state, text))]
+pub(crate) async fn import_text(
+    state: State,
+    user: User,
+    Path(list): Path<Uuid>,
+    extract::Query(query): extract::Query<ImportTextQuery>,
+    text: String,
+) -> Rsp<ImportResponse> {
+    user.require_write()?;
+
+    let delimiter = query
+        .delimiter
+        .as_deref()
+        .unwrap_or(DEFAULT_IMPORT_DELIMITER);
+    if delimiter.is_empty() {
+        return Err(Error::EmptyDelimiter);
+    }
+
+    check_list(&state.0.pool, user.id, list, true).await?;
+
+    let case_mode = list_case_mode(&state.0.pool, list).await?;
+
+    let mut items = Vec::new();
+    let mut skipped = 0;
+    let mut errors = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let (name, amount) = match parse_text_line(line, delimiter) {
+            Some(item) => item,
+            None => continue,
+        };
+
+        match apply_item_name(case_mode, name) {
+            Ok(name) => items.push((name, amount)),
+            Err(_) => {
+                skipped += 1;
+                if errors.len() < MAX_IMPORT_ERRORS {
+                    errors.push(ImportError {
+                        line: index + 1,
+                        reason: ImportErrorReason::EmptyName,
+                    });
                 }
             }
         }
+    }
+
+    if items.len() > MAX_IMPORT_LINES {
+        return Err(Error::BatchTooLarge);
+    }
+    // Blank amounts are already dropped by the parsing, so only missing ones need a check. Like
+    // items added one by one, they get the default amount of the list
+    if items.iter().any(|(_, amount)| amount.is_none()) {
+        check_amount(&state.0.pool, list, None, true).await?;
+    }
+
+    let mut tx = state.0.pool.begin().await?;
+
+    for (name, amount) in &items {
+        sqlx::query!(
+            r#"INSERT INTO lists_content (list, name, amount, position, creator)
+                   VALUES ($1, $2,
+                       COALESCE($3, (SELECT default_amount FROM lists WHERE id = $1)),
+                       (SELECT COALESCE(MAX(position) + $5, 0) FROM lists_content WHERE list = $1),
+                       $4)"#,
+            list,
+            name,
+            *amount,
+            user.id,
+            POSITION_GAP,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"INSERT INTO history (list, creator, name, amount, last_used)
+                   VALUES ($1, $2, $3::text::citext, $4, now())
+                   ON CONFLICT (list, creator, name) DO
+                   UPDATE SET last_used = now(), amount = EXCLUDED.amount"#,
+            list,
+            user.id,
+            name,
+            *amount,
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    OkResponse::ok(ImportResponse {
+        imported: items.len() as u64,
+        skipped,
+        errors,
+    })
+}
+
+/// Sets the checked state of every item of the list, returning the number of items that changed
+async fn set_all_checked(db: &PgPool, list: Uuid, checked: bool) -> Result<u64, Error> {
+    Ok(sqlx::query!(
+        r#"UPDATE lists_content
+               SET checked = $2,
+                   checked_at = CASE WHEN $2 THEN now() ELSE NULL END
+               WHERE list = $1 AND checked <> $2"#,
+        list,
+        checked
+    )
+    .execute(db)
+    .await?
+    .rows_affected())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/list/{id}/check-all",
+    responses(
+        (status = 200, description = "Number of newly checked items", body = OkAffectedResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn check_all(
+    state: State,
+    user: User,
+    Path(list): Path<Uuid>,
+) -> Rsp<AffectedResponse> {
+    user.require_write()?;
+    check_list(&state.0.pool, user.id, list, true).await?;
+
+    let affected = set_all_checked(&state.0.pool, list, true).await?;
+
+    OkResponse::ok(AffectedResponse { affected })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/list/{id}/uncheck-all",
+    responses(
+        (status = 200, description = "Number of newly unchecked items", body = OkAffectedResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn uncheck_all(
+    state: State,
+    user: User,
+    Path(list): Path<Uuid>,
+) -> Rsp<AffectedResponse> {
+    user.require_write()?;
+    check_list(&state.0.pool, user.id, list, true).await?;
+
+    let affected = set_all_checked(&state.0.pool, list, false).await?;
+
+    OkResponse::ok(AffectedResponse { affected })
+}
+
+/// Renumbers the items of a list from 0 following their current order, removing the gaps left
+/// by deleted items
+#[utoipa::path(
+    post,
+    path = "/api/list/{id}/compact",
+    responses(
+        (status = 200, description = "Number of moved items", body = OkAffectedResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn compact_positions(
+    state: State,
+    user: User,
+    Path(list): Path<Uuid>,
+) -> Rsp<AffectedResponse> {
+    user.require_write()?;
+    check_list(&state.0.pool, user.id, list, true).await?;
+
+    let affected = sqlx::query!(
+        r#"UPDATE lists_content
+               SET position = ordered.new_position
+               FROM (SELECT id, (row_number() OVER (ORDER BY position, id) - 1)::int AS new_position
+                         FROM lists_content
+                         WHERE list = $1) ordered
+               WHERE lists_content.id = ordered.id
+                   AND lists_content.position <> ordered.new_position"#,
+        list
+    )
+    .execute(&state.0.pool)
+    .await?
+    .rows_affected();
+
+    OkResponse::ok(AffectedResponse { affected })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/list/{id}/{item}/position",
+    responses(
+        (status = 200, description = "New position of the item", body = OkPositionResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = PositionRequest,
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("item" = String, Path, description = "Item ID or external ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn move_item(
+    state: State,
+    user: User,
+    Path((list, item)): Path<(Uuid, ItemRef)>,
+    Json(request): Json<PositionRequest>,
+) -> Rsp<PositionResponse> {
+    user.require_write()?;
+    item.check()?;
+    check_list(&state.0.pool, user.id, list, true).await?;
+    let item = item.resolve(&state.0.pool, list).await?;
+
+    let position = move_to(&state.0.pool, LIST_ITEMS, list, item, request).await?;
+
+    OkResponse::ok(PositionResponse { position })
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ReceiptQuery {
+    since: Option<DateTime<Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/list/{id}/receipt",
+    responses(
+        (status = 200, description = "Checked items", body = OkReceiptResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("since" = Option<DateTime<Utc>>, Query, description = "Only items checked after this time"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn receipt(
+    state: State,
+    ListUser(user): ListUser,
+    Path(id): Path<Uuid>,
+    extract::Query(query): extract::Query<ReceiptQuery>,
+) -> Rsp<ReceiptResponse> {
+    check_list(&state.0.pool, user.id, id, false).await?;
+
+    let items = sqlx::query!(
+        r#"SELECT id, name, amount, checked_at as "checked_at!"
+               FROM lists_content
+               WHERE list = $1
+                   AND checked
+                   AND checked_at IS NOT NULL
+                   AND ($2::timestamptz IS NULL OR checked_at > $2)
+               ORDER BY checked_at"#,
+        id,
+        query.since,
+    )
+    .fetch_all(&state.0.pool)
+    .await?;
+
+    OkResponse::ok(ReceiptResponse {
+        items: items
+            .into_iter()
+            .map(|row| ReceiptItem {
+                id: row.id,
+                name: row.name,
+                amount: row.amount,
+                checked_at: row.checked_at,
+            })
+            .collect(),
+    })
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ChangesQuery {
+    since: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/list/{id}/changes",
+    responses(
+        (status = 200, description = "Changes to the list items", body = OkListChangesResponse),
+        (status = 400, description = "Invalid request, or `since` older than the kept changes", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("since" = Option<i64>, Query, description = "`next_since` of the previous request, all items if absent"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn list_changes(
+    state: State,
+    ListUser(user): ListUser,
+    Path(id): Path<Uuid>,
+    extract::Query(query): extract::Query<ChangesQuery>,
+) -> Rsp<ListChangesResponse> {
+    check_list(&state.0.pool, user.id, id, false).await?;
+
+    // Changes of a list commit in the order of their counter (see the item_changes migration), so
+    // every change up to the current counter is visible to the queries below. Changes committed
+    // in the meantime may be returned too, and are returned again by the next request.
+    let counters = sqlx::query!("SELECT change_seq, pruned_seq FROM lists WHERE id = $1", id)
+        .fetch_one(&state.0.pool)
+        .await?;
+    let readonly = list_readonly(&state.0.pool, user.id, id).await?;
+
+    if let Some(since) = query.since {
+        if since < counters.pruned_seq {
+            return Err(Error::ChangesExpired);
+        }
+    }
+
+    let items = sqlx::query!(
+        r#"SELECT lists_content.id, external_id, lists_content.name, amount, unit, category,
+                  checked, maybe, position, url, creator, accounts.name::text as "creator_name!"
+               FROM lists_content
+               JOIN accounts ON accounts.id = lists_content.creator
+               WHERE list = $1 AND ($2::bigint IS NULL OR change_seq > $2)
+               ORDER BY position, lists_content.id"#,
+        id,
+        query.since,
+    )
+    .fetch_all(&state.0.pool)
+    .await?;
+
+    let deleted = match query.since {
+        Some(since) => {
+            sqlx::query_as!(
+                DeletedItem,
+                "SELECT id, external_id FROM deleted_items WHERE list = $1 AND change_seq > $2",
+                id,
+                since,
+            )
+            .fetch_all(&state.0.pool)
+            .await?
+        }
+        None => Vec::new(),
+    };
+
+    OkResponse::ok(ListChangesResponse {
+        items: items
+            .into_iter()
+            .map(|row| Item {
+                id: row.id,
+                external_id: row.external_id,
+                name: row.name,
+                amount: row.amount,
+                unit: row.unit,
+                category: row.category,
+                checked: row.checked,
+                status: item_status(row.checked, row.maybe),
+                position: row.position,
+                url: row.url,
+                added_by: row.creator,
+                added_by_name: row.creator_name,
+                editable: item_editable(readonly),
+            })
+            .collect(),
+        deleted,
+        next_since: counters.change_seq,
     })
 }
+
+/// Escapes the wildcards of a `LIKE` pattern, with `\` as the escape character
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct SearchItemsQuery {
+    q: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/list/{id}/search",
+    responses(
+        (status = 200, description = "Matching items", body = OkSearchItemsResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("q" = String, Query, description = "Part of the item name"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn search_items(
+    state: State,
+    ListUser(user): ListUser,
+    Path(id): Path<Uuid>,
+    extract::Query(query): extract::Query<SearchItemsQuery>,
+) -> Rsp<SearchItemsResponse> {
+    check_list(&state.0.pool, user.id, id, false).await?;
+
+    let readonly = list_readonly(&state.0.pool, user.id, id).await?;
+
+    let items = sqlx::query!(
+        r#"SELECT lists_content.id, external_id, lists_content.name, amount, unit, category,
+                  checked, maybe, position, url, creator, accounts.name::text as "creator_name!"
+               FROM lists_content
+               JOIN accounts ON accounts.id = lists_content.creator
+               WHERE list = $1 AND lists_content.name ILIKE '%' || $2 || '%' ESCAPE '\'
+               ORDER BY position, lists_content.id"#,
+        id,
+        escape_like(&query.q),
+    )
+    .fetch_all(&state.0.pool)
+    .await?
+    .into_iter()
+    .map(|row| Item {
+        id: row.id,
+        external_id: row.external_id,
+        name: row.name,
+        amount: row.amount,
+        unit: row.unit,
+        category: row.category,
+        checked: row.checked,
+        status: item_status(row.checked, row.maybe),
+        position: row.position,
+        url: row.url,
+        added_by: row.creator,
+        added_by_name: row.creator_name,
+        editable: item_editable(readonly),
+    })
+    .collect();
+
+    OkResponse::ok(SearchItemsResponse { items })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/list/{id}/{item}",
+    responses(
+        (status = 200, description = "Delete Item", body = OkDeleteItemResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("item" = String, Path, description = "Item ID or external ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+pub(crate) async fn delete_item(
+    state: State,
+    user: User,
+    Path((list, item)): Path<(Uuid, ItemRef)>,
+) -> Rsp<DeleteItemResponse> {
+    user.require_write()?;
+    item.check()?;
+    check_list(&state.0.pool, user.id, list, true).await?;
+    let item = item.resolve(&state.0.pool, list).await?;
+
+    let mut tx = state.0.pool.begin().await?;
+
+    // Give the amount back to the pantry item the entry came from. Amounts that are not numbers
+    // ("a handful") make convert_to_numeric return NULL, they count as 0 so that the pantry amount
+    // is left unchanged instead of being nulled
+    sqlx::query!(
+        "UPDATE pantry_content
+        SET amount = amount + COALESCE(
+            (SELECT convert_to_numeric(lists_content.amount)
+            FROM lists_content
+            WHERE lists_content.list = $1 AND lists_content.id = $2), 0)
+        WHERE
+            pantry_content.item =
+                (SELECT lists_content.from_pantry
+                 FROM lists_content
+                 WHERE lists_content.list = $1 AND lists_content.id = $2)",
+        list,
+        item
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM lists_content WHERE list = $1 AND id = $2",
+        list,
+        item
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    OkResponse::ok(DeleteItemResponse {})
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/list/{id}/{item}/to-pantry",
+    responses(
+        (status = 200, description = "Item Stocked", body = OkToPantryResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 404, description = "Unknown Item", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = ToPantryRequest,
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("item" = String, Path, description = "Item ID or external ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn item_to_pantry(
+    state: State,
+    user: User,
+    Path((list, item)): Path<(Uuid, ItemRef)>,
+    Json(request): Json<ToPantryRequest>,
+) -> Rsp<ToPantryResponse> {
+    user.require_write()?;
+    item.check()?;
+    check_list(&state.0.pool, user.id, list, true).await?;
+    let item = item.resolve(&state.0.pool, list).await?;
+
+    let mut tx = state.0.pool.begin().await?;
+
+    // As in delete_item amounts that are not numbers count as 0
+    let entry = sqlx::query!(
+        r#"SELECT name, COALESCE(convert_to_numeric(amount), 0)::float8 as "amount!", from_pantry
+               FROM lists_content
+               WHERE list = $1 AND id = $2"#,
+        list,
+        item
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(Error::ItemNotFound)?;
+
+    // Stock the pantry item the entry came from, or else the one with the same name
+    let existing = sqlx::query!(
+        r#"UPDATE pantry_content
+               SET amount = amount + $4::float8::numeric, target = $5::float8
+               WHERE list = $1 AND item = COALESCE(
+                   $3::int,
+                   (SELECT item FROM pantry_content
+                        WHERE list = $1 AND lower(name) = lower($2)))
+               RETURNING item, amount::float8 as "amount!""#,
+        list,
+        entry.name,
+        entry.from_pantry,
+        entry.amount,
+        request.target,
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (pantry_item, amount) = match existing {
+        Some(row) => (row.item, row.amount),
+        None => {
+            let inserted = sqlx::query!(
+                r#"INSERT INTO pantry_content (list, name, amount, target, position)
+                       VALUES ($1, $2, $3::float8, $4::float8,
+                           (SELECT COALESCE(MAX(position) + 1, 0) FROM pantry_content WHERE list = $1))
+                       RETURNING item, amount::float8 as "amount!""#,
+                list,
+                entry.name,
+                entry.amount,
+                request.target,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            (inserted.item, inserted.amount)
+        }
+    };
+
+    // The amount was already given to the pantry, so delete_item must not refund it again
+    if request.remove {
+        sqlx::query!(
+            "DELETE FROM lists_content WHERE list = $1 AND id = $2",
+            list,
+            item
+        )
+        .execute(&mut *tx)
+        .await?;
+    } else {
+        sqlx::query!(
+            "UPDATE lists_content SET from_pantry = NULL WHERE list = $1 AND id = $2",
+            list,
+            item
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    OkResponse::ok(ToPantryResponse {
+        pantry_item,
+        amount,
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/list/{id}",
+    responses(
+        (status = 200, description = "List Deleted", body = OkDeleteListResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn delete_list(
+    state: State,
+    user: User,
+    Path(id): Path<Uuid>,
+) -> Rsp<DeleteListResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+    let mut tx = state.0.pool.begin().await?;
+
+    sqlx::query!("DELETE FROM list_sharing WHERE list = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM lists_content WHERE list = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM deleted_items WHERE list = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM share_links WHERE list = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM list_seen WHERE list = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM list_tags WHERE list = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM guest_tokens WHERE list = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM history WHERE list = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM lists WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    OkResponse::ok(DeleteListResponse {})
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/list/{id}/public",
+    responses(
+        (status = 200, description = "Sucess", body = OkSetPublicResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn set_public(state: State, Path(id): Path<Uuid>, user: User) -> Rsp<SetPublicResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+
+    sqlx::query!("UPDATE lists SET pub = true WHERE id = $1", id)
+        .execute(&state.0.pool)
+        .await?;
+
+    OkResponse::ok(SetPublicResponse {
+        short_code: Some(assign_short_code(&state.0.pool, id).await?),
+    })
+}
+
+/// Letters and digits that can't be mistaken for one another, one per 5 bits of a short code
+const SHORT_CODE_ALPHABET: &[u8; 32] = b"abcdefghijkmnpqrstuvwxyz23456789";
+
+/// Number of random codes tried before giving up on finding an unused one
+const SHORT_CODE_ATTEMPTS: usize = 5;
+
+/// Encodes 40 random bits as an 8 characters short code
+fn encode_short_code(bytes: [u8; 5]) -> String {
+    let bits = bytes
+        .iter()
+        .fold(0u64, |bits, &byte| (bits << 8) | u64::from(byte));
+
+    (0..8)
+        .rev()
+        .map(|i| SHORT_CODE_ALPHABET[((bits >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+/// Returns the short code of the list, generating one if it has none yet. A list keeps its code
+/// when it stops being public, so that links work again if it is published again
+async fn assign_short_code(db: &PgPool, id: Uuid) -> Result<String, Error> {
+    for _ in 0..SHORT_CODE_ATTEMPTS {
+        let existing = sqlx::query!("SELECT short_code FROM lists WHERE id = $1", id)
+            .fetch_one(db)
+            .await?
+            .short_code;
+        if let Some(code) = existing {
+            return Ok(code);
+        }
+
+        let bytes = sqlx::query!(r#"SELECT gen_random_bytes(5) as "bytes!""#)
+            .fetch_one(db)
+            .await?
+            .bytes;
+        let code = encode_short_code(bytes.try_into().map_err(|_| Error::Internal)?);
+
+        // Does nothing if the code is taken, or if a concurrent call already gave a code
+        let assigned = match sqlx::query!(
+            r#"UPDATE lists SET short_code = $1
+                   WHERE id = $2 AND short_code IS NULL
+                       AND NOT EXISTS(SELECT 1 FROM lists WHERE short_code = $1)"#,
+            code,
+            id
+        )
+        .execute(db)
+        .await
+        {
+            Ok(result) => result.rows_affected(),
+            // Another list took the same code after the check, so try another one
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => 0,
+            Err(e) => return Err(e.into()),
+        };
+        if assigned == 1 {
+            return Ok(code);
+        }
+    }
+
+    tracing::error!("Could not find an unused short code for {id}");
+    Err(Error::Internal)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/list/{id}/public",
+    responses(
+        (status = 200, description = "Sucess", body = OkRemovePublicResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn remove_public(
+    state: State,
+    Path(id): Path<Uuid>,
+    user: User,
+) -> Rsp<RemovePublicResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+
+    sqlx::query!("UPDATE lists SET pub = false WHERE id = $1", id)
+        .execute(&state.0.pool)
+        .await?;
+
+    OkResponse::ok(RemovePublicResponse {})
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/list/{id}/archive",
+    responses(
+        (status = 200, description = "Sucess", body = OkArchiveListResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn archive_list(state: State, Path(id): Path<Uuid>, user: User) -> Rsp<ArchiveListResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+
+    sqlx::query!("UPDATE lists SET archived = true WHERE id = $1", id)
+        .execute(&state.0.pool)
+        .await?;
+
+    OkResponse::ok(ArchiveListResponse {})
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/list/{id}/archive",
+    responses(
+        (status = 200, description = "Sucess", body = OkUnarchiveListResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn unarchive_list(
+    state: State,
+    Path(id): Path<Uuid>,
+    user: User,
+) -> Rsp<UnarchiveListResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+
+    sqlx::query!("UPDATE lists SET archived = false WHERE id = $1", id)
+        .execute(&state.0.pool)
+        .await?;
+
+    OkResponse::ok(UnarchiveListResponse {})
+}
+
+/// Tags of the list, in alphabetical order
+async fn list_tags(db: &PgPool, id: Uuid) -> Result<Vec<String>, Error> {
+    Ok(
+        sqlx::query!("SELECT tag FROM list_tags WHERE list = $1 ORDER BY tag", id)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|row| row.tag)
+            .collect(),
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/list/{id}/tags/{tag}",
+    responses(
+        (status = 200, description = "Tags of the list", body = OkListTagsResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("tag" = String, Path, description = "Tag to add, case insensitive"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn add_tag(
+    state: State,
+    Path((id, tag)): Path<(Uuid, String)>,
+    user: User,
+) -> Rsp<ListTagsResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+
+    let tag = normalize_tag(&tag);
+    if tag.is_empty() || tag.chars().count() > MAX_TAG_LENGTH {
+        return Err(Error::InvalidTag);
+    }
+
+    sqlx::query!(
+        "INSERT INTO list_tags (list, tag) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        id,
+        tag
+    )
+    .execute(&state.0.pool)
+    .await?;
+
+    OkResponse::ok(ListTagsResponse {
+        tags: list_tags(&state.0.pool, id).await?,
+    })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/list/{id}/tags/{tag}",
+    responses(
+        (status = 200, description = "Tags of the list", body = OkListTagsResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("tag" = String, Path, description = "Tag to remove, case insensitive"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn remove_tag(
+    state: State,
+    Path((id, tag)): Path<(Uuid, String)>,
+    user: User,
+) -> Rsp<ListTagsResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+
+    sqlx::query!(
+        "DELETE FROM list_tags WHERE list = $1 AND tag = $2",
+        id,
+        normalize_tag(&tag)
+    )
+    .execute(&state.0.pool)
+    .await?;
+
+    OkResponse::ok(ListTagsResponse {
+        tags: list_tags(&state.0.pool, id).await?,
+    })
+}
+
+pub(crate) enum PublicError {
+    NotFound,
+    InternalError,
+}
+
+impl From<sqlx::Error> for PublicError {
+    fn from(e: sqlx::Error) -> Self {
+        tracing::error!("SQLX error: {:?}", e);
+        Self::InternalError
+    }
+}
+
+impl IntoResponse for PublicError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            PublicError::NotFound => (StatusCode::NOT_FOUND, "List not found").into_response(),
+            PublicError::InternalError => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response()
+            }
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/list/{id}/public",
+    responses(
+        (status = 200, description = "List", body = String, content_type = "text/html"),
+        (status = 304, description = "List Not Modified"),
+        (status = 404, description = "List Not Found", body = String, content_type = "text/plain"),
+        (status = 500, description = "Internal Error", body = String, content_type = "text/plain"),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+)]
+#[tracing::instrument(skip(state))]
+async fn get_public_list(
+    state: State,
+    Path(id): Path<Uuid>,
+    if_modified_since: Option<TypedHeader<IfModifiedSince>>,
+) -> Result<axum::response::Response, PublicError> {
+    public_list_page(&state.0, id, if_modified_since).await
+}
+
+/// Same page as the public list, found by the short code given when the list was made public
+#[utoipa::path(
+    get,
+    path = "/api/p/{code}",
+    responses(
+        (status = 200, description = "List", body = String, content_type = "text/html"),
+        (status = 304, description = "List Not Modified"),
+        (status = 404, description = "List Not Found", body = String, content_type = "text/plain"),
+        (status = 500, description = "Internal Error", body = String, content_type = "text/plain"),
+    ),
+    params(
+        ("code" = String, Path, description = "Short code of the list"),
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn get_public_list_by_code(
+    state: State,
+    Path(code): Path<String>,
+    if_modified_since: Option<TypedHeader<IfModifiedSince>>,
+) -> Result<axum::response::Response, PublicError> {
+    // Codes are generated in lower case, but may have been typed in upper case
+    let id = sqlx::query!(
+        "SELECT id FROM lists WHERE short_code = $1",
+        code.to_lowercase()
+    )
+    .fetch_optional(&state.0.pool)
+    .await?
+    .ok_or(PublicError::NotFound)?
+    .id;
+
+    public_list_page(&state.0, id, if_modified_since).await
+}
+
+async fn public_list_page(
+    state: &KabalistState,
+    id: Uuid,
+    if_modified_since: Option<TypedHeader<IfModifiedSince>>,
+) -> Result<axum::response::Response, PublicError> {
+    let pb = sqlx::query!(
+        "SELECT pub, description, updated_at FROM lists WHERE id = $1",
+        id
+    )
+    .fetch_one(&state.pool)
+    .await?;
+
+    if !pb.r#pub.unwrap_or(false) {
+        return Err(PublicError::NotFound);
+    }
+
+    let last_modified = SystemTime::from(pb.updated_at);
+    if let Some(TypedHeader(since)) = if_modified_since {
+        if !since.is_modified(last_modified) {
+            return Ok(StatusCode::NOT_MODIFIED.into_response());
+        }
+    }
+
+    let contents = sqlx::query!(
+        "SELECT name,amount,url FROM lists_content WHERE list = $1 ORDER BY position, id",
+        id
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    let config = &state.config;
+
+    // The page is readable by anyone: user provided content must only be spliced with
+    // `(...)` so that maud escapes it, never through `PreEscaped`.
+    let page = maud::html! {
+        (maud::DOCTYPE)
+        html {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { (config.public_title) }
+                @if config.public_css_url == BOOTSTRAP_CSS_URL {
+                    link href=(BOOTSTRAP_CSS_URL)
+                         integrity="sha384-KyZXEAg3QhqLMpG8r+8fhAXLRk2vvoC2f3B09zVXn8CA5QIVfZOJ3BCsw2P0p/We"
+                         rel="stylesheet" crossorigin="anonymous";
+                } @else if !config.public_css_url.is_empty() {
+                    link href=(config.public_css_url) rel="stylesheet";
+                }
+                @if let Some(css) = &config.public_inline_css {
+                    // Comes from the server configuration, not from users
+                    style { (maud::PreEscaped(css)) }
+                }
+            }
+            body {
+                @if let Some(description) = pb.description {
+                    p .container.pt-3.text-muted { (description) }
+                }
+                ul .list-group.container.py-3 {
+                    @for item in contents {
+                        li .list-group-item.d-flex.gap-3.py-3 {
+                            @if let Some(url) = &item.url {
+                                a href=(url) rel="noopener noreferrer" { (item.name) }
+                            } @else {
+                                (item.name)
+                            }
+                            @if let Some(amount) = item.amount { (format!(" ({amount})")) }
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    Ok((TypedHeader(LastModified::from(last_modified)), page).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use kabalist_client::RspData;
+    use kabalist_types::{HistorySuggestion, ItemUpdate};
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::tests::{assert_api_error, client, spawn, state, PASSWORD};
+
+    #[sqlx::test(migrations = false)]
+    async fn insert_at_position(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        owner.add(&list, "Eggs", None).await.unwrap();
+        owner.add(&list, "Milk", None).await.unwrap();
+        // A free position is used as is, a taken one pushes the items from there on
+        owner
+            .add_at(&list, "Flour", None, POSITION_GAP / 2)
+            .await
+            .unwrap();
+        owner
+            .add_at(&list, "Butter", None, POSITION_GAP / 2)
+            .await
+            .unwrap();
+
+        let items = owner.read(&list).await.unwrap().items;
+        let names: Vec<_> = items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["Eggs", "Butter", "Flour", "Milk"]);
+        let positions: Vec<_> = items.iter().map(|item| item.position).collect();
+        let gap = POSITION_GAP;
+        assert_eq!(positions, [0, gap / 2, gap + gap / 2, 2 * gap]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn items_from_other_lists(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let groceries = owner.create_list("Groceries").await.unwrap().id;
+        let hardware = owner.create_list("Hardware").await.unwrap().id;
+        let eggs = owner.add(&groceries, "Eggs", None).await.unwrap().id;
+        let nails = owner.add(&hardware, "Nails", None).await.unwrap().id;
+
+        assert_api_error(
+            owner
+                .update_item(&groceries, nails, Some("Screws"), None)
+                .await,
+            Error::ItemNotFound,
+        );
+        assert_api_error(
+            owner.delete_item(&groceries, nails).await,
+            Error::ItemNotFound,
+        );
+        assert_api_error(
+            owner
+                .move_item(
+                    &groceries,
+                    nails,
+                    PositionRequest {
+                        before: Some(eggs),
+                        after: None,
+                    },
+                )
+                .await,
+            Error::ItemNotFound,
+        );
+
+        let items = owner.read(&hardware).await.unwrap().items;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "Nails");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn non_positive_item_ids(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        let eggs = owner.add(&list, "Eggs", None).await.unwrap().id;
+
+        for item in [0, -1] {
+            assert_api_error(
+                owner.update_item(&list, item, Some("Milk"), None).await,
+                Error::InvalidItemId,
+            );
+            assert_api_error(owner.delete_item(&list, item).await, Error::InvalidItemId);
+            assert_api_error(
+                owner
+                    .move_item(
+                        &list,
+                        item,
+                        PositionRequest {
+                            before: Some(eggs),
+                            after: None,
+                        },
+                    )
+                    .await,
+                Error::InvalidItemId,
+            );
+        }
+    }
+
+    async fn positions(client: &kabalist_client::Client, list: &Uuid) -> Vec<(String, i32)> {
+        client
+            .read(list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| (item.name, item.position))
+            .collect()
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn move_item(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let eggs = owner.add(&list, "Eggs", None).await.unwrap().id;
+        let milk = owner.add(&list, "Milk", None).await.unwrap().id;
+        let flour = owner.add(&list, "Flour", None).await.unwrap().id;
+        let before = |item| PositionRequest {
+            before: Some(item),
+            after: None,
+        };
+        let after = |item| PositionRequest {
+            before: None,
+            after: Some(item),
+        };
+        let gap = POSITION_GAP;
+
+        // Between two items
+        let moved = owner.move_item(&list, flour, before(milk)).await.unwrap();
+        assert_eq!(moved.position, gap / 2);
+        // To the end of the list
+        owner.move_item(&list, eggs, after(milk)).await.unwrap();
+        assert_eq!(
+            positions(&owner, &list).await,
+            [
+                ("Flour".to_string(), gap / 2),
+                ("Milk".to_string(), gap),
+                ("Eggs".to_string(), 2 * gap),
+            ]
+        );
+
+        // Between two adjacent items, the following ones are pushed to make room
+        owner
+            .add_at(&list, "Butter", None, gap / 2 + 1)
+            .await
+            .unwrap();
+        owner.move_item(&list, eggs, after(flour)).await.unwrap();
+        assert_eq!(
+            positions(&owner, &list).await,
+            [
+                ("Flour".to_string(), gap / 2),
+                ("Eggs".to_string(), gap),
+                ("Butter".to_string(), gap + gap / 2 + 1),
+                ("Milk".to_string(), 2 * gap),
+            ]
+        );
+
+        for request in [
+            PositionRequest {
+                before: Some(milk),
+                after: Some(flour),
+            },
+            PositionRequest {
+                before: None,
+                after: None,
+            },
+            before(eggs),
+        ] {
+            assert_api_error(
+                owner.move_item(&list, eggs, request).await,
+                Error::InvalidPosition,
+            );
+        }
+        assert_api_error(
+            owner.move_item(&list, eggs, before(eggs + 100)).await,
+            Error::ItemNotFound,
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn compaction(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let mut items = Vec::new();
+        for name in ["Eggs", "Milk", "Bread", "Salt", "Rice"] {
+            items.push(owner.add(&list, name, None).await.unwrap().id);
+        }
+        owner.delete_item(&list, items[1]).await.unwrap();
+        owner.delete_item(&list, items[3]).await.unwrap();
+
+        assert_eq!(owner.compact(&list).await.unwrap().affected, 2);
+        assert_eq!(owner.compact(&list).await.unwrap().affected, 0);
+
+        let positions: Vec<_> = owner
+            .read(&list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| (item.name, item.position))
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                ("Eggs".to_string(), 0),
+                ("Bread".to_string(), 1),
+                ("Rice".to_string(), 2),
+            ]
+        );
+
+        // Items added afterwards still go last
+        owner.add(&list, "Flour", None).await.unwrap();
+        let last = owner.read(&list).await.unwrap().items.pop().unwrap();
+        assert_eq!(
+            (last.name.as_str(), last.position),
+            ("Flour", 2 + POSITION_GAP)
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn external_ids(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let token = kabalist_client::login(&url, "owner", PASSWORD)
+            .await
+            .unwrap()
+            .token;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        owner.add(&list, "Eggs", None).await.unwrap();
+        owner.add(&list, "Milk", None).await.unwrap();
+        let items = owner.read(&list).await.unwrap().items;
+        assert_ne!(items[0].external_id, items[1].external_id);
+
+        // The client only knows about integer ids
+        let http = reqwest::Client::new();
+        let item_url = |item: Uuid| format!("{}/api/list/{}/{}", url, list, item);
+        let update: RspData<UpdateItemResponse> = http
+            .patch(item_url(items[0].external_id))
+            .bearer_auth(&token)
+            .json(&UpdateItemRequest {
+                name: Some("Brown eggs".into()),
+                amount: None,
+                unit: None,
+                checked: None,
+                status: None,
+                url: None,
+                category: None,
+            })
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        Result::from(update).unwrap();
+        let delete = |item| http.delete(item_url(item)).bearer_auth(&token).send();
+        let deleted: RspData<DeleteItemResponse> = delete(items[1].external_id)
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        Result::from(deleted).unwrap();
+        let unknown: RspData<DeleteItemResponse> =
+            delete(Uuid::new_v4()).await.unwrap().json().await.unwrap();
+        assert_eq!(
+            Result::from(unknown).unwrap_err().code,
+            Error::ItemNotFound as usize
+        );
+
+        let after = owner.read(&list).await.unwrap().items;
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].name, "Brown eggs");
+        assert_eq!(after[0].id, items[0].id);
+        assert_eq!(after[0].external_id, items[0].external_id);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn list_names(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+
+        assert_api_error(owner.create_list("").await, Error::InvalidListName);
+        assert_api_error(owner.create_list(" \t\n").await, Error::InvalidListName);
+        // The length is counted in characters rather than bytes
+        let name = "é".repeat(MAX_LIST_NAME_LENGTH + 1);
+        assert_api_error(owner.create_list(&name).await, Error::ListNameTooLong);
+        owner.create_list(&name[2..]).await.unwrap();
+
+        let list = owner.create_list("  Groceries ").await.unwrap().id;
+        assert_eq!(
+            owner.lists().await.unwrap().results[&list].name,
+            "Groceries"
+        );
+        // Duplicates are detected on the trimmed name
+        assert_api_error(
+            owner.create_list("Groceries").await,
+            Error::ListAlreadyExists,
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn list_names_are_unique_per_owner(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, other_client) = client(&state, &url, "other").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+
+        let groceries = owner.create_list("Groceries").await.unwrap().id;
+        assert_ne!(
+            other_client.create_list("Groceries").await.unwrap().id,
+            groceries
+        );
+        assert_api_error(
+            owner.create_list("groceries").await,
+            Error::ListAlreadyExists,
+        );
+
+        // The unique index is scoped to the owner
+        let (definition,): (String,) =
+            sqlx::query_as("SELECT indexdef FROM pg_indexes WHERE indexname = 'lists_owner_name'")
+                .fetch_one(&state.pool)
+                .await
+                .unwrap();
+        assert!(
+            definition.starts_with("CREATE UNIQUE INDEX"),
+            "{}",
+            definition
+        );
+        assert!(
+            definition.ends_with("(owner, lower(name))"),
+            "{}",
+            definition
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn concurrent_list_creation(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+
+        // Both requests may pass the duplicate check before either inserts, the unique index
+        // must still let only one of them through
+        for name in ["Groceries", "Hardware", "Books", "Garden"] {
+            let upper = name.to_uppercase();
+            let (first, second) = tokio::join!(owner.create_list(name), owner.create_list(&upper));
+            match (first, second) {
+                (Ok(_), Err(e)) | (Err(e), Ok(_)) => {
+                    assert_api_error::<()>(Err(e), Error::ListAlreadyExists)
+                }
+                (first, second) => panic!("{:?} and {:?}", first, second),
+            }
+        }
+        assert_eq!(owner.lists().await.unwrap().results.len(), 4);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn list_limit(db: PgPool) {
+        let mut state = state(db).await;
+        Arc::get_mut(&mut state).unwrap().config.max_lists_per_user = Some(2);
+        let url = spawn(state.clone()).await;
+        let (_, other) = client(&state, &url, "other").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+
+        let groceries = owner.create_list("Groceries").await.unwrap().id;
+        let hardware = owner.create_list("Hardware").await.unwrap().id;
+        // Archived lists still count
+        owner.archive_list(&hardware).await.unwrap();
+        assert_api_error(owner.create_list("Garden").await, Error::ListLimitReached);
+        // The limit is per account
+        other.create_list("Garden").await.unwrap();
+
+        owner.delete_list(&groceries).await.unwrap();
+        owner.create_list("Garden").await.unwrap();
+
+        // Both requests may count the lists before either inserts, only one may get the last slot
+        let (first, second) = tokio::join!(other.create_list("Tools"), other.create_list("Games"));
+        match (first, second) {
+            (Ok(_), Err(e)) | (Err(e), Ok(_)) => {
+                assert_api_error::<()>(Err(e), Error::ListLimitReached)
+            }
+            (first, second) => panic!("{:?} and {:?}", first, second),
+        }
+        assert_eq!(other.lists().await.unwrap().results.len(), 2);
+    }
+
+    #[test]
+    fn urls_must_be_http() {
+        assert!(check_url("https://example.com").is_ok());
+        assert!(check_url("HTTP://example.com").is_ok());
+        assert!(check_url("javascript:alert(1)").is_err());
+        assert!(check_url("example.com").is_err());
+    }
+
+    /// Fetches the public page of `list`
+    async fn public_page(url: &str, list: &Uuid) -> String {
+        reqwest::get(format!("{}/api/list/{}/public", url, list))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn public_page_escapes_items(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+
+        let list = owner.create_list("Public").await.unwrap().id;
+        owner
+            .add(&list, "<script>alert(1)</script>", Some(")</li><b>&"))
+            .await
+            .unwrap();
+        let linked = owner.add(&list, "Linked", None).await.unwrap().id;
+        sqlx::query("UPDATE lists_content SET url = $1 WHERE id = $2")
+            .bind(r#"https://example.com/"><script>alert(2)</script>"#)
+            .bind(linked)
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        owner.set_public(&list).await.unwrap();
+
+        let page = public_page(&url, &list).await;
+        assert!(!page.contains("<script>"), "{}", page);
+        assert!(!page.contains("<b>"), "{}", page);
+        assert!(
+            page.contains("&lt;script&gt;alert(1)&lt;/script&gt;"),
+            "{}",
+            page
+        );
+        assert!(page.contains(" ()&lt;/li&gt;&lt;b&gt;&amp;)"), "{}", page);
+        assert!(
+            page.contains(r#"href="https://example.com/&quot;&gt;&lt;script&gt;alert(2)"#),
+            "{}",
+            page
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn public_page_escapes_description(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+
+        let list = owner.create_list("Public").await.unwrap().id;
+        sqlx::query("UPDATE lists SET description = $1 WHERE id = $2")
+            .bind("<img src=x onerror=alert(1)>")
+            .bind(list)
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        owner.set_public(&list).await.unwrap();
+
+        let page = public_page(&url, &list).await;
+        assert!(!page.contains("<img"), "{}", page);
+        assert!(
+            page.contains("&lt;img src=x onerror=alert(1)&gt;"),
+            "{}",
+            page
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn public_page_branding(db: PgPool) {
+        let mut state = state(db).await;
+        let config = &mut Arc::get_mut(&mut state).unwrap().config;
+        config.public_title = "Family <lists>".into();
+        config.public_css_url = "https://example.com/style.css".into();
+        config.public_inline_css = Some("body { color: red; }".into());
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Public").await.unwrap().id;
+        owner.set_public(&list).await.unwrap();
+
+        let page = public_page(&url, &list).await;
+        assert!(
+            page.contains("<title>Family &lt;lists&gt;</title>"),
+            "{}",
+            page
+        );
+        assert!(
+            page.contains(r#"<link href="https://example.com/style.css" rel="stylesheet">"#),
+            "{}",
+            page
+        );
+        assert!(
+            page.contains("<style>body { color: red; }</style>"),
+            "{}",
+            page
+        );
+        assert!(!page.contains(BOOTSTRAP_CSS_URL), "{}", page);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn public_page_default_branding(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Public").await.unwrap().id;
+        owner.set_public(&list).await.unwrap();
+
+        let page = public_page(&url, &list).await;
+        assert!(page.contains("<title>KabaList</title>"), "{}", page);
+        assert!(page.contains(BOOTSTRAP_CSS_URL), "{}", page);
+        assert!(!page.contains("<style>"), "{}", page);
+    }
+
+    /// Fetches the public page of `list` with an `If-Modified-Since` header
+    async fn public_page_since(url: &str, list: &Uuid, since: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/api/list/{}/public", url, list))
+            .header(reqwest::header::IF_MODIFIED_SINCE, since)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn public_page_conditional_get(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Public").await.unwrap().id;
+        owner.add(&list, "Eggs", None).await.unwrap();
+        owner.set_public(&list).await.unwrap();
+
+        let response = reqwest::get(format!("{}/api/list/{}/public", url, list))
+            .await
+            .unwrap();
+        let last_modified = response.headers()[reqwest::header::LAST_MODIFIED]
+            .to_str()
+            .unwrap()
+            .to_owned();
+
+        let response = public_page_since(&url, &list, &last_modified).await;
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_MODIFIED);
+        assert!(response.text().await.unwrap().is_empty());
+
+        // Last-Modified has a precision of a second
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        owner.add(&list, "Milk", None).await.unwrap();
+
+        let response = public_page_since(&url, &list, &last_modified).await;
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_ne!(
+            response.headers()[reqwest::header::LAST_MODIFIED],
+            last_modified.as_str()
+        );
+        assert!(response.text().await.unwrap().contains("Milk"));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn short_codes(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Public").await.unwrap().id;
+        owner.add(&list, "Eggs", None).await.unwrap();
+
+        let code = owner.set_public(&list).await.unwrap().short_code.unwrap();
+        assert_eq!(code.len(), 8);
+        assert!(code.bytes().all(|c| SHORT_CODE_ALPHABET.contains(&c)));
+        // The code is kept when the list is made public again
+        assert_eq!(
+            owner.set_public(&list).await.unwrap().short_code,
+            Some(code.clone())
+        );
+
+        let by_code = |code: String| {
+            let url = url.clone();
+            async move {
+                reqwest::get(format!("{}/api/p/{}", url, code))
+                    .await
+                    .unwrap()
+            }
+        };
+        for code in [code.clone(), code.to_uppercase()] {
+            let page = by_code(code).await.text().await.unwrap();
+            assert!(page.contains("Eggs"), "{}", page);
+        }
+        assert_eq!(
+            by_code("zzzzzzzz".into()).await.status(),
+            reqwest::StatusCode::NOT_FOUND
+        );
+
+        owner.remove_public(&list).await.unwrap();
+        assert_eq!(by_code(code).await.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn filter_lists_by_status(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, friend) = client(&state, &url, "friend").await;
+        let (user, client) = client(&state, &url, "user").await;
+
+        let owned = client.create_list("Owned").await.unwrap().id;
+        let read = friend.create_list("Read").await.unwrap().id;
+        friend.share(&read, &user, true).await.unwrap();
+        let write = friend.create_list("Write").await.unwrap().id;
+        friend.share(&write, &user, false).await.unwrap();
+
+        let ids = |lists: GetListsResponse| {
+            let mut ids: Vec<_> = lists.results.into_keys().collect();
+            ids.sort();
+            ids
+        };
+        let mut all = vec![owned, read, write];
+        all.sort();
+        assert_eq!(ids(client.lists().await.unwrap()), all);
+
+        for (status, list) in [
+            (ListStatus::Owned, owned),
+            (ListStatus::SharedRead, read),
+            (ListStatus::SharedWrite, write),
+        ] {
+            let lists = client.lists_by_status(status).await.unwrap();
+            assert_eq!(ids(lists), [list]);
+        }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn recently_modified_lists(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, friend) = client(&state, &url, "friend").await;
+        let (user, owner) = client(&state, &url, "owner").await;
+
+        let groceries = owner.create_list("Groceries").await.unwrap().id;
+        let hardware = owner.create_list("Hardware").await.unwrap().id;
+        let shared = friend.create_list("Shared").await.unwrap().id;
+        friend.share(&shared, &user, false).await.unwrap();
+        let quiet = friend.create_list("Quiet").await.unwrap().id;
+        friend.share(&quiet, &user, false).await.unwrap();
+
+        let since = sqlx::query_scalar!(r#"SELECT max(updated_at) as "since!" FROM lists"#)
+            .fetch_one(&state.pool)
+            .await
+            .unwrap();
+        owner.add(&groceries, "Eggs", None).await.unwrap();
+        friend.add(&shared, "Milk", None).await.unwrap();
+
+        let mut recent: Vec<_> = owner
+            .lists_modified_since(since)
+            .await
+            .unwrap()
+            .results
+            .into_keys()
+            .collect();
+        recent.sort();
+        let mut expected = vec![groceries, shared];
+        expected.sort();
+        assert_eq!(recent, expected);
+
+        let all = owner.lists().await.unwrap().results;
+        assert_eq!(all.len(), 4);
+        assert!(all.contains_key(&hardware) && all.contains_key(&quiet));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn read_list_sharing_status(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (reader, reader_client) = client(&state, &url, "reader").await;
+        let (writer, _) = client(&state, &url, "writer").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let read = owner.read(&list).await.unwrap();
+        assert!(!read.public);
+        assert_eq!(read.share_count, 0);
+
+        owner.share(&list, &reader, true).await.unwrap();
+        owner.share(&list, &writer, false).await.unwrap();
+        owner.set_public(&list).await.unwrap();
+
+        let read = reader_client.read(&list).await.unwrap();
+        assert!(read.readonly);
+        assert!(read.public);
+        assert_eq!(read.share_count, 2);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn owner_is_never_readonly(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (id, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        // A bogus read-only share of the list with its own owner
+        sqlx::query("INSERT INTO list_sharing (list, shared, readonly) VALUES ($1, $2, true)")
+            .bind(list)
+            .bind(id)
+            .execute(&state.pool)
+            .await
+            .unwrap();
+
+        assert!(!owner.read(&list).await.unwrap().readonly);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn editable_items(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (reader, reader_client) = client(&state, &url, "reader").await;
+        let (writer, writer_client) = client(&state, &url, "writer").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        owner.share(&list, &reader, true).await.unwrap();
+        owner.share(&list, &writer, false).await.unwrap();
+        owner.add(&list, "Eggs", None).await.unwrap();
+
+        for (viewer, editable) in [
+            (&owner, true),
+            (&writer_client, true),
+            (&reader_client, false),
+        ] {
+            let read = viewer.read(&list).await.unwrap().items;
+            assert_eq!(read[0].editable, editable);
+            let changes = viewer.list_changes(&list, None).await.unwrap().items;
+            assert_eq!(changes[0].editable, editable);
+        }
+    }
+
+    #[test]
+    fn item_names_are_not_empty() {
+        assert_eq!(
+            apply_item_name(CaseMode::TitleCase, "oat\nMILK")
+                .ok()
+                .as_deref(),
+            Some("Oat Milk")
+        );
+        for mode in [CaseMode::None, CaseMode::Lower, CaseMode::TitleCase] {
+            assert!(matches!(
+                apply_item_name(mode, " \t"),
+                Err(Error::InvalidItemName)
+            ));
+        }
+    }
+
+    /// Name of the item `id` of `list`
+    async fn current_name(owner: &kabalist_client::Client, list: Uuid, id: i32) -> String {
+        let items = owner.read(&list).await.unwrap().items;
+        items.into_iter().find(|item| item.id == id).unwrap().name
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn case_modes(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        let set_mode = |mode| UpdateListRequest {
+            description: None,
+            allowed_units: None,
+            normalize_case: Some(mode),
+            aisle_order: None,
+            default_amount: None,
+            require_amount: None,
+        };
+
+        // Names are kept as typed by default
+        assert_eq!(
+            owner.list_info(&list).await.unwrap().normalize_case,
+            CaseMode::None
+        );
+        let milk = owner.add(&list, "oat MILK", None).await.unwrap().id;
+        assert_eq!(current_name(&owner, list, milk).await, "oat MILK");
+
+        owner
+            .update_list(&list, &set_mode(CaseMode::Lower))
+            .await
+            .unwrap();
+        assert_eq!(
+            owner.list_info(&list).await.unwrap().normalize_case,
+            CaseMode::Lower
+        );
+        let eggs = owner.add(&list, "Brown EGGS", None).await.unwrap().id;
+        assert_eq!(current_name(&owner, list, eggs).await, "brown eggs");
+        // Existing items are only changed when renamed
+        assert_eq!(current_name(&owner, list, milk).await, "oat MILK");
+        owner
+            .update_item(&list, milk, Some("Oat Milk"), None)
+            .await
+            .unwrap();
+        assert_eq!(current_name(&owner, list, milk).await, "oat milk");
+
+        owner
+            .update_list(&list, &set_mode(CaseMode::TitleCase))
+            .await
+            .unwrap();
+        let juice = owner.add(&list, "orange JUICE", None).await.unwrap().id;
+        assert_eq!(current_name(&owner, list, juice).await, "Orange Juice");
+        owner
+            .bulk_update(
+                &list,
+                vec![ItemUpdate {
+                    id: eggs,
+                    name: Some("free range eggs".into()),
+                    amount: None,
+                    checked: None,
+                    position: None,
+                }],
+            )
+            .await
+            .unwrap();
+        assert_eq!(current_name(&owner, list, eggs).await, "Free Range Eggs");
+
+        owner
+            .update_list(&list, &set_mode(CaseMode::None))
+            .await
+            .unwrap();
+        let bread = owner.add(&list, "bREAD", None).await.unwrap().id;
+        assert_eq!(current_name(&owner, list, bread).await, "bREAD");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn item_names(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let eggs = owner.add(&list, "Brown\teggs\n", None).await.unwrap().id;
+        let milk = owner.add(&list, "Milk", None).await.unwrap().id;
+        owner
+            .update_item(&list, milk, Some("Oat\r\nmilk "), None)
+            .await
+            .unwrap();
+
+        assert_api_error(
+            owner.add(&list, " \n\t", None).await,
+            Error::InvalidItemName,
+        );
+        assert_api_error(
+            owner.update_item(&list, eggs, Some("\r\n"), None).await,
+            Error::InvalidItemName,
+        );
+        assert_api_error(
+            owner
+                .bulk_update(
+                    &list,
+                    vec![ItemUpdate {
+                        id: eggs,
+                        name: Some("\u{7}".into()),
+                        amount: None,
+                        checked: None,
+                        position: None,
+                    }],
+                )
+                .await,
+            Error::InvalidItemName,
+        );
+
+        let names: Vec<_> = owner
+            .read(&list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| item.name)
+            .collect();
+        assert_eq!(names, ["Brown eggs", "Oat milk"]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn emoji_names(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+
+        // Four bytes per character, counted as characters by the length limit
+        let list_name = "👍".repeat(MAX_LIST_NAME_LENGTH);
+        assert_eq!(list_name.len(), 4 * MAX_LIST_NAME_LENGTH);
+        let list = owner.create_list(&list_name).await.unwrap().id;
+        assert_eq!(owner.lists().await.unwrap().results[&list].name, list_name);
+
+        let family = "👨\u{200d}👩\u{200d}👧\u{200d}👦 snacks";
+        assert_eq!(family.chars().count(), 7 + " snacks".len());
+        let names = [
+            family,
+            // Regional indicators, skin tone modifier and combining accent
+            "🇫🇷 cheese",
+            "👍🏽",
+            "e\u{301}clair",
+        ];
+        for name in names {
+            owner.add(&list, name, None).await.unwrap();
+        }
+        owner.add(&list, family, None).await.unwrap();
+
+        let items: Vec<_> = owner
+            .read(&list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| item.name)
+            .collect();
+        let mut expected_items = names.to_vec();
+        expected_items.push(family);
+        assert_eq!(items, expected_items);
+
+        let mut history = owner.search_history(&list, "").await.unwrap().matches;
+        history.sort();
+        let mut expected = names.map(String::from);
+        expected.sort();
+        assert_eq!(history, expected);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn archived_lists(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (friend, friend_client) = client(&state, &url, "friend").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let archived = owner.create_list("Last week").await.unwrap().id;
+        let current = owner.create_list("This week").await.unwrap().id;
+        owner.share(&archived, &friend, false).await.unwrap();
+
+        owner.archive_list(&archived).await.unwrap();
+        // Only the owner can archive a list
+        assert_api_error(
+            friend_client.unarchive_list(&archived).await,
+            Error::NoSuchList,
+        );
+
+        let lists = owner.lists().await.unwrap().results;
+        assert_eq!(lists.keys().collect::<Vec<_>>(), [&current]);
+        assert!(friend_client.lists().await.unwrap().results.is_empty());
+
+        let lists = owner.lists_with_archived().await.unwrap().results;
+        assert_eq!(lists.len(), 2);
+        assert!(lists[&archived].archived);
+        assert!(!lists[&current].archived);
+        let lists = friend_client.lists_with_archived().await.unwrap().results;
+        assert!(lists[&archived].archived);
+
+        owner.unarchive_list(&archived).await.unwrap();
+        assert_eq!(owner.lists().await.unwrap().results.len(), 2);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn tags(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (friend, friend_client) = client(&state, &url, "friend").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let groceries = owner.create_list("Groceries").await.unwrap().id;
+        let hardware = owner.create_list("Hardware").await.unwrap().id;
+        owner.share(&groceries, &friend, false).await.unwrap();
+
+        owner.add_tag(&groceries, "Home").await.unwrap();
+        owner.add_tag(&hardware, "home").await.unwrap();
+        let tags = owner.add_tag(&groceries, "Party").await.unwrap().tags;
+        assert_eq!(tags, ["home", "party"]);
+        // Tags are case insensitive
+        let tags = owner.add_tag(&groceries, "PARTY").await.unwrap().tags;
+        assert_eq!(tags, ["home", "party"]);
+
+        assert_api_error(owner.add_tag(&groceries, " ").await, Error::InvalidTag);
+        assert_api_error(
+            owner.add_tag(&groceries, &"a".repeat(33)).await,
+            Error::InvalidTag,
+        );
+        // Tags may contain characters with a meaning in URLs
+        let tags = owner.add_tag(&hardware, "50% off/#1?").await.unwrap().tags;
+        assert_eq!(tags, ["50% off/#1?", "home"]);
+        let tags = owner
+            .remove_tag(&hardware, "50% off/#1?")
+            .await
+            .unwrap()
+            .tags;
+        assert_eq!(tags, ["home"]);
+        // Only the owner tags their lists
+        assert_api_error(
+            friend_client.add_tag(&groceries, "work").await,
+            Error::NoSuchList,
+        );
+
+        let lists = owner.lists().await.unwrap().results;
+        assert_eq!(lists[&groceries].tags, ["home", "party"]);
+        assert_eq!(lists[&hardware].tags, ["home"]);
+        let lists = owner.lists_with_tag("Party").await.unwrap().results;
+        assert_eq!(lists.keys().collect::<Vec<_>>(), [&groceries]);
+        assert_eq!(owner.lists_with_tag("home").await.unwrap().results.len(), 2);
+        assert!(owner
+            .lists_with_tag("work")
+            .await
+            .unwrap()
+            .results
+            .is_empty());
+
+        // Shared lists come with the tags of their owner
+        let lists = friend_client.lists_with_tag("party").await.unwrap().results;
+        assert_eq!(lists[&groceries].tags, ["home", "party"]);
+
+        let tags = owner.remove_tag(&groceries, "Party").await.unwrap().tags;
+        assert_eq!(tags, ["home"]);
+        assert!(friend_client
+            .lists_with_tag("party")
+            .await
+            .unwrap()
+            .results
+            .is_empty());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn items_record_their_creator(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (friend, friend_client) = client(&state, &url, "friend").await;
+        let (owner, owner_client) = client(&state, &url, "owner").await;
+        let list = owner_client.create_list("Groceries").await.unwrap().id;
+        owner_client.share(&list, &friend, false).await.unwrap();
+
+        owner_client.add(&list, "Eggs", None).await.unwrap();
+        friend_client.add(&list, "Milk", None).await.unwrap();
+
+        let items: Vec<_> = friend_client
+            .read(&list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| (item.name, item.added_by, item.added_by_name))
+            .collect();
+        assert_eq!(
+            items,
+            [
+                ("Eggs".to_string(), owner, "owner".to_string()),
+                ("Milk".to_string(), friend, "friend".to_string()),
+            ]
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn creation_location(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let token = kabalist_client::login(&url, "owner", PASSWORD)
+            .await
+            .unwrap()
+            .token;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .post(format!("{}/api/list", url))
+            .bearer_auth(&token)
+            .json(&CreateListRequest {
+                name: "Groceries".into(),
+                description: None,
+            })
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        let location = response.headers()[reqwest::header::LOCATION]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body: RspData<CreateListResponse> = response.json().await.unwrap();
+        let list = Result::from(body).unwrap().id;
+        assert_eq!(location, format!("/api/list/{}", list));
+
+        let response = http
+            .post(format!("{}{}", url, location))
+            .bearer_auth(&token)
+            .json(&AddToListRequest {
+                name: "Eggs".into(),
+                amount: None,
+                unit: None,
+                position: None,
+                url: None,
+                merge_duplicates: None,
+                category: None,
+            })
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+        let location = response.headers()[reqwest::header::LOCATION]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body: RspData<AddToListResponse> = response.json().await.unwrap();
+        let item = Result::from(body).unwrap().id;
+        assert_eq!(location, format!("/api/list/{}/{}", list, item));
+
+        // The client doesn't care about the status
+        owner.add(&list, "Milk", None).await.unwrap();
+        assert_eq!(owner.read(&list).await.unwrap().items.len(), 2);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn duplicate_warning(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let eggs = owner.add(&list, "Eggs", None).await.unwrap();
+        assert_eq!(eggs.duplicate_of, None);
+        let again = owner.add_warn_duplicate(&list, "Eggs", None).await.unwrap();
+        assert_eq!(again.duplicate_of, Some(eggs.id));
+        assert_ne!(again.id, eggs.id);
+        // Without the query parameter nothing changes
+        let third = owner.add(&list, "Eggs", None).await.unwrap();
+        assert_eq!(third.duplicate_of, None);
+        assert_eq!(owner.read(&list).await.unwrap().items.len(), 3);
+
+        // Checked items are not duplicates
+        let milk = owner.add(&list, "Milk", None).await.unwrap().id;
+        owner.set_checked(&list, milk, true).await.unwrap();
+        let milk = owner.add_warn_duplicate(&list, "Milk", None).await.unwrap();
+        assert_eq!(milk.duplicate_of, None);
+    }
+
+    fn item(name: &str, category: Option<&str>) -> Item {
+        Item {
+            id: 0,
+            external_id: Uuid::nil(),
+            name: name.into(),
+            amount: None,
+            unit: None,
+            category: category.map(Into::into),
+            checked: false,
+            status: ItemStatus::Active,
+            position: 0,
+            url: None,
+            added_by: Uuid::nil(),
+            added_by_name: String::new(),
+            editable: true,
+        }
+    }
+
+    #[test]
+    fn sort_follows_aisles() {
+        let mut items = vec![
+            item("milk", Some("Dairy")),
+            item("soap", None),
+            item("Apples", Some("Fruits")),
+            item("bread", Some("Bakery")),
+            item("butter", Some("Dairy")),
+            item("cake", Some("bakery")),
+        ];
+        sort_by_aisle(&mut items, &["Fruits".into(), "Dairy".into()]);
+
+        let names: Vec<_> = items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["Apples", "butter", "milk", "soap", "bread", "cake"]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn aisle_sort(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        owner
+            .add_with_category(&list, "Milk", "Dairy")
+            .await
+            .unwrap();
+        owner.add(&list, "Soap", None).await.unwrap();
+        owner
+            .add_with_category(&list, "Apples", " Fruits ")
+            .await
+            .unwrap();
+        owner
+            .add_with_category(&list, "Bread", "Bakery")
+            .await
+            .unwrap();
+
+        owner
+            .update_list(
+                &list,
+                &UpdateListRequest {
+                    description: None,
+                    allowed_units: None,
+                    normalize_case: None,
+                    aisle_order: Some(vec!["Fruits".into(), " Dairy".into(), "".into()]),
+                    default_amount: None,
+                    require_amount: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            owner.list_info(&list).await.unwrap().aisle_order,
+            ["Fruits", "Dairy"]
+        );
+
+        let names =
+            |items: Vec<Item>| -> Vec<_> { items.into_iter().map(|item| item.name).collect() };
+        // Without a sort, items stay in their position order
+        assert_eq!(
+            names(owner.read(&list).await.unwrap().items),
+            ["Milk", "Soap", "Apples", "Bread"]
+        );
+        let sorted = owner.read_sorted(&list, ItemSort::Aisle).await.unwrap();
+        assert_eq!(names(sorted.items), ["Apples", "Milk", "Soap", "Bread"]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn checked_counts(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let read = owner.read(&list).await.unwrap();
+        assert_eq!((read.checked_count, read.total_count), (0, 0));
+
+        let eggs = owner.add(&list, "Eggs", Some("2")).await.unwrap().id;
+        let milk = owner.add(&list, "Milk", None).await.unwrap().id;
+        owner.add(&list, "Eggs", Some("4")).await.unwrap();
+        owner.set_checked(&list, eggs, true).await.unwrap();
+        owner.set_checked(&list, milk, true).await.unwrap();
+
+        let read = owner.read(&list).await.unwrap();
+        assert_eq!((read.checked_count, read.total_count), (2, 3));
+        // Grouping merges items, but the counts stay over the whole list
+        let grouped = owner.read_grouped(&list).await.unwrap();
+        assert!(grouped.items.len() < 3);
+        assert_eq!((grouped.checked_count, grouped.total_count), (2, 3));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn grouped_read(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let eggs = owner.add(&list, "Eggs", Some("2")).await.unwrap().id;
+        owner.add(&list, "Flour", Some("1")).await.unwrap();
+        owner.add(&list, "Eggs", Some(" 4")).await.unwrap();
+        owner.add(&list, "Flour", Some("500g")).await.unwrap();
+        owner.add(&list, "Eggs", None).await.unwrap();
+        owner.add(&list, "Milk", None).await.unwrap();
+        owner.set_checked(&list, eggs, true).await.unwrap();
+
+        let grouped = owner.read_grouped(&list).await.unwrap().items;
+        let grouped: Vec<_> = grouped
+            .iter()
+            .map(|item| {
+                let amount = item.amount.as_deref();
+                (item.id == eggs, item.name.as_str(), amount, item.checked)
+            })
+            .collect();
+        assert_eq!(
+            grouped,
+            [
+                (true, "Eggs", Some("6"), false),
+                (false, "Flour", Some("1, 500g"), false),
+                (false, "Milk", None, false),
+            ]
+        );
+
+        // Stored rows are left alone
+        assert_eq!(owner.read(&list).await.unwrap().items.len(), 6);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn merge_into_pantry_item(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        owner
+            .add_to_pantry(list, "Water".into(), 6.0)
+            .await
+            .unwrap();
+        let water = owner.pantry(list).await.unwrap().items[0].id;
+        owner
+            .edit_pantry_item(list, water, Some(4.0), None, None)
+            .await
+            .unwrap();
+        owner.refill_pantry(list).await.unwrap();
+        let refilled = owner.read(&list).await.unwrap().items[0].id;
+
+        let merged = owner.add_or_merge(&list, "Water", Some("3")).await.unwrap();
+        assert_eq!(merged.id, refilled);
+        let items = owner.read(&list).await.unwrap().items;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].amount.as_deref(), Some("3"));
+
+        // Only items from the pantry are merged into, and only when asked to
+        let milk = owner.add(&list, "Milk", None).await.unwrap().id;
+        assert_ne!(
+            owner.add_or_merge(&list, "Milk", None).await.unwrap().id,
+            milk
+        );
+        assert_ne!(owner.add(&list, "Water", None).await.unwrap().id, refilled);
+        assert_eq!(owner.read(&list).await.unwrap().items.len(), 4);
+
+        // The merged item still refunds the pantry with its new amount
+        owner.delete_item(&list, refilled).await.unwrap();
+        assert_eq!(owner.pantry(list).await.unwrap().items[0].amount, 7.0);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn read_several_lists(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, friend) = client(&state, &url, "friend").await;
+        let (_, stranger) = client(&state, &url, "stranger").await;
+        let (user, user_client) = client(&state, &url, "user").await;
+
+        let owned = user_client.create_list("Owned").await.unwrap().id;
+        user_client.add(&owned, "Eggs", None).await.unwrap();
+        let shared = friend.create_list("Shared").await.unwrap().id;
+        friend.add(&shared, "Milk", Some("2")).await.unwrap();
+        friend.share(&shared, &user, true).await.unwrap();
+        let private = stranger.create_list("Private").await.unwrap().id;
+
+        let lists = user_client
+            .read_lists(vec![owned, private, shared, Uuid::new_v4(), owned])
+            .await
+            .unwrap()
+            .lists;
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists[&owned], user_client.read(&owned).await.unwrap());
+        assert_eq!(lists[&shared], user_client.read(&shared).await.unwrap());
+        assert!(lists[&shared].readonly);
+
+        assert_api_error(
+            user_client.read_lists(vec![owned; 51]).await,
+            Error::TooManyLists,
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn allowed_units(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Baking").await.unwrap().id;
+        let allow = |units: &[&str]| UpdateListRequest {
+            description: None,
+            allowed_units: Some(units.iter().map(|unit| unit.to_string()).collect()),
+            normalize_case: None,
+            aisle_order: None,
+            default_amount: None,
+            require_amount: None,
+        };
+
+        // Lists accept any unit by default
+        owner
+            .add_with_unit(&list, "Flour", None, "cup")
+            .await
+            .unwrap();
+
+        owner
+            .update_list(&list, &allow(&["g", " kg "]))
+            .await
+            .unwrap();
+        assert_eq!(
+            owner.list_info(&list).await.unwrap().allowed_units,
+            ["g", "kg"]
+        );
+
+        let sugar = owner
+            .add_with_unit(&list, "Sugar", Some("1"), "kg")
+            .await
+            .unwrap()
+            .id;
+        owner.add(&list, "Eggs", None).await.unwrap();
+        assert_api_error(
+            owner.add_with_unit(&list, "Milk", None, "L").await,
+            Error::UnitNotAllowed,
+        );
+        assert_api_error(
+            owner.set_unit(&list, sugar, "L").await,
+            Error::UnitNotAllowed,
+        );
+        owner.set_unit(&list, sugar, "g").await.unwrap();
+
+        let units: Vec<_> = owner
+            .read(&list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| (item.name, item.unit))
+            .collect();
+        assert_eq!(
+            units,
+            [
+                ("Flour".to_string(), Some("cup".to_string())),
+                ("Sugar".to_string(), Some("g".to_string())),
+                ("Eggs".to_string(), None),
+            ]
+        );
+
+        owner.update_list(&list, &allow(&[])).await.unwrap();
+        owner.add_with_unit(&list, "Milk", None, "L").await.unwrap();
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn sorted_overview(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, friend) = client(&state, &url, "friend").await;
+        let (user, user_client) = client(&state, &url, "user").await;
+
+        let groceries = user_client.create_list("groceries").await.unwrap().id;
+        let shared = friend.create_list("Hardware").await.unwrap().id;
+        friend.share(&shared, &user, false).await.unwrap();
+        let baking = user_client.create_list("Baking").await.unwrap().id;
+
+        let lists = user_client.lists().await.unwrap();
+        assert_eq!(lists.order, [groceries, shared, baking]);
+        assert!(lists.results[&groceries].created_at < lists.results[&baking].created_at);
+        assert_eq!(
+            user_client.lists_sorted(ListSort::Created).await.unwrap(),
+            lists
+        );
+        assert_eq!(
+            user_client
+                .lists_sorted(ListSort::Name)
+                .await
+                .unwrap()
+                .order,
+            [baking, groceries, shared]
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn delete_non_numeric_pantry_item(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        for name in ["Rice", "Water"] {
+            owner.add_to_pantry(list, name.into(), 6.0).await.unwrap();
+        }
+        for item in owner.pantry(list).await.unwrap().items {
+            owner
+                .edit_pantry_item(list, item.id, Some(4.0), None, None)
+                .await
+                .unwrap();
+        }
+        owner.refill_pantry(list).await.unwrap();
+        let items = owner.read(&list).await.unwrap().items;
+        let id = |name: &str| items.iter().find(|item| item.name == name).unwrap().id;
+
+        owner
+            .update_item(&list, id("Rice"), None, Some("a handful"))
+            .await
+            .unwrap();
+        owner.delete_item(&list, id("Rice")).await.unwrap();
+        owner.delete_item(&list, id("Water")).await.unwrap();
+
+        let mut pantry: Vec<_> = owner
+            .pantry(list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| (item.name, item.amount))
+            .collect();
+        pantry.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            pantry,
+            [("Rice".to_string(), 4.0), ("Water".to_string(), 6.0)]
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn bulk_update_counts_items(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        let eggs = owner.add(&list, "Eggs", None).await.unwrap().id;
+        let milk = owner.add(&list, "Milk", None).await.unwrap().id;
+
+        let check = |id| ItemUpdate {
+            id,
+            name: None,
+            amount: None,
+            checked: Some(true),
+            position: None,
+        };
+        let updated = owner
+            .bulk_update(&list, vec![check(eggs), check(milk), check(-1)])
+            .await
+            .unwrap();
+        assert_eq!(updated.affected, 2);
+
+        let items = owner.read(&list).await.unwrap().items;
+        assert!(items.iter().all(|item| item.checked));
+    }
+
+    #[test]
+    fn parse_text_lines() {
+        assert_eq!(
+            parse_text_line("Flour - 500g", DEFAULT_IMPORT_DELIMITER),
+            Some(("Flour", Some("500g")))
+        );
+        assert_eq!(
+            parse_text_line("  - Eggs  ", DEFAULT_IMPORT_DELIMITER),
+            Some(("Eggs", None))
+        );
+        assert_eq!(
+            parse_text_line("* Salt - 1 ", DEFAULT_IMPORT_DELIMITER),
+            Some(("Salt", Some("1")))
+        );
+        assert_eq!(
+            parse_text_line("Pepper - ", DEFAULT_IMPORT_DELIMITER),
+            Some(("Pepper -", None))
+        );
+        assert_eq!(
+            parse_text_line("Salt - and - pepper - 2", DEFAULT_IMPORT_DELIMITER),
+            Some(("Salt - and - pepper", Some("2")))
+        );
+        assert_eq!(
+            parse_text_line("-5 eggs", DEFAULT_IMPORT_DELIMITER),
+            Some(("-5 eggs", None))
+        );
+        assert_eq!(
+            parse_text_line("- ", DEFAULT_IMPORT_DELIMITER),
+            Some(("", None))
+        );
+        assert_eq!(parse_text_line("   ", DEFAULT_IMPORT_DELIMITER), None);
+
+        assert_eq!(parse_text_line("Milk; 2L", ";"), Some(("Milk", Some("2L"))));
+        assert_eq!(parse_text_line("Milk - 2L", ";"), Some(("Milk - 2L", None)));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn import_text(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        owner.add(&list, "Milk", None).await.unwrap();
+
+        let text = "Flour - 500g\n\n- Eggs\n*\n* Salt - a pinch\n  \n- \nButter";
+        let imported = owner.import_text(&list, text.to_string()).await.unwrap();
+        assert_eq!(
+            imported,
+            ImportResponse {
+                imported: 4,
+                skipped: 2,
+                errors: vec![
+                    ImportError {
+                        line: 4,
+                        reason: ImportErrorReason::EmptyName,
+                    },
+                    ImportError {
+                        line: 7,
+                        reason: ImportErrorReason::EmptyName,
+                    },
+                ],
+            }
+        );
+
+        // Imported items go after the existing ones, in the order of the text
+        let items = owner.read(&list).await.unwrap().items;
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| (item.name.as_str(), item.amount.as_deref()))
+                .collect::<Vec<_>>(),
+            [
+                ("Milk", None),
+                ("Flour", Some("500g")),
+                ("Eggs", None),
+                ("Salt", Some("a pinch")),
+                ("Butter", None),
+            ]
+        );
+
+        let history = owner.search_history(&list, "sal").await.unwrap();
+        assert_eq!(
+            history.suggestions,
+            [HistorySuggestion {
+                name: "Salt".to_string(),
+                amount: Some("a pinch".to_string()),
+            }]
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn import_text_caps_errors(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        // Valid rows are imported around the invalid ones, which are all counted but only the
+        // first ones are reported
+        let mut text = "Milk\n".to_string();
+        for _ in 0..MAX_IMPORT_ERRORS + 5 {
+            text.push_str("-\n");
+        }
+        text.push_str("Flour - 500g");
+        let imported = owner.import_text(&list, text).await.unwrap();
+        assert_eq!(imported.imported, 2);
+        assert_eq!(imported.skipped, MAX_IMPORT_ERRORS as u64 + 5);
+        assert_eq!(imported.errors.len(), MAX_IMPORT_ERRORS);
+        assert_eq!(imported.errors[0].line, 2);
+        assert_eq!(imported.errors.last().unwrap().line, MAX_IMPORT_ERRORS + 1);
+        assert!(imported
+            .errors
+            .iter()
+            .all(|error| error.reason == ImportErrorReason::EmptyName));
+        assert_eq!(owner.read(&list).await.unwrap().items.len(), 2);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn import_text_with_delimiter(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let text = "Milk: 2L\nFlour - 500g\n- Eggs: 6";
+        let imported = owner
+            .import_text_with_delimiter(&list, text.to_string(), ":")
+            .await
+            .unwrap();
+        assert_eq!(imported.imported, 3);
+
+        let items = owner.read(&list).await.unwrap().items;
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| (item.name.as_str(), item.amount.as_deref()))
+                .collect::<Vec<_>>(),
+            [
+                ("Milk", Some("2L")),
+                ("Flour - 500g", None),
+                ("Eggs", Some("6")),
+            ]
+        );
+
+        assert_api_error(
+            owner
+                .import_text_with_delimiter(&list, text.to_string(), "")
+                .await,
+            Error::EmptyDelimiter,
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn check_and_uncheck_all(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (reader, reader_client) = client(&state, &url, "reader").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        owner.share(&list, &reader, true).await.unwrap();
+        let eggs = owner.add(&list, "Eggs", None).await.unwrap().id;
+        owner.add(&list, "Milk", None).await.unwrap();
+        owner.add(&list, "Flour", None).await.unwrap();
+        owner
+            .bulk_update(
+                &list,
+                vec![ItemUpdate {
+                    id: eggs,
+                    name: None,
+                    amount: None,
+                    checked: Some(true),
+                    position: None,
+                }],
+            )
+            .await
+            .unwrap();
+
+        // Only the items whose state changes are counted
+        assert_eq!(owner.check_all(&list).await.unwrap().affected, 2);
+        let items = owner.read(&list).await.unwrap().items;
+        assert!(items.iter().all(|item| item.checked));
+        assert_eq!(owner.check_all(&list).await.unwrap().affected, 0);
+
+        assert_eq!(owner.uncheck_all(&list).await.unwrap().affected, 3);
+        let items = owner.read(&list).await.unwrap().items;
+        assert!(items.iter().all(|item| !item.checked));
+        assert_eq!(owner.uncheck_all(&list).await.unwrap().affected, 0);
+
+        assert_api_error(reader_client.check_all(&list).await, Error::NotWritable);
+        assert_api_error(reader_client.uncheck_all(&list).await, Error::NotWritable);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn stock_item_in_pantry(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        let coffee = owner.add(&list, "Coffee", Some("2")).await.unwrap().id;
+
+        let stocked = owner
+            .item_to_pantry(&list, coffee, 3.0, true)
+            .await
+            .unwrap();
+        assert_eq!(stocked.amount, 2.0);
+
+        let pantry = owner.pantry(list).await.unwrap().items;
+        assert_eq!(pantry.len(), 1);
+        assert_eq!(pantry[0].id, stocked.pantry_item);
+        assert_eq!(pantry[0].name, "Coffee");
+        assert_eq!(pantry[0].amount, 2.0);
+        assert_eq!(pantry[0].target, 3.0);
+        assert!(owner.read(&list).await.unwrap().items.is_empty());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn stocked_items_are_not_refunded(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        owner
+            .add_to_pantry(list, "Water".into(), 6.0)
+            .await
+            .unwrap();
+        owner.refill_pantry(list).await.unwrap();
+        let water = owner.read(&list).await.unwrap().items[0].id;
+
+        let stocked = owner
+            .item_to_pantry(&list, water, 6.0, false)
+            .await
+            .unwrap();
+        owner.delete_item(&list, water).await.unwrap();
+
+        let pantry = owner.pantry(list).await.unwrap().items;
+        assert_eq!(pantry.len(), 1);
+        assert_eq!(pantry[0].amount, stocked.amount);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn read_empty_list(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let content = owner.read(&list).await.unwrap();
+        assert!(content.items.is_empty());
+        assert!(!content.readonly);
+        assert_eq!(content.total_count, 0);
+        assert_eq!(content.checked_count, 0);
+        assert!(owner.read_grouped(&list).await.unwrap().items.is_empty());
+
+        // An array, not null or a missing field
+        let token = kabalist_client::login(&url, "owner", PASSWORD)
+            .await
+            .unwrap()
+            .token;
+        let raw: serde_json::Value = reqwest::Client::new()
+            .get(format!("{}/api/list/{}", url, list))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(raw["ok"]["items"], serde_json::json!([]));
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn minimal_fields(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (reader, _) = client(&state, &url, "reader").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        owner.share(&list, &reader, false).await.unwrap();
+        let eggs = owner.add(&list, "Eggs", Some("6")).await.unwrap().id;
+
+        let full = owner.read(&list).await.unwrap();
+        let minimal = owner.read_minimal(&list).await.unwrap();
+        assert_eq!(
+            minimal,
+            MinimalReadListResponse {
+                items: vec![MinimalItem {
+                    id: eggs,
+                    name: "Eggs".to_string(),
+                    amount: Some("6".to_string()),
+                    checked: false,
+                }],
+                readonly: full.readonly,
+                public: full.public,
+                share_count: full.share_count,
+                checked_count: full.checked_count,
+                total_count: full.total_count,
+            }
+        );
+        assert_eq!(minimal.share_count, 1);
+
+        // The heavy item fields are not sent at all
+        let token = kabalist_client::login(&url, "owner", PASSWORD)
+            .await
+            .unwrap()
+            .token;
+        let raw: serde_json::Value = reqwest::Client::new()
+            .get(format!("{}/api/list/{}?fields=minimal", url, list))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let mut fields: Vec<_> = raw["ok"]["items"][0]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        fields.sort();
+        assert_eq!(fields, ["amount", "checked", "id", "name"]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn items_added_between(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        let flour = owner.add(&list, "Flour", None).await.unwrap().id;
+        let eggs = owner.add(&list, "Eggs", None).await.unwrap().id;
+        owner.add(&list, "Milk", None).await.unwrap();
+        for (id, days) in [(flour, 10), (eggs, 3)] {
+            sqlx::query(
+                "UPDATE lists_content SET created_at = now() - make_interval(days => $1) WHERE id = $2",
+            )
+            .bind(days)
+            .bind(id)
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        }
+
+        let now = Utc::now();
+        let names = |content: ReadListResponse| {
+            content
+                .items
+                .into_iter()
+                .map(|item| item.name)
+                .collect::<Vec<_>>()
+        };
+
+        let week = owner
+            .read_added_between(&list, now - chrono::Duration::days(7), now)
+            .await
+            .unwrap();
+        // The counts still cover the whole list
+        assert_eq!(week.total_count, 3);
+        assert_eq!(names(week), ["Eggs", "Milk"]);
+
+        let older = owner
+            .read_added_between(
+                &list,
+                now - chrono::Duration::days(14),
+                now - chrono::Duration::days(7),
+            )
+            .await
+            .unwrap();
+        assert_eq!(names(older), ["Flour"]);
+
+        assert_api_error(
+            owner
+                .read_added_between(&list, now, now - chrono::Duration::days(7))
+                .await,
+            Error::InvalidRange,
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn search_items(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (reader, reader_client) = client(&state, &url, "reader").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        owner.share(&list, &reader, true).await.unwrap();
+        for name in [
+            "Milk",
+            "Eggs",
+            "Oat milk",
+            "Cream 50%",
+            "Cream 500g",
+            "Pasta_fresh",
+        ] {
+            owner.add(&list, name, None).await.unwrap();
+        }
+
+        let names = |items: Vec<Item>| items.into_iter().map(|item| item.name).collect::<Vec<_>>();
+
+        // Matches ignore case and come back in list order, with the access of the caller
+        let found = reader_client
+            .search_items(&list, "MILK")
+            .await
+            .unwrap()
+            .items;
+        assert!(found.iter().all(|item| !item.editable));
+        assert_eq!(names(found), ["Milk", "Oat milk"]);
+
+        // Wildcards are searched for literally
+        let found = owner.search_items(&list, "50%").await.unwrap().items;
+        assert_eq!(names(found), ["Cream 50%"]);
+        let found = owner.search_items(&list, "a_f").await.unwrap().items;
+        assert_eq!(names(found), ["Pasta_fresh"]);
+        let found = owner.search_items(&list, "m 5_0").await.unwrap().items;
+        assert!(found.is_empty());
+
+        assert!(owner
+            .search_items(&list, "Flour")
+            .await
+            .unwrap()
+            .items
+            .is_empty());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn default_amount(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        let set_default = |amount: &str| UpdateListRequest {
+            description: None,
+            allowed_units: None,
+            normalize_case: None,
+            aisle_order: None,
+            default_amount: Some(amount.into()),
+            require_amount: None,
+        };
+
+        // Items have no amount by default
+        owner.add(&list, "Eggs", None).await.unwrap();
+
+        owner.update_list(&list, &set_default(" 1 ")).await.unwrap();
+        assert_eq!(
+            owner
+                .list_info(&list)
+                .await
+                .unwrap()
+                .default_amount
+                .as_deref(),
+            Some("1")
+        );
+        owner.add(&list, "Milk", None).await.unwrap();
+        owner.add(&list, "Flour", Some("500g")).await.unwrap();
+
+        // An empty amount removes the default
+        owner.update_list(&list, &set_default("")).await.unwrap();
+        assert_eq!(owner.list_info(&list).await.unwrap().default_amount, None);
+        owner.add(&list, "Salt", None).await.unwrap();
+
+        let items = owner.read(&list).await.unwrap().items;
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| (item.name.as_str(), item.amount.as_deref()))
+                .collect::<Vec<_>>(),
+            [
+                ("Eggs", None),
+                ("Milk", Some("1")),
+                ("Flour", Some("500g")),
+                ("Salt", None),
+            ]
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn sync_cycle(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        let eggs = owner.add(&list, "Eggs", None).await.unwrap().id;
+        let milk = owner.add(&list, "Milk", None).await.unwrap().id;
+
+        let full = owner.list_changes(&list, None).await.unwrap();
+        assert_eq!(full.items.len(), 2);
+        assert!(full.deleted.is_empty());
+
+        owner.set_checked(&list, eggs, true).await.unwrap();
+        owner.delete_item(&list, milk).await.unwrap();
+        let bread = owner.add(&list, "Bread", None).await.unwrap().id;
+
+        let changes = owner
+            .list_changes(&list, Some(full.next_since))
+            .await
+            .unwrap();
+        let mut changed: Vec<_> = changes.items.iter().map(|item| item.id).collect();
+        changed.sort_unstable();
+        assert_eq!(changed, [eggs, bread]);
+        assert!(changes
+            .items
+            .iter()
+            .any(|item| item.id == eggs && item.checked));
+        assert_eq!(changes.deleted.len(), 1);
+        assert_eq!(changes.deleted[0].id, milk);
+
+        // A write that started before the read but commits after it is returned by the next one
+        let mut writer = state.pool.begin().await.unwrap();
+        sqlx::query("UPDATE lists_content SET amount = '12' WHERE id = $1")
+            .bind(eggs)
+            .execute(&mut *writer)
+            .await
+            .unwrap();
+        let during = owner
+            .list_changes(&list, Some(changes.next_since))
+            .await
+            .unwrap();
+        assert!(during.items.is_empty());
+        writer.commit().await.unwrap();
+
+        let after = owner
+            .list_changes(&list, Some(during.next_since))
+            .await
+            .unwrap();
+        assert_eq!(after.items.len(), 1);
+        assert_eq!(after.items[0].id, eggs);
+        assert_eq!(after.items[0].amount.as_deref(), Some("12"));
+
+        let none = owner
+            .list_changes(&list, Some(after.next_since))
+            .await
+            .unwrap();
+        assert!(none.items.is_empty() && none.deleted.is_empty());
+        assert_eq!(none.next_since, after.next_since);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn sync_after_pruned_deletions(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        let eggs = owner.add(&list, "Eggs", None).await.unwrap().id;
+        let milk = owner.add(&list, "Milk", None).await.unwrap().id;
+
+        let before = owner.list_changes(&list, None).await.unwrap().next_since;
+        owner.delete_item(&list, eggs).await.unwrap();
+        sqlx::query("UPDATE deleted_items SET deleted_at = now() - interval '31 days'")
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        // Deleting another item forgets the old tombstone
+        owner.delete_item(&list, milk).await.unwrap();
+
+        assert_api_error(
+            owner.list_changes(&list, Some(before)).await,
+            Error::ChangesExpired,
+        );
+        let full = owner.list_changes(&list, None).await.unwrap();
+        assert!(full.items.is_empty());
+        owner
+            .list_changes(&list, Some(full.next_since))
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn require_amount(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Cake").await.unwrap().id;
+        let update = |default_amount: Option<&str>, require_amount| UpdateListRequest {
+            description: None,
+            allowed_units: None,
+            normalize_case: None,
+            aisle_order: None,
+            default_amount: default_amount.map(Into::into),
+            require_amount,
+        };
+
+        // Optional by default
+        assert!(!owner.list_info(&list).await.unwrap().require_amount);
+        let sugar = owner.add(&list, "Sugar", None).await.unwrap().id;
+
+        owner
+            .update_list(&list, &update(None, Some(true)))
+            .await
+            .unwrap();
+        assert!(owner.list_info(&list).await.unwrap().require_amount);
+        assert_api_error(owner.add(&list, "Eggs", None).await, Error::AmountRequired);
+        assert_api_error(
+            owner.add(&list, "Eggs", Some(" ")).await,
+            Error::AmountRequired,
+        );
+        assert_api_error(
+            owner.update_item(&list, sugar, None, Some("")).await,
+            Error::AmountRequired,
+        );
+        assert_api_error(
+            owner.import_text(&list, "Eggs\nFlour - 200g".into()).await,
+            Error::AmountRequired,
+        );
+        owner.add(&list, "Eggs", Some("3")).await.unwrap();
+        owner
+            .update_item(&list, sugar, None, Some("100g"))
+            .await
+            .unwrap();
+
+        // The default amount counts for new items, including imported ones
+        owner
+            .update_list(&list, &update(Some("1"), None))
+            .await
+            .unwrap();
+        owner.add(&list, "Butter", None).await.unwrap();
+        owner
+            .import_text(&list, "Milk\nFlour - 200g".into())
+            .await
+            .unwrap();
+
+        let amounts: Vec<_> = owner
+            .read(&list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| (item.name, item.amount))
+            .collect();
+        let amount = |name: &str| {
+            amounts
+                .iter()
+                .find(|(item, _)| item == name)
+                .and_then(|(_, amount)| amount.as_deref())
+        };
+        assert_eq!(amount("Butter"), Some("1"));
+        assert_eq!(amount("Milk"), Some("1"));
+        assert_eq!(amount("Flour"), Some("200g"));
+        assert_eq!(amount("Sugar"), Some("100g"));
+
+        // Back to optional
+        owner
+            .update_list(&list, &update(Some(""), Some(false)))
+            .await
+            .unwrap();
+        owner.add(&list, "Salt", None).await.unwrap();
+    }
+
+    /// IDs of the lists that changed since `client` last saw them
+    async fn unseen(client: &kabalist_client::Client) -> Vec<Uuid> {
+        let unseen = client.unseen().await.unwrap().lists;
+        unseen.into_iter().map(|list| list.id).collect()
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn own_writes_are_seen(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, alice) = client(&state, &url, "alice").await;
+        let (bob_id, bob) = client(&state, &url, "bob").await;
+
+        // Creating the list and editing it are not news to its owner
+        let list = alice.create_list("Groceries").await.unwrap().id;
+        alice.add(&list, "Eggs", None).await.unwrap();
+        alice.share(&list, &bob_id, false).await.unwrap();
+        assert!(unseen(&alice).await.is_empty());
+
+        assert_eq!(unseen(&bob).await, [list]);
+        bob.read(&list).await.unwrap();
+        assert!(unseen(&bob).await.is_empty());
+
+        let milk = alice.add(&list, "Milk", None).await.unwrap().id;
+        assert_eq!(unseen(&bob).await, [list]);
+        assert!(unseen(&alice).await.is_empty());
+
+        bob.set_checked(&list, milk, true).await.unwrap();
+        assert!(unseen(&bob).await.is_empty());
+        assert_eq!(unseen(&alice).await, [list]);
+
+        // Failed writes don't count as reading the list
+        assert_api_error(alice.add(&list, " ", None).await, Error::InvalidItemName);
+        assert_eq!(unseen(&alice).await, [list]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn status_transitions(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let eggs = owner.add(&list, "Eggs", None).await.unwrap().id;
+        let milk = owner.add(&list, "Milk", None).await.unwrap().id;
+        let bread = owner.add(&list, "Bread", None).await.unwrap().id;
+        owner
+            .set_status(&list, milk, ItemStatus::Maybe)
+            .await
+            .unwrap();
+        owner
+            .set_status(&list, bread, ItemStatus::Checked)
+            .await
+            .unwrap();
+
+        let statuses = |items: Vec<Item>| -> Vec<_> {
+            items
+                .into_iter()
+                .map(|item| (item.id, item.status, item.checked))
+                .collect()
+        };
+        let items = owner.read(&list).await.unwrap().items;
+        assert_eq!(
+            statuses(items),
+            [
+                (eggs, ItemStatus::Active, false),
+                (milk, ItemStatus::Maybe, false),
+                (bread, ItemStatus::Checked, true),
+            ]
+        );
+
+        for (status, expected) in [
+            (ItemStatus::Active, eggs),
+            (ItemStatus::Maybe, milk),
+            (ItemStatus::Checked, bread),
+        ] {
+            let items = owner.read_with_status(&list, status).await.unwrap().items;
+            assert_eq!(
+                statuses(items),
+                [(expected, status, status == ItemStatus::Checked)]
+            );
+        }
+
+        // A checked maybe goes back to maybe once unchecked
+        owner.set_checked(&list, milk, true).await.unwrap();
+        let items = owner
+            .read_with_status(&list, ItemStatus::Checked)
+            .await
+            .unwrap();
+        assert_eq!(items.items.len(), 2);
+        owner.set_checked(&list, milk, false).await.unwrap();
+        let items = owner
+            .read_with_status(&list, ItemStatus::Maybe)
+            .await
+            .unwrap();
+        assert_eq!(statuses(items.items), [(milk, ItemStatus::Maybe, false)]);
+
+        owner
+            .set_status(&list, milk, ItemStatus::Active)
+            .await
+            .unwrap();
+        let items = owner
+            .read_with_status(&list, ItemStatus::Active)
+            .await
+            .unwrap();
+        assert_eq!(items.items.len(), 2);
+        assert!(owner
+            .read_with_status(&list, ItemStatus::Maybe)
+            .await
+            .unwrap()
+            .items
+            .is_empty());
+    }
+}
@@ -1,10 +1,10 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use axum::{
-    extract::{self, Query},
+    extract::{rejection::PathRejection, DefaultBodyLimit, FromRequestParts, Query},
     http::{header, HeaderValue, Method, StatusCode},
     response::IntoResponse,
-    routing::get,
+    routing::{get, post},
     Json, Router,
 };
 use figment::{
@@ -23,12 +23,16 @@ use utoipa::{
 };
 
 mod account;
+mod admin;
 mod config;
+mod contacts;
 mod list;
 mod pantry;
 mod share;
+#[cfg(test)]
+mod tests;
 
-pub(crate) use account::User;
+pub(crate) use account::{ListUser, User};
 
 macro_rules! define_error {
     (
@@ -71,6 +75,7 @@ macro_rules! define_error {
                             err: UserError {
                                 code: self,
                                 description,
+                                retryable: self.retryable(),
                             }
                         }
                     }
@@ -148,13 +153,180 @@ define_error! {
             code: 9,
             status: StatusCode::UNAUTHORIZED,
         },
+        Unavailable = {
+            description: "service temporarily unavailable",
+            code: 11,
+            status: StatusCode::SERVICE_UNAVAILABLE,
+        },
+        InvalidListName = {
+            description: "list name is empty",
+            code: 12,
+            status: StatusCode::BAD_REQUEST,
+        },
+        ListNameTooLong = {
+            description: "list name is too long",
+            code: 13,
+            status: StatusCode::BAD_REQUEST,
+        },
+        InvalidPath = {
+            description: "invalid path parameter",
+            code: 14,
+            status: StatusCode::BAD_REQUEST,
+        },
+        InvalidUrl = {
+            description: "url must use http or https",
+            code: 15,
+            status: StatusCode::BAD_REQUEST,
+        },
+        InvalidAmount = {
+            description: "amount must be positive",
+            code: 16,
+            status: StatusCode::BAD_REQUEST,
+        },
+        PantryItemNotFound = {
+            description: "pantry item does not exist",
+            code: 17,
+            status: StatusCode::NOT_FOUND,
+        },
+        ItemNotFound = {
+            description: "item not found",
+            code: 18,
+            status: StatusCode::NOT_FOUND,
+        },
+        NotFound = {
+            description: "Not found",
+            code: 19,
+            status: StatusCode::NOT_FOUND,
+        },
+        InvalidPosition = {
+            description: "exactly one of before or after must be set",
+            code: 20,
+            status: StatusCode::BAD_REQUEST,
+        },
+        InsufficientScope = {
+            description: "token scope does not allow this action",
+            code: 21,
+            status: StatusCode::FORBIDDEN,
+        },
+        ListLimitReached = {
+            description: "maximum number of lists reached",
+            code: 22,
+            status: StatusCode::FORBIDDEN,
+        },
+        InvalidItemName = {
+            description: "item name is empty",
+            code: 23,
+            status: StatusCode::BAD_REQUEST,
+        },
+        TooManyLists = {
+            description: "too many lists requested",
+            code: 24,
+            status: StatusCode::BAD_REQUEST,
+        },
+        UnitNotAllowed = {
+            description: "unit is not allowed in this list",
+            code: 25,
+            status: StatusCode::BAD_REQUEST,
+        },
+        LinkExpired = {
+            description: "link has expired",
+            code: 26,
+            status: StatusCode::GONE,
+        },
+        ChangesExpired = {
+            description: "changes this old are not kept, read the whole list instead",
+            code: 27,
+            status: StatusCode::BAD_REQUEST,
+        },
+        BatchTooLarge = {
+            description: "too many items in the batch",
+            code: 28,
+            status: StatusCode::BAD_REQUEST,
+        },
+        BodyTooLarge = {
+            description: "request body is too large",
+            code: 29,
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+        },
+        InvalidAdminKey = {
+            description: "missing or invalid admin key",
+            code: 30,
+            status: StatusCode::FORBIDDEN,
+        },
+        RegistrationDisabled = {
+            description: "registration is disabled on this instance",
+            code: 31,
+            status: StatusCode::FORBIDDEN,
+        },
+        UsernameTaken = {
+            description: "username is already taken",
+            code: 32,
+            status: StatusCode::CONFLICT,
+        },
+        PantryItemExists = {
+            description: "pantry already has an item with this name",
+            code: 33,
+            status: StatusCode::CONFLICT,
+        },
+        InvalidRange = {
+            description: "start of the range is after its end",
+            code: 34,
+            status: StatusCode::BAD_REQUEST,
+        },
+        InvalidItemId = {
+            description: "item ids are positive",
+            code: 35,
+            status: StatusCode::BAD_REQUEST,
+        },
+        InvalidTag = {
+            description: "tag is empty or too long",
+            code: 36,
+            status: StatusCode::BAD_REQUEST,
+        },
+        EmptyDelimiter = {
+            description: "delimiter is empty",
+            code: 37,
+            status: StatusCode::BAD_REQUEST,
+        },
+        AmountRequired = {
+            description: "items of this list must have an amount",
+            code: 38,
+            status: StatusCode::BAD_REQUEST,
+        },
     }
 }
 
+/// SQLSTATEs for serialization_failure and deadlock_detected, both of which succeed on retry
+const TRANSIENT_SQLSTATES: &[&str] = &["40001", "40P01"];
+
 impl From<sqlx::Error> for Error {
     fn from(e: sqlx::Error) -> Self {
-        tracing::error!("Database error: {:?}", e);
-        Error::Internal
+        match &e {
+            sqlx::Error::PoolTimedOut | sqlx::Error::Io(_) => {
+                tracing::warn!("Database unavailable: {:?}", e);
+                Error::Unavailable
+            }
+            sqlx::Error::Database(db)
+                if db
+                    .code()
+                    .is_some_and(|code| TRANSIENT_SQLSTATES.contains(&code.as_ref())) =>
+            {
+                tracing::warn!("Transient database error: {:?}", e);
+                Error::Unavailable
+            }
+            _ => {
+                tracing::error!("Database error: {:?}", e);
+                Error::Internal
+            }
+        }
+    }
+}
+
+/// Maps the violation of a unique constraint to `error`, and other database errors as usual
+fn on_unique_violation(error: Error) -> impl FnOnce(sqlx::Error) -> Error {
+    move |e| match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => error,
+        _ => e.into(),
     }
 }
 
@@ -165,6 +337,18 @@ impl From<jwt_simple::Error> for Error {
     }
 }
 
+impl From<PathRejection> for Error {
+    fn from(value: PathRejection) -> Self {
+        tracing::debug!("Invalid path: {value:?}");
+        Error::InvalidPath
+    }
+}
+
+/// Path extractor replying with the API error format when the path does not parse
+#[derive(FromRequestParts, Debug)]
+#[from_request(via(axum::extract::Path), rejection(Error))]
+pub(crate) struct Path<T>(pub(crate) T);
+
 trait OkResponse {
     type Wrapper;
 
@@ -193,33 +377,68 @@ macro_rules! alias {
 use ok_response::*;
 
 alias! {
+    OkAccountExportResponse => AccountExportResponse,
+    OkAddContactResponse => AddContactResponse,
     OkAddToListResponse => AddToListResponse,
     OkAddToPantryResponse => AddToPantryResponse,
+    OkAdminRegisterResponse => AdminRegisterResponse,
+    OkAffectedResponse => AffectedResponse,
+    OkArchiveListResponse => ArchiveListResponse,
+    OkBatchPantryResponse => BatchPantryResponse,
+    OkConsumeResponse => ConsumeResponse,
+    OkCreateApiKeyResponse => CreateApiKeyResponse,
+    OkCreateGuestTokenResponse => CreateGuestTokenResponse,
     OkCreateListResponse => CreateListResponse,
+    OkCreateShareLinkResponse => CreateShareLinkResponse,
+    OkDeleteContactResponse => DeleteContactResponse,
     OkDeleteItemResponse => DeleteItemResponse,
     OkDeleteListResponse => DeleteListResponse,
     OkDeletePantryItemResponse => DeletePantryItemResponse,
     OkDeleteShareResponse => DeleteShareResponse,
     OkEditPantryItemResponse => EditPantryItemResponse,
     OkGetAccountNameResponse => GetAccountNameResponse,
+    OkGetContactsResponse => GetContactsResponse,
     OkGetHistoryResponse => GetHistoryResponse,
     OkGetListsResponse => GetListsResponse,
     OkGetPantryResponse => GetPantryResponse,
+    OkGetPublicListsResponse => GetPublicListsResponse,
+    OkGetSharedListsResponse => GetSharedListsResponse,
     OkGetSharesResponse => GetSharesResponse,
+    OkImportResponse => ImportResponse,
+    OkListChangesResponse => ListChangesResponse,
+    OkListDetail => ListDetail,
+    OkListTagsResponse => ListTagsResponse,
     OkLoginResponse => LoginResponse,
+    OkMetaResponse => MetaResponse,
+    OkMinimalReadListResponse => MinimalReadListResponse,
+    OkMultiReadResponse => MultiReadResponse,
+    OkPantryStatusResponse => PantryStatusResponse,
+    OkPositionResponse => PositionResponse,
+    OkQuickAddResponse => QuickAddResponse,
     OkReadListResponse => ReadListResponse,
+    OkReceiptResponse => ReceiptResponse,
     OkRecoverPasswordResponse => RecoverPasswordResponse,
     OkRecoveryInfoResponse => RecoveryInfoResponse,
     OkRefillPantryResponse => RefillPantryResponse,
     OkRegisterResponse => RegisterResponse,
     OkRemovePublicResponse => RemovePublicResponse,
+    OkRevokeGuestTokenResponse => RevokeGuestTokenResponse,
+    OkRevokeShareLinkResponse => RevokeShareLinkResponse,
     OkSearchAccountResponse => SearchAccountResponse,
+    OkSearchItemsResponse => SearchItemsResponse,
     OkSetPublicResponse => SetPublicResponse,
     OkShareListResponse => ShareListResponse,
+    OkShareManyResponse => ShareManyResponse,
+    OkToPantryResponse => ToPantryResponse,
+    OkUnarchiveListResponse => UnarchiveListResponse,
+    OkUnseenResponse => UnseenResponse,
     OkUnshareResponse => UnshareResponse,
     OkUpdateItemResponse => UpdateItemResponse,
+    OkUpdateListResponse => UpdateListResponse,
+    OkValidateTokenResponse => ValidateTokenResponse,
 }
 
+/// Body of every error returned by the JSON routes, `RspErr` in the types crate on the client side
 #[derive(Serialize, Deserialize, ToResponse, ToSchema)]
 struct ErrResponse {
     err: UserError,
@@ -227,15 +446,66 @@ struct ErrResponse {
 
 type Rsp<T> = Result<Json<<T as OkResponse>::Wrapper>, Error>;
 
+/// Response for handlers creating a resource, answering 201 with the location of the resource
+type Created<T> = Result<
+    (
+        StatusCode,
+        [(header::HeaderName, String); 1],
+        Json<<T as OkResponse>::Wrapper>,
+    ),
+    Error,
+>;
+
+fn created<T: OkResponse>(location: String, v: T) -> Created<T> {
+    let body = T::ok(v)?;
+    Ok((StatusCode::CREATED, [(header::LOCATION, location)], body))
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 struct UserError {
+    /// Stable identifier of the error, to be matched on by clients
     code: Error,
+    /// Human readable message, may be more specific than the default one for the code
     description: String,
+    /// Whether the same request may succeed if sent again later
+    retryable: bool,
 }
 
+impl Error {
+    /// Transient failures, every other error will happen again on the same request
+    fn retryable(&self) -> bool {
+        matches!(self, Error::Unavailable)
+    }
+}
+
+/// Delay advertised to clients in the `Retry-After` header of transient errors
+const RETRY_AFTER_SECS: &str = "5";
+
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
-        (self.status(), Json(self.into_err())).into_response()
+        let mut response = (self.status(), Json(self.into_err())).into_response();
+        if self.retryable() {
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from_static(RETRY_AFTER_SECS),
+            );
+        }
+        response
+    }
+}
+
+/// Fallback for unknown API routes, answering with the usual error envelope
+async fn not_found() -> Error {
+    Error::NotFound
+}
+
+/// Extractors reject bodies over the limit with a plain text 413, this answers with the usual
+/// error envelope instead
+async fn body_too_large(response: axum::response::Response) -> axum::response::Response {
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        Error::BodyTooLarge.into_response()
+    } else {
+        response
     }
 }
 
@@ -254,13 +524,25 @@ async fn is_owner(db: &PgPool, user_id: Uuid, list_id: Uuid) -> Result<(), Error
     }
 }
 
+/// Item ids come from a sequence, so a non-positive id can't match anything
+fn check_item_id(item: i32) -> Result<(), Error> {
+    if item <= 0 {
+        return Err(Error::InvalidItemId);
+    }
+
+    Ok(())
+}
+
 async fn check_list(db: &PgPool, user_id: Uuid, list_id: Uuid, write: bool) -> Result<(), Error> {
     if is_owner(db, user_id, list_id).await.is_ok() {
         return Ok(());
     }
 
+    // Guest tokens act as a read-only share of their list
     let mut shared_status = sqlx::query!(
-        "SELECT readonly FROM list_sharing WHERE list = $1 AND shared = $2",
+        r#"SELECT readonly as "readonly!" FROM list_sharing WHERE list = $1 AND shared = $2
+           UNION ALL
+           SELECT true FROM guest_tokens WHERE list = $1 AND id = $2"#,
         list_id,
         user_id
     )
@@ -294,13 +576,12 @@ async fn check_list(db: &PgPool, user_id: Uuid, list_id: Uuid, write: bool) -> R
     )
 )]
 #[tracing::instrument(skip(state))]
-async fn search_list(
-    state: State,
-    user: User,
-    extract::Path(name): extract::Path<String>,
-) -> Rsp<GetListsResponse> {
+async fn search_list(state: State, user: User, Path(name): Path<String>) -> Rsp<GetListsResponse> {
     let results_owned = sqlx::query!(
-        "SELECT name, id, pub, owner FROM lists WHERE owner = $1 AND name ILIKE '%' || $2 || '%'",
+        r#"SELECT name, id, pub, owner, description, archived, created_at, require_amount,
+                  ARRAY(SELECT tag FROM list_tags WHERE list = lists.id ORDER BY tag) as "tags!"
+               FROM lists
+               WHERE owner = $1 AND name ILIKE '%' || $2 || '%'"#,
         user.id,
         name
     )
@@ -308,7 +589,9 @@ async fn search_list(
     .await?;
 
     let results_shared = sqlx::query!(
-        r#"SELECT name, id, readonly, pub, owner
+        r#"SELECT name, id, readonly, pub, owner, description, archived, created_at,
+                          require_amount,
+                  ARRAY(SELECT tag FROM list_tags WHERE list = lists.id ORDER BY tag) as "tags!"
                FROM lists, list_sharing
                WHERE (lists.id = list_sharing.list)
                    AND shared = $1
@@ -319,8 +602,8 @@ async fn search_list(
     .fetch_all(&state.0.pool)
     .await?;
 
-    OkResponse::ok(GetListsResponse {
-        results: results_owned
+    OkResponse::ok(list::sorted_lists(
+        results_owned
             .into_iter()
             .map(|row| {
                 (
@@ -330,6 +613,11 @@ async fn search_list(
                         status: ListStatus::Owned,
                         public: row.r#pub.unwrap_or(false),
                         owner: row.owner,
+                        description: row.description,
+                        archived: row.archived,
+                        created_at: row.created_at,
+                        tags: row.tags,
+                        require_amount: row.require_amount,
                     },
                 )
             })
@@ -345,11 +633,16 @@ async fn search_list(
                         },
                         public: row.r#pub.unwrap_or(false),
                         owner: row.owner,
+                        description: row.description,
+                        archived: row.archived,
+                        created_at: row.created_at,
+                        tags: row.tags,
+                        require_amount: row.require_amount,
                     },
                 )
-            }))
-            .collect(),
-    })
+            })),
+        ListSort::default(),
+    ))
 }
 
 #[utoipa::path(
@@ -358,6 +651,7 @@ async fn search_list(
     responses(
         (status = 200, description = "Account ID", body = OkSearchAccountResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 404, description = "Unknown Account", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
     params(
@@ -371,14 +665,16 @@ async fn search_list(
 async fn search_account(
     state: State,
     _user: User,
-    extract::Path(name): extract::Path<String>,
+    Path(name): Path<String>,
 ) -> Rsp<SearchAccountResponse> {
+    // Same normalization as login and registration
     let result = sqlx::query!(
-        "SELECT id FROM accounts WHERE name ILIKE $1::text::citext",
+        "SELECT id FROM accounts WHERE name_key = lower(normalize($1, NFKC))",
         name
     )
-    .fetch_one(&state.0.pool)
-    .await?;
+    .fetch_optional(&state.0.pool)
+    .await?
+    .ok_or(Error::UnknownAccount)?;
 
     OkResponse::ok(SearchAccountResponse { id: result.id })
 }
@@ -408,31 +704,436 @@ struct SearchQuery {
 async fn history_search(
     state: State,
     user: User,
-    extract::Path(list): extract::Path<Uuid>,
+    Path(list): Path<Uuid>,
     search: Query<SearchQuery>,
 ) -> Rsp<GetHistoryResponse> {
-    let results =
-        sqlx::query!(
-            "SELECT name::text FROM history WHERE list = $1 AND creator = $2 AND name ILIKE '%' || $3 || '%'",
-            list,
-            user.id,
-            search.search.as_deref().unwrap_or_default(),
-        )
-        .fetch_all(&state.0.pool)
-        .await?;
+    let results = sqlx::query!(
+        r#"SELECT name::text as "name!", amount FROM history
+           WHERE list = $1 AND creator = $2 AND name ILIKE '%' || $3 || '%'
+           ORDER BY last_used DESC"#,
+        list,
+        user.id,
+        search.search.as_deref().unwrap_or_default(),
+    )
+    .fetch_all(&state.0.pool)
+    .await?;
 
     OkResponse::ok(GetHistoryResponse {
-        matches: results.into_iter().filter_map(|row| row.name).collect(),
+        matches: results.iter().map(|row| row.name.clone()).collect(),
+        suggestions: results
+            .into_iter()
+            .map(|row| HistorySuggestion {
+                name: row.name,
+                amount: row.amount,
+            })
+            .collect(),
+    })
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+struct QuickAddQuery {
+    limit: Option<i64>,
+}
+
+/// Maximum size of a request body, well above what a single JSON request needs
+const BODY_LIMIT: usize = 64 * 1024;
+/// Maximum size of a request body for the routes acting on many items at once
+pub(crate) const BULK_BODY_LIMIT: usize = 1024 * 1024;
+
+const DEFAULT_QUICKADD_LIMIT: i64 = 10;
+const MAX_QUICKADD_LIMIT: i64 = 50;
+
+#[utoipa::path(
+    get,
+    path = "/api/quickadd",
+    responses(
+        (status = 200, description = "Most used item names", body = OkQuickAddResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("limit" = Option<i64>, Query, description = "Maximum number of names")
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn quickadd(state: State, user: User, query: Query<QuickAddQuery>) -> Rsp<QuickAddResponse> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_QUICKADD_LIMIT)
+        .clamp(0, MAX_QUICKADD_LIMIT);
+
+    let results = sqlx::query!(
+        r#"SELECT name::text
+               FROM history
+               WHERE creator = $1
+                   AND (list IN (SELECT id FROM lists WHERE owner = $1)
+                       OR list IN (SELECT list FROM list_sharing WHERE shared = $1))
+               GROUP BY name
+               ORDER BY COUNT(*) DESC, MAX(last_used) DESC
+               LIMIT $2"#,
+        user.id,
+        limit,
+    )
+    .fetch_all(&state.0.pool)
+    .await?;
+
+    OkResponse::ok(QuickAddResponse {
+        names: results.into_iter().filter_map(|row| row.name).collect(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/meta",
+    responses(
+        (status = 200, description = "Server time and version", body = OkMetaResponse),
+    ),
+)]
+#[tracing::instrument]
+async fn meta() -> Rsp<MetaResponse> {
+    OkResponse::ok(MetaResponse {
+        server_time: chrono::Utc::now(),
+        version: env!("CARGO_PKG_VERSION").into(),
     })
 }
 
 struct KabalistState {
     pool: PgPool,
     config: config::Config,
+    pantry_alerts: pantry::PantryAlerts,
 }
 
 type State = axum::extract::State<Arc<KabalistState>>;
 
+/// Installs the extensions needed by the migrations, then runs them
+async fn setup_database(db: &PgPool) -> color_eyre::Result<()> {
+    tracing::info!("Inserting extensions");
+    sqlx::query!("CREATE EXTENSION IF NOT EXISTS pgcrypto;")
+        .execute(db)
+        .await?;
+    sqlx::query!("CREATE EXTENSION IF NOT EXISTS citext;")
+        .execute(db)
+        .await?;
+    sqlx::query!(r#"CREATE EXTENSION IF NOT EXISTS "uuid-ossp";"#)
+        .execute(db)
+        .await?;
+
+    tracing::info!("Running SQLx migrations");
+    sqlx::migrate!("sqlx/migrations").run(db).await?;
+
+    Ok(())
+}
+
+/// Routes served under `/api`
+fn api_router(state: Arc<KabalistState>) -> Router {
+    Router::<Arc<KabalistState>>::new()
+        .route("/search/list/{name}", get(search_list))
+        .route("/search/account/{name}", get(search_account))
+        .route("/history/{id}", get(history_search))
+        .route("/quickadd", get(quickadd))
+        .route("/meta", get(meta))
+        .route("/unseen", get(list::unseen_lists))
+        .route("/p/{code}", get(list::get_public_list_by_code))
+        .route("/lists/read", post(list::read_lists))
+        .nest(
+            "/list",
+            list::router().route_layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                list::seen_after_write,
+            )),
+        )
+        .nest("/share", share::router())
+        .nest("/contacts", contacts::router())
+        .nest("/account", account::router())
+        .nest("/admin", admin::router())
+        .nest("/pantry", pantry::router())
+        .fallback(not_found)
+        // Bodies over the limit are rejected with 413 Payload Too Large
+        .layer(DefaultBodyLimit::max(BODY_LIMIT))
+        .layer(axum::middleware::map_response(body_too_large))
+        .with_state(state)
+}
+
+/// OpenAPI description of the routes, served by the swagger UI
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        search_list,
+        search_account,
+        history_search,
+        quickadd,
+        meta,
+        list::create_list,
+        list::update_item,
+        list::bulk_update,
+        list::import_text,
+        list::check_all,
+        list::uncheck_all,
+        list::compact_positions,
+        list::move_item,
+        list::item_to_pantry,
+        list::receipt,
+        list::list_changes,
+        list::search_items,
+        list::delete_item,
+        list::list_lists,
+        list::read_list,
+        list::read_lists,
+        list::update_list,
+        list::list_info,
+        list::add_list,
+        list::delete_list,
+        list::set_public,
+        list::remove_public,
+        list::archive_list,
+        list::unarchive_list,
+        list::add_tag,
+        list::remove_tag,
+        list::get_public_list,
+        list::get_public_list_by_code,
+        list::unseen_lists,
+        account::login,
+        account::validate_token,
+        account::create_api_key,
+        account::leave_shares,
+        account::get_shared_lists,
+        account::get_public_lists,
+        account::export_account,
+        account::register,
+        admin::admin_register,
+        account::recovery_info,
+        account::recover_password,
+        account::get_account_name,
+        share::delete_shares,
+        share::unshare,
+        share::get_shares,
+        share::share_list,
+        share::share_many,
+        share::cleanup_shares,
+        contacts::get_contacts,
+        contacts::add_contact,
+        contacts::delete_contact,
+        share::create_link,
+        share::revoke_link,
+        account::create_guest_token,
+        account::revoke_guest_token,
+        share::read_link,
+        share::add_with_link,
+        pantry::get_pantry,
+        pantry::pantry_status,
+        pantry::pantry_alerts,
+        pantry::move_pantry_item,
+        pantry::add_to_pantry,
+        pantry::batch_add_to_pantry,
+        pantry::refill_pantry,
+        pantry::set_pantry_item,
+        pantry::delete_pantry_item,
+        pantry::consume_pantry_item,
+    ),
+    components(
+        schemas(
+            UserError,
+            RspErr,
+            Error,
+            SecretString,
+            CreateListRequest,
+            UpdateListRequest,
+            LoginRequest,
+            LoginProfile,
+            ValidateTokenRequest,
+            CreateApiKeyRequest,
+            TokenScope,
+            ListInfo,
+            ListTagsResponse,
+            UnseenList,
+            UnseenResponse,
+            ListDetail,
+            ListStatus,
+            ItemSort,
+            ItemFields,
+            ItemStatus,
+            MinimalItem,
+            SearchItemsResponse,
+            MinimalReadListResponse,
+            CaseMode,
+            ListSort,
+            Item,
+            AddToListRequest,
+            UpdateItemRequest,
+            ItemUpdate,
+            BulkUpdateRequest,
+            PositionRequest,
+            ToPantryRequest,
+            ReceiptItem,
+            ShareListRequest,
+            ShareManyRequest,
+            Contact,
+            GetContactsResponse,
+            AddContactRequest,
+            AddContactResponse,
+            DeleteContactResponse,
+            CreateShareLinkRequest,
+            CreateShareLinkResponse,
+            RevokeShareLinkResponse,
+            CreateGuestTokenResponse,
+            RevokeGuestTokenResponse,
+            ShareInfo,
+            ShareRejection,
+            RejectedShare,
+            PreviewItem,
+            RecoverPasswordRequest,
+            RegisterRequest,
+            PantryItem,
+            AddToPantryRequest,
+            BatchPantryRequest,
+            BatchPantryResponse,
+            EditPantryItemRequest,
+            ConsumeRequest,
+            OkLoginResponse,
+            OkValidateTokenResponse,
+            OkCreateApiKeyResponse,
+            OkAffectedResponse,
+            OkImportResponse,
+            OkGetSharedListsResponse,
+            OkGetPublicListsResponse,
+            OkAccountExportResponse,
+            OkCreateListResponse,
+            OkCreateShareLinkResponse,
+            OkRevokeShareLinkResponse,
+            OkCreateGuestTokenResponse,
+            OkRevokeGuestTokenResponse,
+            OkGetListsResponse,
+            OkListDetail,
+            OkListTagsResponse,
+            OkUnseenResponse,
+            OkSearchAccountResponse,
+            OkReadListResponse,
+            OkMinimalReadListResponse,
+            OkMultiReadResponse,
+            OkAddToListResponse,
+            OkGetHistoryResponse,
+            OkQuickAddResponse,
+            OkMetaResponse,
+            OkUpdateItemResponse,
+            OkPositionResponse,
+            OkPantryStatusResponse,
+            OkToPantryResponse,
+            OkUpdateListResponse,
+            OkReceiptResponse,
+            OkListChangesResponse,
+            OkSearchItemsResponse,
+            OkDeleteItemResponse,
+            OkDeleteListResponse,
+            OkUnshareResponse,
+            OkGetSharesResponse,
+            OkShareListResponse,
+            OkShareManyResponse,
+            OkDeleteShareResponse,
+            OkGetContactsResponse,
+            OkAddContactResponse,
+            OkDeleteContactResponse,
+            OkRecoveryInfoResponse,
+            OkRecoverPasswordResponse,
+            OkRegisterResponse,
+            OkAdminRegisterResponse,
+            OkGetAccountNameResponse,
+            OkSetPublicResponse,
+            OkRemovePublicResponse,
+            OkArchiveListResponse,
+            OkUnarchiveListResponse,
+            OkGetPantryResponse,
+            OkAddToPantryResponse,
+            OkBatchPantryResponse,
+            OkRefillPantryResponse,
+            OkEditPantryItemResponse,
+            OkDeletePantryItemResponse,
+            OkConsumeResponse,
+            ErrResponse,
+            LoginResponse,
+            ValidateTokenResponse,
+            CreateApiKeyResponse,
+            AffectedResponse,
+            ImportResponse,
+            ImportError,
+            ImportErrorReason,
+            SharedList,
+            GetSharedListsResponse,
+            PublicList,
+            GetPublicListsResponse,
+            ExportedList,
+            ExportedHistoryEntry,
+            AccountExportResponse,
+            CreateListResponse,
+            GetListsResponse,
+            SearchAccountResponse,
+            ReadListResponse,
+            MultiReadRequest,
+            MultiReadResponse,
+            AddToListResponse,
+            GetHistoryResponse,
+            HistorySuggestion,
+            QuickAddResponse,
+            MetaResponse,
+            UpdateItemResponse,
+            PositionResponse,
+            PantryStatusResponse,
+            ToPantryResponse,
+            UpdateListResponse,
+            ReceiptResponse,
+            ListChangesResponse,
+            DeletedItem,
+            DeleteItemResponse,
+            DeleteListResponse,
+            UnshareResponse,
+            GetSharesResponse,
+            ShareListResponse,
+            ShareManyResponse,
+            DeleteShareResponse,
+            RecoveryInfoResponse,
+            RecoverPasswordResponse,
+            RegisterResponse,
+            AdminRegisterResponse,
+            GetAccountNameResponse,
+            RemovePublicResponse,
+            SetPublicResponse,
+            ArchiveListResponse,
+            UnarchiveListResponse,
+            GetPantryResponse,
+            AddToPantryResponse,
+            RefillPantryResponse,
+            EditPantryItemResponse,
+            DeletePantryItemResponse,
+            ConsumeResponse,
+            PantryAlert,
+        ),
+    ),
+    modifiers(&SecurityKey),
+)]
+struct ApiDoc;
+
+/// Documents the bearer token accepted by the routes
+struct SecurityKey;
+
+impl Modify for SecurityKey {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "token",
+                SecurityScheme::Http(security::Http::new(security::HttpAuthScheme::Bearer)),
+            );
+            components.add_security_scheme(
+                "admin_key",
+                SecurityScheme::ApiKey(security::ApiKey::Header(security::ApiKeyValue::new(
+                    admin::ADMIN_KEY_HEADER.as_str(),
+                ))),
+            );
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
@@ -447,159 +1148,28 @@ async fn main() -> color_eyre::Result<()> {
     tracing::info!("Starting with config: {:#?}", config);
     let addr = SocketAddr::from((config.listen_addr, config.port));
 
-    /// Allowed dead_code until the we reuse swagger-ui (aka axum 0.8.0)
-    #[allow(dead_code)]
-    #[derive(OpenApi)]
-    #[openapi(
-        paths(
-            search_list,
-            search_account,
-            history_search,
-            list::create_list,
-            list::update_item,
-            list::delete_item,
-            list::list_lists,
-            list::read_list,
-            list::add_list,
-            list::delete_list,
-            list::set_public,
-            list::remove_public,
-            list::get_public_list,
-            account::login,
-            account::register,
-            account::recovery_info,
-            account::recover_password,
-            account::get_account_name,
-            share::delete_shares,
-            share::unshare,
-            share::get_shares,
-            share::share_list,
-            pantry::get_pantry,
-            pantry::add_to_pantry,
-            pantry::refill_pantry,
-            pantry::set_pantry_item,
-            pantry::delete_pantry_item,
-        ),
-        components(
-            schemas(
-                UserError,
-                Error,
-                SecretString,
-                CreateListRequest,
-                LoginRequest,
-                ListInfo,
-                ListStatus,
-                Item,
-                AddToListRequest,
-                UpdateItemRequest,
-                ShareListRequest,
-                RecoverPasswordRequest,
-                RegisterRequest,
-                PantryItem,
-                AddToPantryRequest,
-                EditPantryItemRequest,
-                OkLoginResponse,
-                OkCreateListResponse,
-                OkGetListsResponse,
-                OkSearchAccountResponse,
-                OkReadListResponse,
-                OkAddToListResponse,
-                OkGetHistoryResponse,
-                OkUpdateItemResponse,
-                OkDeleteItemResponse,
-                OkDeleteListResponse,
-                OkUnshareResponse,
-                OkGetSharesResponse,
-                OkShareListResponse,
-                OkDeleteShareResponse,
-                OkRecoveryInfoResponse,
-                OkRecoverPasswordResponse,
-                OkRegisterResponse,
-                OkGetAccountNameResponse,
-                OkSetPublicResponse,
-                OkRemovePublicResponse,
-                OkGetPantryResponse,
-                OkAddToPantryResponse,
-                OkRefillPantryResponse,
-                OkEditPantryItemResponse,
-                OkDeletePantryItemResponse,
-                OkCreateListResponse,
-                ErrResponse,
-                LoginResponse,
-                CreateListResponse,
-                GetListsResponse,
-                SearchAccountResponse,
-                ReadListResponse,
-                AddToListResponse,
-                GetHistoryResponse,
-                UpdateItemResponse,
-                DeleteItemResponse,
-                DeleteListResponse,
-                UnshareResponse,
-                GetSharesResponse,
-                ShareListResponse,
-                DeleteShareResponse,
-                RecoveryInfoResponse,
-                RecoverPasswordResponse,
-                RegisterResponse,
-                GetAccountNameResponse,
-                RemovePublicResponse,
-                SetPublicResponse,
-                GetPantryResponse,
-                AddToPantryResponse,
-                RefillPantryResponse,
-                EditPantryItemResponse,
-                DeletePantryItemResponse,
-            ),
-        ),
-        modifiers(&SecurityKey),
-    )]
-    struct ApiDoc;
-
-    struct SecurityKey;
-
-    impl Modify for SecurityKey {
-        fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
-            if let Some(components) = openapi.components.as_mut() {
-                components.add_security_scheme(
-                    "token",
-                    SecurityScheme::Http(security::Http::new(security::HttpAuthScheme::Bearer)),
-                )
-            }
-        }
-    }
-
     tracing::info!("Opening database");
-    let db = PgPoolOptions::new().connect(&config.database_url).await?;
-
-    tracing::info!("Inserting extensions");
-    sqlx::query!("CREATE EXTENSION IF NOT EXISTS pgcrypto;")
-        .execute(&db)
-        .await?;
-    sqlx::query!("CREATE EXTENSION IF NOT EXISTS citext;")
-        .execute(&db)
-        .await?;
-    sqlx::query!(r#"CREATE EXTENSION IF NOT EXISTS "uuid-ossp";"#)
-        .execute(&db)
+    let db = PgPoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .acquire_timeout(Duration::from_secs(config.db_acquire_timeout))
+        .idle_timeout(
+            (config.db_idle_timeout != 0).then(|| Duration::from_secs(config.db_idle_timeout)),
+        )
+        .connect(&config.database_url)
         .await?;
 
-    tracing::info!("Running SQLx migrations");
-    sqlx::migrate!("sqlx/migrations").run(&db).await?;
+    setup_database(&db).await?;
 
     #[cfg(feature = "frontend")]
     let frontend = config.frontend.clone();
 
     let allow_origin = config.cors_allow_origin.parse::<HeaderValue>()?;
 
-    let api = Router::<Arc<KabalistState>>::new()
-        .route("/search/list/{name}", get(search_list))
-        .route("/search/account/{name}", get(search_account))
-        .route("/history/{id}", get(history_search))
-        .nest("/list", list::router())
-        .nest("/share", share::router())
-        .nest("/account", account::router())
-        .nest("/pantry", pantry::router())
-        .with_state(Arc::new(KabalistState { config, pool: db }));
+    let api = api_router(Arc::new(KabalistState {
+        config,
+        pool: db,
+        pantry_alerts: Default::default(),
+    }));
 
     let app = Router::new()
         .merge(utoipa_swagger_ui::SwaggerUi::new("/swagger-ui").url("/api-doc/openapi.json", ApiDoc::openapi()))
@@ -607,7 +1177,11 @@ async fn main() -> color_eyre::Result<()> {
         .layer(
             CorsLayer::new()
                 .allow_origin(allow_origin)
-                .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+                .allow_headers([
+                    header::CONTENT_TYPE,
+                    header::AUTHORIZATION,
+                    admin::ADMIN_KEY_HEADER,
+                ])
                 .allow_methods([
                     Method::GET,
                     Method::PATCH,
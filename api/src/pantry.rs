@@ -1,28 +1,108 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 use axum::{
-    extract::Path,
+    extract::{DefaultBodyLimit, Query},
+    response::sse::{Event, KeepAlive, Sse},
     routing::{get, patch, post},
     Json, Router,
 };
 use kabalist_types::{
-    AddToPantryRequest, AddToPantryResponse, DeletePantryItemResponse, EditPantryItemRequest,
-    EditPantryItemResponse, GetPantryResponse, PantryItem, RefillPantryResponse,
+    AddToPantryRequest, AddToPantryResponse, BatchPantryRequest, BatchPantryResponse,
+    ConsumeRequest, ConsumeResponse, DeletePantryItemResponse, EditPantryItemRequest,
+    EditPantryItemResponse, GetPantryResponse, PantryAlert, PantryItem, PantryStatusResponse,
+    PositionRequest, PositionResponse, PreviewItem, RefillPantryResponse,
 };
+use serde::Deserialize;
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
 use uuid::Uuid;
 
 use crate::{
-    account::User, check_list, ok_response::*, ErrResponse, KabalistState, OkResponse, Rsp, State,
+    account::User,
+    check_item_id, check_list,
+    list::{mark_seen, move_to, Positioned, POSITION_GAP},
+    ok_response::*,
+    on_unique_violation, ErrResponse, Error, KabalistState, ListUser, OkResponse, Path, Rsp, State,
 };
 
 pub(crate) fn router() -> Router<Arc<KabalistState>> {
     Router::new()
         .route("/{id}", get(get_pantry).post(add_to_pantry))
+        .route(
+            "/{id}/batch",
+            post(batch_add_to_pantry).layer(DefaultBodyLimit::max(crate::BULK_BODY_LIMIT)),
+        )
         .route("/{id}/refill", post(refill_pantry))
+        .route("/{id}/status", get(pantry_status))
+        .route("/{id}/alerts", get(pantry_alerts))
         .route(
             "/{id}/{item}",
             patch(set_pantry_item).delete(delete_pantry_item),
         )
+        .route("/{id}/{item}/consume", post(consume_pantry_item))
+        .route("/{id}/{item}/position", post(move_pantry_item))
+}
+
+/// Number of alerts kept for a subscriber that is not keeping up, older ones are dropped
+const ALERT_CAPACITY: usize = 16;
+
+/// Channels on which low stock alerts are sent, one per list with subscribers
+#[derive(Default)]
+pub(crate) struct PantryAlerts {
+    channels: Mutex<HashMap<Uuid, broadcast::Sender<PantryAlert>>>,
+}
+
+impl PantryAlerts {
+    fn subscribe(&self, list: Uuid) -> broadcast::Receiver<PantryAlert> {
+        let mut channels = self.channels.lock().unwrap();
+        // Subscribers that went away leave their channel behind, drop the ones nobody listens to
+        channels.retain(|_, sender| sender.receiver_count() > 0);
+        channels
+            .entry(list)
+            .or_insert_with(|| broadcast::channel(ALERT_CAPACITY).0)
+            .subscribe()
+    }
+
+    fn publish(&self, list: Uuid, alert: PantryAlert) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(&list) {
+            if sender.receiver_count() == 0 {
+                channels.remove(&list);
+            } else {
+                // Only fails when the last subscriber left since the count, the alert is lost anyway
+                let _ = sender.send(alert);
+            }
+        }
+    }
+
+    /// Publishes `alert` if its item went below its threshold, `was_low` being its previous state
+    fn publish_crossing(&self, list: Uuid, was_low: bool, alert: PantryAlert) {
+        if !was_low && alert.amount < alert.threshold {
+            self.publish(list, alert);
+        }
+    }
+}
+
+impl std::fmt::Debug for PantryAlerts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PantryAlerts").finish_non_exhaustive()
+    }
+}
+
+fn display_amount(amount: f64, target: f64, unit: Option<&str>) -> String {
+    match unit {
+        Some(unit) => format!("{amount} / {target} {unit}"),
+        None => format!("{amount} / {target}"),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct PantryQuery {
+    needs_restock: Option<bool>,
 }
 
 #[utoipa::path(
@@ -35,34 +115,136 @@ pub(crate) fn router() -> Router<Arc<KabalistState>> {
     ),
     params(
         ("id" = Uuid, Path, description = "List ID"),
+        ("needs_restock" = Option<bool>, Query, description = "Only return items that a refill would add"),
     ),
     security(
         ("token" = [])
     )
 )]
 #[tracing::instrument(skip(state))]
-async fn get_pantry(state: State, user: User, Path(list): Path<Uuid>) -> Rsp<GetPantryResponse> {
+async fn get_pantry(
+    state: State,
+    ListUser(user): ListUser,
+    Path(list): Path<Uuid>,
+    Query(query): Query<PantryQuery>,
+) -> Rsp<GetPantryResponse> {
     check_list(&state.0.pool, user.id, list, false).await?;
-    let items = sqlx::query!("SELECT * FROM pantry_content WHERE list = $1", list)
-        .fetch_all(&state.0.pool)
-        .await?
-        .into_iter()
-        .map(|row| PantryItem {
-            name: row.name,
-            id: row.item,
-            amount: row.amount,
-            target: row.target,
-        })
-        .collect();
+    let items = pantry_items(&state.0.pool, list, query.needs_restock.unwrap_or(false)).await?;
     OkResponse::ok(GetPantryResponse { items })
 }
 
+/// Reads the pantry of a list, the caller is responsible for the access checks
+pub(crate) async fn pantry_items(
+    db: &PgPool,
+    list: Uuid,
+    needs_restock: bool,
+) -> Result<Vec<PantryItem>, Error> {
+    let items = sqlx::query!(
+        r#"SELECT item, name, amount::float8 as "amount!", target::float8 as "target!",
+                  min::float8, unit
+               FROM pantry_content
+               WHERE list = $1 AND (NOT $2 OR amount < COALESCE(min, target))
+               ORDER BY position, item"#,
+        list,
+        needs_restock
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| PantryItem {
+        display_amount: display_amount(row.amount, row.target, row.unit.as_deref()),
+        name: row.name,
+        id: row.item,
+        amount: row.amount,
+        target: row.target,
+        min: row.min,
+        unit: row.unit,
+    })
+    .collect();
+
+    Ok(items)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/pantry/{id}/status",
+    responses(
+        (status = 200, description = "Pantry Coverage", body = OkPantryStatusResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn pantry_status(
+    state: State,
+    ListUser(user): ListUser,
+    Path(list): Path<Uuid>,
+) -> Rsp<PantryStatusResponse> {
+    check_list(&state.0.pool, user.id, list, false).await?;
+
+    let status = sqlx::query!(
+        r#"SELECT COUNT(*) as "total_items!",
+                  COUNT(*) FILTER (WHERE amount < target) as "below_target!",
+                  (SUM(amount) / NULLIF(SUM(target), 0))::float8 as fill_ratio
+               FROM pantry_content
+               WHERE list = $1"#,
+        list
+    )
+    .fetch_one(&state.0.pool)
+    .await?;
+
+    OkResponse::ok(PantryStatusResponse {
+        total_items: status.total_items,
+        below_target: status.below_target,
+        fill_ratio: status.fill_ratio,
+    })
+}
+
+/// Streams a `low_stock` event each time an item of the pantry goes below its refill threshold
+#[utoipa::path(
+    get,
+    path = "/api/pantry/{id}/alerts",
+    responses(
+        (status = 200, description = "Low Stock Alerts", body = PantryAlert, content_type = "text/event-stream"),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn pantry_alerts(
+    state: State,
+    ListUser(user): ListUser,
+    Path(list): Path<Uuid>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, Error> {
+    check_list(&state.0.pool, user.id, list, false).await?;
+
+    // Alerts missed by a lagging subscriber are skipped
+    let alerts = BroadcastStream::new(state.0.pantry_alerts.subscribe(list))
+        .filter_map(|alert| alert.ok())
+        .map(|alert| Event::default().event("low_stock").json_data(alert));
+
+    Ok(Sse::new(alerts).keep_alive(KeepAlive::default()))
+}
+
 #[utoipa::path(
     post,
     path = "/api/pantry/{id}",
     responses(
         (status = 200, description = "Item Added", body = OkAddToPantryResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 409, description = "Name Already Used", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
     request_body = AddToPantryRequest,
@@ -80,26 +262,123 @@ async fn add_to_pantry(
     Path(list): Path<Uuid>,
     Json(request): Json<AddToPantryRequest>,
 ) -> Rsp<AddToPantryResponse> {
+    user.require_write()?;
     check_list(&state.0.pool, user.id, list, true).await?;
 
     sqlx::query!(
-        "INSERT INTO pantry_content (list, name, target) VALUES ($1, $2, $3)",
+        r#"INSERT INTO pantry_content (list, name, target, position)
+               VALUES ($1, $2, $3::float8,
+                   (SELECT COALESCE(MAX(position) + 1, 0) FROM pantry_content WHERE list = $1))
+               ON CONFLICT DO NOTHING
+               RETURNING item"#,
         list,
         request.name,
         request.target
     )
-    .execute(&state.0.pool)
-    .await?;
+    .fetch_optional(&state.0.pool)
+    .await?
+    .ok_or(Error::PantryItemExists)?;
 
     OkResponse::ok(AddToPantryResponse {})
 }
 
+/// Maximum number of items in a single [`batch_add_to_pantry`] call
+const MAX_PANTRY_BATCH: usize = 100;
+
+#[utoipa::path(
+    post,
+    path = "/api/pantry/{id}/batch",
+    responses(
+        (status = 200, description = "Items Added", body = OkBatchPantryResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = BatchPantryRequest,
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn batch_add_to_pantry(
+    state: State,
+    user: User,
+    Path(list): Path<Uuid>,
+    Json(request): Json<BatchPantryRequest>,
+) -> Rsp<BatchPantryResponse> {
+    user.require_write()?;
+    check_list(&state.0.pool, user.id, list, true).await?;
+    if request.items.len() > MAX_PANTRY_BATCH {
+        return Err(Error::BatchTooLarge);
+    }
+
+    let mut tx = state.0.pool.begin().await?;
+
+    let mut ids = Vec::with_capacity(request.items.len());
+    let mut alerts = Vec::new();
+    for item in request.items {
+        // Locks an existing item until the commit, so that its state before the upsert can't
+        // change under us. The upsert can't read it itself, as it doesn't see the rows it updates
+        let was_low = sqlx::query!(
+            r#"SELECT amount < COALESCE(min, target) as "low!"
+                   FROM pantry_content
+                   WHERE list = $1 AND lower(name) = lower($2)
+                   FOR UPDATE"#,
+            list,
+            item.name,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_none_or(|row| row.low);
+
+        // An item that is already in the pantry only gets its target updated
+        let row = sqlx::query!(
+            r#"INSERT INTO pantry_content (list, name, target, position)
+                   VALUES ($1, $2, $3::float8,
+                       (SELECT COALESCE(MAX(position) + 1, 0) FROM pantry_content WHERE list = $1))
+                   ON CONFLICT (list, lower(name)) DO UPDATE SET target = EXCLUDED.target
+                   RETURNING item, name, amount::float8 as "amount!",
+                             COALESCE(min, target)::float8 as "threshold!", unit"#,
+            list,
+            item.name,
+            item.target,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // A higher target can put an existing item below its threshold, new items never alert
+        alerts.push((
+            was_low,
+            PantryAlert {
+                item: row.item,
+                name: row.name,
+                amount: row.amount,
+                threshold: row.threshold,
+                unit: row.unit,
+            },
+        ));
+        ids.push(row.item);
+    }
+
+    tx.commit().await?;
+
+    for (was_low, alert) in alerts {
+        state.0.pantry_alerts.publish_crossing(list, was_low, alert);
+    }
+
+    OkResponse::ok(BatchPantryResponse { ids })
+}
+
 #[utoipa::path(
     patch,
     path = "/api/pantry/{id}/{item}",
     responses(
         (status = 200, description = "Item Edited", body = OkEditPantryItemResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 404, description = "Unknown Pantry Item", body = ErrResponse),
+        (status = 409, description = "Name Already Used", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
     request_body = EditPantryItemRequest,
@@ -118,32 +397,139 @@ async fn set_pantry_item(
     Path((list, item)): Path<(Uuid, i32)>,
     Json(request): Json<EditPantryItemRequest>,
 ) -> Rsp<EditPantryItemResponse> {
+    user.require_write()?;
+    check_item_id(item)?;
     check_list(&state.0.pool, user.id, list, true).await?;
 
-    sqlx::query!(
-        "
+    // The old state is read under a row lock so that concurrent edits can't both see the item above
+    // its threshold and each send an alert
+    let updated = sqlx::query!(
+        r#"
+        WITH old AS (
+            SELECT item, amount < COALESCE(min, target) as low
+                FROM pantry_content
+                WHERE list = $6 AND item = $7
+                FOR UPDATE
+        )
         UPDATE pantry_content
-            SET amount = COALESCE($1, amount),
-                target = COALESCE($2, target)
-            WHERE
-                list = $3 AND item = $4",
+            SET name = COALESCE($1, name),
+                amount = COALESCE($2::float8, amount),
+                target = COALESCE($3::float8, target),
+                min = COALESCE($4::float8, CASE WHEN $8 THEN NULL ELSE min END),
+                unit = NULLIF(COALESCE($5, unit), '')
+            FROM old
+            WHERE pantry_content.item = old.item
+            RETURNING name, amount::float8 as "amount!",
+                      COALESCE(min, target)::float8 as "threshold!", unit, old.low as "was_low!""#,
+        request.name,
         request.amount,
         request.target,
+        request.min,
+        request.unit,
         list,
-        item
+        item,
+        request.clear_min,
     )
-    .execute(&state.0.pool)
-    .await?;
+    .fetch_optional(&state.0.pool)
+    .await
+    // Names are unique in a pantry, ignoring case
+    .map_err(on_unique_violation(Error::PantryItemExists))?
+    .ok_or(Error::PantryItemNotFound)?;
+
+    state.0.pantry_alerts.publish_crossing(
+        list,
+        updated.was_low,
+        PantryAlert {
+            item,
+            name: updated.name,
+            amount: updated.amount,
+            threshold: updated.threshold,
+            unit: updated.unit,
+        },
+    );
 
     OkResponse::ok(EditPantryItemResponse {})
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/pantry/{id}/{item}/consume",
+    responses(
+        (status = 200, description = "Item Consumed", body = OkConsumeResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 404, description = "Unknown Pantry Item", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = ConsumeRequest,
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("item" = i32, Path, description = "Item ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn consume_pantry_item(
+    state: State,
+    user: User,
+    Path((list, item)): Path<(Uuid, i32)>,
+    Json(request): Json<ConsumeRequest>,
+) -> Rsp<ConsumeResponse> {
+    user.require_write()?;
+    check_item_id(item)?;
+    // Consuming a negative amount would add stock, which is what editing the amount is for
+    if request.amount <= 0.0 {
+        return Err(Error::InvalidAmount);
+    }
+    check_list(&state.0.pool, user.id, list, true).await?;
+
+    let remaining = sqlx::query!(
+        r#"WITH old AS (
+               SELECT item, amount < COALESCE(min, target) as low
+                   FROM pantry_content
+                   WHERE list = $2 AND item = $3
+                   FOR UPDATE
+           )
+           UPDATE pantry_content
+               SET amount = GREATEST(amount - $1::float8::numeric, 0)
+               FROM old
+               WHERE pantry_content.item = old.item
+               RETURNING name, amount::float8 as "amount!",
+                         COALESCE(min, target)::float8 as "threshold!", unit,
+                         old.low as "was_low!""#,
+        request.amount,
+        list,
+        item
+    )
+    .fetch_optional(&state.0.pool)
+    .await?
+    .ok_or(Error::PantryItemNotFound)?;
+
+    state.0.pantry_alerts.publish_crossing(
+        list,
+        remaining.was_low,
+        PantryAlert {
+            item,
+            name: remaining.name,
+            amount: remaining.amount,
+            threshold: remaining.threshold,
+            unit: remaining.unit,
+        },
+    );
+
+    OkResponse::ok(ConsumeResponse {
+        amount: remaining.amount,
+    })
+}
+
 #[utoipa::path(
     delete,
     path = "/api/pantry/{id}/{item}",
     responses(
         (status = 200, description = "Item Deleted", body = OkDeletePantryItemResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 404, description = "Unknown Pantry Item", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
     params(
@@ -160,6 +546,8 @@ async fn delete_pantry_item(
     user: User,
     Path((list, item)): Path<(Uuid, i32)>,
 ) -> Rsp<DeletePantryItemResponse> {
+    user.require_write()?;
+    check_item_id(item)?;
     check_list(&state.0.pool, user.id, list, true).await?;
 
     let mut tx = state.0.pool.begin().await?;
@@ -172,19 +560,73 @@ async fn delete_pantry_item(
     .execute(&mut *tx)
     .await?;
 
-    sqlx::query!(
+    let deleted = sqlx::query!(
         "DELETE FROM pantry_content WHERE item = $1 AND list = $2",
         item,
         list
     )
     .execute(&mut *tx)
-    .await?;
+    .await?
+    .rows_affected();
+
+    if deleted == 0 {
+        // Dropping the transaction rolls back the deletion of the list entries
+        return Err(Error::PantryItemNotFound);
+    }
 
     tx.commit().await?;
+    // The items removed from the list are the caller's own change
+    mark_seen(&state.0.pool, user.id, list).await;
 
     OkResponse::ok(DeletePantryItemResponse {})
 }
 
+const PANTRY_ITEMS: Positioned = Positioned {
+    table: "pantry_content",
+    id: "item",
+    not_found: Error::PantryItemNotFound,
+};
+
+/// Same as moving a list item, but in the pantry
+#[utoipa::path(
+    post,
+    path = "/api/pantry/{id}/{item}/position",
+    responses(
+        (status = 200, description = "New position of the item", body = OkPositionResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 404, description = "Unknown Pantry Item", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = PositionRequest,
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("item" = i32, Path, description = "Item ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+async fn move_pantry_item(
+    state: State,
+    user: User,
+    Path((list, item)): Path<(Uuid, i32)>,
+    Json(request): Json<PositionRequest>,
+) -> Rsp<PositionResponse> {
+    user.require_write()?;
+    check_item_id(item)?;
+    check_list(&state.0.pool, user.id, list, true).await?;
+
+    let position = move_to(&state.0.pool, PANTRY_ITEMS, list, item, request).await?;
+
+    OkResponse::ok(PositionResponse { position })
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct RefillQuery {
+    dry_run: Option<bool>,
+}
+
 #[utoipa::path(
     post,
     path = "/api/pantry/{id}/refill",
@@ -195,6 +637,7 @@ async fn delete_pantry_item(
     ),
     params(
         ("id" = Uuid, Path, description = "List ID"),
+        ("dry_run" = Option<bool>, Query, description = "Only return the items that would be added"),
     ),
     security(
         ("token" = [])
@@ -203,20 +646,833 @@ async fn delete_pantry_item(
 #[tracing::instrument(skip(state))]
 async fn refill_pantry(
     state: State,
-    user: User,
+    ListUser(user): ListUser,
     Path(list): Path<Uuid>,
+    Query(query): Query<RefillQuery>,
 ) -> Rsp<RefillPantryResponse> {
+    if query.dry_run.unwrap_or(false) {
+        check_list(&state.0.pool, user.id, list, false).await?;
+
+        let added = sqlx::query!(
+            r#"SELECT name, trim_scale(target - amount)::text as "amount!", item,
+                      (target - amount)::float8 as "needed!", target::float8 as "target!"
+                   FROM pantry_content
+                   WHERE amount < COALESCE(min, target) AND list = $1
+                   ORDER BY item"#,
+            list
+        )
+        .fetch_all(&state.0.pool)
+        .await?
+        .into_iter()
+        .map(|row| PreviewItem {
+            name: row.name,
+            amount: row.amount,
+            from_pantry: row.item,
+            needed: row.needed,
+            target: row.target,
+        })
+        .collect();
+
+        return OkResponse::ok(RefillPantryResponse { added });
+    }
+
+    user.require_write()?;
     check_list(&state.0.pool, user.id, list, true).await?;
 
-    sqlx::query!(
-        r#"INSERT INTO lists_content (list,name,amount,from_pantry)
-            SELECT list,name,(target - amount) as amount,item as from_pantry
-                FROM pantry_content
-                WHERE amount < target AND list = $1"#,
-        list
+    let added = sqlx::query!(
+        r#"WITH added AS (
+            INSERT INTO lists_content (list,name,amount,from_pantry,position,creator)
+                SELECT list,name,trim_scale(target - amount)::text as amount,item as from_pantry,
+                        (SELECT COALESCE(MAX(position) + $3, 0) FROM lists_content WHERE list = $1)
+                            + $3 * (row_number() OVER (ORDER BY item) - 1) as position,
+                        $2 as creator
+                    FROM pantry_content
+                    WHERE amount < COALESCE(min, target) AND list = $1
+                RETURNING name, amount, from_pantry
+            )
+            SELECT added.name, added.amount as "amount!", added.from_pantry as "from_pantry!",
+                   (pantry_content.target - pantry_content.amount)::float8 as "needed!",
+                   pantry_content.target::float8 as "target!"
+                FROM added
+                JOIN pantry_content ON pantry_content.item = added.from_pantry
+                ORDER BY added.from_pantry"#,
+        list,
+        user.id,
+        POSITION_GAP,
     )
-    .execute(&state.0.pool)
-    .await?;
+    .fetch_all(&state.0.pool)
+    .await?
+    .into_iter()
+    .map(|row| PreviewItem {
+        name: row.name,
+        amount: row.amount,
+        from_pantry: row.from_pantry,
+        needed: row.needed,
+        target: row.target,
+    })
+    .collect();
+    mark_seen(&state.0.pool, user.id, list).await;
+
+    OkResponse::ok(RefillPantryResponse { added })
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::tests::{assert_api_error, client, spawn, state, PASSWORD};
+
+    #[sqlx::test(migrations = false)]
+    async fn consume(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+
+        owner
+            .add_to_pantry(list, "Water".into(), 6.0)
+            .await
+            .unwrap();
+        let water = owner.pantry(list).await.unwrap().items[0].id;
+        owner
+            .edit_pantry_item(list, water, Some(4.0), None, None)
+            .await
+            .unwrap();
+
+        let consumed = owner.consume_pantry_item(list, water, 1.0).await.unwrap();
+        assert_eq!(consumed.amount, 3.0);
+        let consumed = owner.consume_pantry_item(list, water, 10.0).await.unwrap();
+        assert_eq!(consumed.amount, 0.0);
+
+        assert_api_error(
+            owner.consume_pantry_item(list, water + 1, 1.0).await,
+            Error::PantryItemNotFound,
+        );
+        assert_api_error(
+            owner.consume_pantry_item(list, water, 0.0).await,
+            Error::InvalidAmount,
+        );
+        assert_api_error(
+            owner.consume_pantry_item(list, water, -2.0).await,
+            Error::InvalidAmount,
+        );
+        assert_eq!(owner.pantry(list).await.unwrap().items[0].amount, 0.0);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn refill_below_min_or_target(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+
+        for name in ["Water", "Rice", "Pasta"] {
+            owner.add_to_pantry(list, name.into(), 6.0).await.unwrap();
+        }
+        let items = owner.pantry(list).await.unwrap().items;
+        let id = |name: &str| items.iter().find(|item| item.name == name).unwrap().id;
+        // Below the target without a min
+        owner
+            .edit_pantry_item(list, id("Water"), Some(4.0), None, None)
+            .await
+            .unwrap();
+        // Below the target but not below the min
+        owner
+            .edit_pantry_item(list, id("Rice"), Some(4.0), None, Some(2.0))
+            .await
+            .unwrap();
+        // Below the min
+        owner
+            .edit_pantry_item(list, id("Pasta"), Some(1.0), None, Some(2.0))
+            .await
+            .unwrap();
+
+        owner.refill_pantry(list).await.unwrap();
+        let mut refilled: Vec<_> = owner
+            .read(&list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| (item.name, item.amount))
+            .collect();
+        refilled.sort();
+        assert_eq!(
+            refilled,
+            [
+                ("Pasta".to_string(), Some("5".to_string())),
+                ("Water".to_string(), Some("2".to_string())),
+            ]
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn refill_dry_run(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (reader, reader_client) = client(&state, &url, "reader").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+        owner.share(&list, &reader, true).await.unwrap();
+
+        for name in ["Water", "Rice", "Salt"] {
+            owner.add_to_pantry(list, name.into(), 6.0).await.unwrap();
+        }
+        let items = owner.pantry(list).await.unwrap().items;
+        let id = |name: &str| items.iter().find(|item| item.name == name).unwrap().id;
+        // Salt is fully stocked, so only the other two need a refill
+        for (name, amount) in [("Water", 4.0), ("Rice", 1.5), ("Salt", 6.0)] {
+            owner
+                .edit_pantry_item(list, id(name), Some(amount), None, None)
+                .await
+                .unwrap();
+        }
+
+        let expected = vec![
+            PreviewItem {
+                name: "Water".into(),
+                amount: "2".into(),
+                from_pantry: id("Water"),
+                needed: 2.0,
+                target: 6.0,
+            },
+            PreviewItem {
+                name: "Rice".into(),
+                amount: "4.5".into(),
+                from_pantry: id("Rice"),
+                needed: 4.5,
+                target: 6.0,
+            },
+        ];
+
+        // Previewing only needs read access and leaves the list untouched
+        let preview = reader_client.preview_refill(list).await.unwrap().added;
+        assert_eq!(preview, expected);
+        assert!(owner.read(&list).await.unwrap().items.is_empty());
+        assert_api_error(reader_client.refill_pantry(list).await, Error::NotWritable);
+
+        let added = owner.refill_pantry(list).await.unwrap().added;
+        assert_eq!(added, expected);
+        let positions: Vec<_> = owner
+            .read(&list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| item.position)
+            .collect();
+        assert_eq!(positions, [0, POSITION_GAP]);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn needs_restock(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+
+        for name in ["Water", "Rice", "Pasta", "Salt"] {
+            owner.add_to_pantry(list, name.into(), 6.0).await.unwrap();
+        }
+        let items = owner.pantry(list).await.unwrap().items;
+        let id = |name: &str| items.iter().find(|item| item.name == name).unwrap().id;
+        for (name, amount, min) in [
+            ("Water", 4.0, None),
+            ("Rice", 4.0, Some(2.0)),
+            ("Pasta", 1.0, Some(2.0)),
+            ("Salt", 6.0, None),
+        ] {
+            owner
+                .edit_pantry_item(list, id(name), Some(amount), None, min)
+                .await
+                .unwrap();
+        }
+
+        let mut restock: Vec<_> = owner
+            .pantry_needing_restock(list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| item.name)
+            .collect();
+        restock.sort();
+        assert_eq!(restock, ["Pasta", "Water"]);
+        assert_eq!(owner.pantry(list).await.unwrap().items.len(), 4);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn pantry_status(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (reader, reader_client) = client(&state, &url, "reader").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+        owner.share(&list, &reader, true).await.unwrap();
+
+        let status = owner.pantry_status(list).await.unwrap();
+        assert_eq!(
+            status,
+            PantryStatusResponse {
+                total_items: 0,
+                below_target: 0,
+                fill_ratio: None,
+            }
+        );
+
+        for (name, target) in [("Water", 6.0), ("Rice", 6.0), ("Pasta", 4.0)] {
+            owner
+                .add_to_pantry(list, name.into(), target)
+                .await
+                .unwrap();
+        }
+        let items = owner.pantry(list).await.unwrap().items;
+        let id = |name: &str| items.iter().find(|item| item.name == name).unwrap().id;
+        // Below, at and above the target
+        for (name, amount) in [("Water", 3.0), ("Rice", 6.0), ("Pasta", 5.0)] {
+            owner
+                .edit_pantry_item(list, id(name), Some(amount), None, None)
+                .await
+                .unwrap();
+        }
+
+        // Reading the status only needs read access
+        let status = reader_client.pantry_status(list).await.unwrap();
+        assert_eq!(
+            status,
+            PantryStatusResponse {
+                total_items: 3,
+                below_target: 1,
+                fill_ratio: Some(14.0 / 16.0),
+            }
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn clear_min(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+
+        owner
+            .add_to_pantry(list, "Water".into(), 6.0)
+            .await
+            .unwrap();
+        let water = owner.pantry(list).await.unwrap().items[0].id;
+
+        owner
+            .edit_pantry_item(list, water, None, None, Some(2.0))
+            .await
+            .unwrap();
+        owner
+            .edit_pantry_item(list, water, Some(5.0), None, None)
+            .await
+            .unwrap();
+        assert_eq!(owner.pantry(list).await.unwrap().items[0].min, Some(2.0));
+
+        owner.clear_pantry_min(list, water).await.unwrap();
+        assert_eq!(owner.pantry(list).await.unwrap().items[0].min, None);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn rename(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+
+        for name in ["Water", "Rice"] {
+            owner.add_to_pantry(list, name.into(), 6.0).await.unwrap();
+        }
+        let items = owner.pantry(list).await.unwrap().items;
+        let id = |name: &str| items.iter().find(|item| item.name == name).unwrap().id;
+
+        owner
+            .rename_pantry_item(list, id("Rice"), "Basmati rice".into())
+            .await
+            .unwrap();
+        // Only the case changes, the item keeps its name
+        owner
+            .rename_pantry_item(list, id("Rice"), "Basmati Rice".into())
+            .await
+            .unwrap();
+        assert_api_error(
+            owner
+                .rename_pantry_item(list, id("Rice"), "water".into())
+                .await,
+            Error::PantryItemExists,
+        );
+        assert_api_error(
+            owner.add_to_pantry(list, "WATER".into(), 2.0).await,
+            Error::PantryItemExists,
+        );
+        assert_api_error(
+            owner
+                .rename_pantry_item(list, id("Water") + 100, "Salt".into())
+                .await,
+            Error::PantryItemNotFound,
+        );
+
+        let mut pantry: Vec<_> = owner
+            .pantry(list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| (item.name, item.target))
+            .collect();
+        pantry.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            pantry,
+            [
+                ("Basmati Rice".to_string(), 6.0),
+                ("Water".to_string(), 6.0)
+            ]
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn batch_add(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (reader, reader_client) = client(&state, &url, "reader").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+        owner.share(&list, &reader, true).await.unwrap();
+
+        owner
+            .add_to_pantry(list, "Water".into(), 6.0)
+            .await
+            .unwrap();
+        let water = owner.pantry(list).await.unwrap().items[0].id;
+
+        let item = |name: &str, target| AddToPantryRequest {
+            name: name.into(),
+            target,
+        };
+        let ids = owner
+            .batch_add_to_pantry(list, vec![item("Rice", 2.0), item("WATER", 8.0)])
+            .await
+            .unwrap()
+            .ids;
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids[1], water);
+
+        let mut pantry: Vec<_> = owner
+            .pantry(list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| (item.id, item.name, item.target))
+            .collect();
+        pantry.sort_by_key(|item| item.0);
+        assert_eq!(
+            pantry,
+            [
+                (water, "Water".to_string(), 8.0),
+                (ids[0], "Rice".to_string(), 2.0)
+            ]
+        );
+
+        assert_api_error(
+            owner
+                .batch_add_to_pantry(list, vec![item("Salt", 1.0); MAX_PANTRY_BATCH + 1])
+                .await,
+            Error::BatchTooLarge,
+        );
+        assert_api_error(
+            reader_client
+                .batch_add_to_pantry(list, vec![item("Salt", 1.0)])
+                .await,
+            Error::NotWritable,
+        );
+        assert_eq!(owner.pantry(list).await.unwrap().items.len(), 2);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn unknown_pantry_item(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+        let other = owner.create_list("Other").await.unwrap().id;
+
+        owner
+            .add_to_pantry(list, "Water".into(), 6.0)
+            .await
+            .unwrap();
+        let water = owner.pantry(list).await.unwrap().items[0].id;
+
+        assert_api_error(
+            owner
+                .edit_pantry_item(list, water + 1, Some(1.0), None, None)
+                .await,
+            Error::PantryItemNotFound,
+        );
+        assert_api_error(
+            owner.delete_pantry_item(list, water + 1).await,
+            Error::PantryItemNotFound,
+        );
+        // The item exists, but in another list
+        assert_api_error(
+            owner
+                .edit_pantry_item(other, water, Some(1.0), None, None)
+                .await,
+            Error::PantryItemNotFound,
+        );
+        assert_api_error(
+            owner.delete_pantry_item(other, water).await,
+            Error::PantryItemNotFound,
+        );
+
+        owner.delete_pantry_item(list, water).await.unwrap();
+        assert!(owner.pantry(list).await.unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn display_amounts() {
+        assert_eq!(display_amount(3.0, 6.0, Some("bottles")), "3 / 6 bottles");
+        assert_eq!(display_amount(0.0, 1.0, None), "0 / 1");
+        assert_eq!(display_amount(0.5, 1.5, Some("kg")), "0.5 / 1.5 kg");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn fractional_amounts(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+
+        owner
+            .add_to_pantry(list, "Flour".into(), 2.5)
+            .await
+            .unwrap();
+        let flour = owner.pantry(list).await.unwrap().items[0].id;
+        owner
+            .edit_pantry_item(list, flour, Some(1.25), None, Some(1.5))
+            .await
+            .unwrap();
+        let consumed = owner.consume_pantry_item(list, flour, 0.5).await.unwrap();
+        assert_eq!(consumed.amount, 0.75);
+
+        let added = owner.refill_pantry(list).await.unwrap().added;
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].amount, "1.75");
+
+        // Deleting the refilled item gives its amount back, fractions included
+        let item = owner.read(&list).await.unwrap().items[0].id;
+        owner
+            .update_item(&list, item, None, Some("0.25"))
+            .await
+            .unwrap();
+        owner.delete_item(&list, item).await.unwrap();
+        let flour = &owner.pantry(list).await.unwrap().items[0];
+        assert_eq!(flour.amount, 1.0);
+        assert_eq!(flour.target, 2.5);
+        assert_eq!(flour.min, Some(1.5));
+        assert_eq!(flour.display_amount, "1 / 2.5");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn units(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+
+        owner
+            .add_to_pantry(list, "Water".into(), 6.0)
+            .await
+            .unwrap();
+        let water = owner.pantry(list).await.unwrap().items[0].id;
+        owner
+            .edit_pantry_item(list, water, Some(3.0), None, None)
+            .await
+            .unwrap();
+
+        owner
+            .set_pantry_unit(list, water, "bottles".into())
+            .await
+            .unwrap();
+        let item = &owner.pantry(list).await.unwrap().items[0];
+        assert_eq!(item.unit.as_deref(), Some("bottles"));
+        assert_eq!(item.display_amount, "3 / 6 bottles");
+
+        // An empty unit removes it
+        owner.set_pantry_unit(list, water, "".into()).await.unwrap();
+        let item = &owner.pantry(list).await.unwrap().items[0];
+        assert_eq!(item.unit, None);
+        assert_eq!(item.display_amount, "3 / 6");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn non_positive_item_ids(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+
+        for item in [0, -1] {
+            assert_api_error(
+                owner
+                    .edit_pantry_item(list, item, Some(1.0), None, None)
+                    .await,
+                Error::InvalidItemId,
+            );
+            assert_api_error(
+                owner.consume_pantry_item(list, item, 1.0).await,
+                Error::InvalidItemId,
+            );
+            assert_api_error(
+                owner.delete_pantry_item(list, item).await,
+                Error::InvalidItemId,
+            );
+        }
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn alerts(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+
+        owner
+            .add_to_pantry(list, "Water".into(), 6.0)
+            .await
+            .unwrap();
+        let water = owner.pantry(list).await.unwrap().items[0].id;
+        owner
+            .edit_pantry_item(list, water, Some(6.0), None, None)
+            .await
+            .unwrap();
+
+        let mut alerts = state.pantry_alerts.subscribe(list);
+
+        owner.consume_pantry_item(list, water, 1.0).await.unwrap();
+        let alert = alerts.try_recv().unwrap();
+        assert_eq!(alert.item, water);
+        assert_eq!(alert.name, "Water");
+        assert_eq!(alert.amount, 5.0);
+        assert_eq!(alert.threshold, 6.0);
+
+        // Only crossing the threshold alerts
+        owner.consume_pantry_item(list, water, 1.0).await.unwrap();
+        owner
+            .edit_pantry_item(list, water, Some(10.0), None, None)
+            .await
+            .unwrap();
+        assert!(alerts.try_recv().is_err());
+
+        // A higher target raises the threshold, new items don't alert
+        owner
+            .batch_add_to_pantry(
+                list,
+                vec![
+                    AddToPantryRequest {
+                        name: "water".into(),
+                        target: 12.0,
+                    },
+                    AddToPantryRequest {
+                        name: "Rice".into(),
+                        target: 2.0,
+                    },
+                ],
+            )
+            .await
+            .unwrap();
+        let alert = alerts.try_recv().unwrap();
+        assert_eq!(alert.name, "Water");
+        assert_eq!(alert.amount, 10.0);
+        assert_eq!(alert.threshold, 12.0);
+        assert!(alerts.try_recv().is_err());
+
+        owner
+            .edit_pantry_item(list, water, None, None, Some(8.0))
+            .await
+            .unwrap();
+        owner
+            .edit_pantry_item(list, water, Some(7.0), None, None)
+            .await
+            .unwrap();
+        assert_eq!(alerts.try_recv().unwrap().threshold, 8.0);
+        assert!(alerts.try_recv().is_err());
+    }
+
+    #[test]
+    fn alert_channels_are_dropped() {
+        let alerts = PantryAlerts::default();
+        let (groceries, hardware) = (Uuid::new_v4(), Uuid::new_v4());
+        let channels = || alerts.channels.lock().unwrap().len();
+        let alert = || PantryAlert {
+            item: 1,
+            name: "Water".into(),
+            amount: 1.0,
+            threshold: 6.0,
+            unit: None,
+        };
+
+        let first = alerts.subscribe(groceries);
+        let mut second = alerts.subscribe(groceries);
+        assert_eq!(channels(), 1);
+        drop(first);
+        alerts.publish(groceries, alert());
+        assert_eq!(second.try_recv().unwrap(), alert());
+        assert_eq!(channels(), 1);
+
+        // Publishing without subscribers drops the channel
+        drop(second);
+        alerts.publish(groceries, alert());
+        assert_eq!(channels(), 0);
+
+        // So does subscribing to another list
+        drop(alerts.subscribe(groceries));
+        let _hardware = alerts.subscribe(hardware);
+        assert_eq!(channels(), 1);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn alerts_stream(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+        owner
+            .add_to_pantry(list, "Water".into(), 6.0)
+            .await
+            .unwrap();
+        let water = owner.pantry(list).await.unwrap().items[0].id;
+        owner
+            .edit_pantry_item(list, water, Some(6.0), None, None)
+            .await
+            .unwrap();
+
+        let token = kabalist_client::login(&url, "owner", PASSWORD)
+            .await
+            .unwrap()
+            .token;
+        let mut response = reqwest::Client::new()
+            .get(format!("{}/api/pantry/{}/alerts", url, list))
+            .bearer_auth(token)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        // The subscription exists once the headers are sent
+        owner.consume_pantry_item(list, water, 2.0).await.unwrap();
+
+        let mut body = String::new();
+        let event = loop {
+            let chunk = response.chunk().await.unwrap().unwrap();
+            body.push_str(std::str::from_utf8(&chunk).unwrap());
+            if let Some((event, _)) = body.split_once("\n\n") {
+                break event.to_owned();
+            }
+        };
+        let mut lines = event.lines();
+        assert_eq!(lines.next(), Some("event: low_stock"));
+        let data = lines.next().unwrap().strip_prefix("data: ").unwrap();
+        let alert: PantryAlert = serde_json::from_str(data).unwrap();
+        assert_eq!(
+            alert,
+            PantryAlert {
+                item: water,
+                name: "Water".into(),
+                amount: 4.0,
+                threshold: 6.0,
+                unit: None,
+            }
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn moves(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Pantry").await.unwrap().id;
+
+        for name in ["Water", "Rice", "Salt"] {
+            owner.add_to_pantry(list, name.into(), 1.0).await.unwrap();
+        }
+        let ids: Vec<_> = owner
+            .pantry(list)
+            .await
+            .unwrap()
+            .items
+            .iter()
+            .map(|item| item.id)
+            .collect();
+        let (water, rice, salt) = (ids[0], ids[1], ids[2]);
+
+        // Adjacent positions leave no room, so the following items are pushed
+        let moved = owner
+            .move_pantry_item(
+                &list,
+                salt,
+                PositionRequest {
+                    before: Some(rice),
+                    after: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(moved.position, 512);
+        owner
+            .move_pantry_item(
+                &list,
+                water,
+                PositionRequest {
+                    before: None,
+                    after: Some(rice),
+                },
+            )
+            .await
+            .unwrap();
+
+        let names: Vec<_> = owner
+            .pantry(list)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| item.name)
+            .collect();
+        assert_eq!(names, ["Salt", "Rice", "Water"]);
 
-    OkResponse::ok(RefillPantryResponse {})
+        assert_api_error(
+            owner
+                .move_pantry_item(
+                    &list,
+                    water,
+                    PositionRequest {
+                        before: Some(water + 100),
+                        after: None,
+                    },
+                )
+                .await,
+            Error::PantryItemNotFound,
+        );
+        assert_api_error(
+            owner
+                .move_pantry_item(
+                    &list,
+                    water,
+                    PositionRequest {
+                        before: Some(water),
+                        after: None,
+                    },
+                )
+                .await,
+            Error::InvalidPosition,
+        );
+    }
 }
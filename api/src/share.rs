@@ -1,18 +1,25 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::{
-    extract,
     routing::{delete, get},
     Json, Router,
 };
+use chrono::{DateTime, Utc};
 use kabalist_types::{
-    DeleteShareResponse, GetSharesResponse, ShareListRequest, ShareListResponse, UnshareResponse,
+    AddToListRequest, AddToListResponse, AffectedResponse, CreateShareLinkRequest,
+    CreateShareLinkResponse, DeleteShareResponse, GetSharesResponse, ReadListResponse,
+    RejectedShare, RevokeShareLinkResponse, ShareInfo, ShareListRequest, ShareListResponse,
+    ShareManyRequest, ShareManyResponse, ShareRejection, UnshareResponse,
 };
+use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::{
-    account::User, check_list, is_owner, ok_response::*, ErrResponse, KabalistState, OkResponse,
-    Rsp, State,
+    account::User,
+    check_list, created, is_owner,
+    list::{check_amount, check_unit, check_url, fetch_list, item_name, POSITION_GAP},
+    ok_response::*,
+    Created, ErrResponse, Error, KabalistState, OkResponse, Path, Rsp, State,
 };
 
 pub(crate) fn router() -> Router<Arc<KabalistState>> {
@@ -22,6 +29,7 @@ pub(crate) fn router() -> Router<Arc<KabalistState>> {
             get(get_shares).put(share_list).delete(delete_shares),
         )
         .route("/{id}/{account}", delete(unshare))
+        .route("/link/{token}", get(read_link).post(add_with_link))
 }
 
 #[utoipa::path(
@@ -40,27 +48,33 @@ pub(crate) fn router() -> Router<Arc<KabalistState>> {
     )
 )]
 #[tracing::instrument(skip(state))]
-async fn get_shares(
-    state: State,
-    user: User,
-    extract::Path(id): extract::Path<Uuid>,
-) -> Rsp<GetSharesResponse> {
+async fn get_shares(state: State, user: User, Path(id): Path<Uuid>) -> Rsp<GetSharesResponse> {
     check_list(&state.0.pool, user.id, id, true).await?;
 
+    let shared_with = list_shares(&state.0.pool, id).await?;
+    let shared_read_count = shared_with.values().filter(|&&readonly| readonly).count();
+
+    OkResponse::ok(GetSharesResponse {
+        public_link: None,
+        shared_write_count: shared_with.len() - shared_read_count,
+        shared_read_count,
+        shared_with,
+    })
+}
+
+/// Accounts a list is shared with, mapped to whether their share is read-only
+pub(crate) async fn list_shares(db: &PgPool, id: Uuid) -> Result<HashMap<Uuid, bool>, Error> {
     let shared = sqlx::query!(
         "SELECT shared, readonly FROM list_sharing WHERE list = $1",
         id
     )
-    .fetch_all(&state.0.pool)
+    .fetch_all(db)
     .await?;
 
-    OkResponse::ok(GetSharesResponse {
-        public_link: None,
-        shared_with: shared
-            .into_iter()
-            .map(|row| (row.shared, row.readonly))
-            .collect(),
-    })
+    Ok(shared
+        .into_iter()
+        .map(|row| (row.shared, row.readonly))
+        .collect())
 }
 
 #[utoipa::path(
@@ -69,6 +83,7 @@ async fn get_shares(
     responses(
         (status = 200, description = "Shared list", body = OkShareListResponse),
         (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 404, description = "Unknown Account", body = ErrResponse),
         (status = 500, description = "Internal Error", body = ErrResponse),
     ),
     request_body = ShareListRequest,
@@ -83,11 +98,23 @@ async fn get_shares(
 async fn share_list(
     state: State,
     user: User,
-    extract::Path(id): extract::Path<Uuid>,
+    Path(id): Path<Uuid>,
     Json(request): Json<ShareListRequest>,
 ) -> Rsp<ShareListResponse> {
+    user.require_write()?;
     check_list(&state.0.pool, user.id, id, true).await?;
 
+    let exists = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM accounts WHERE id = $1) as "exists!""#,
+        request.share_with
+    )
+    .fetch_one(&state.0.pool)
+    .await?
+    .exists;
+    if !exists {
+        return Err(Error::AccountNotFound);
+    }
+
     sqlx::query!(
         r#"
             INSERT INTO list_sharing (list, shared, readonly)
@@ -99,7 +126,108 @@ async fn share_list(
     .execute(&state.0.pool)
     .await?;
 
-    OkResponse::ok(ShareListResponse {})
+    // The list might already have been shared, so return what is actually stored
+    let share = sqlx::query!(
+        r#"SELECT list_sharing.shared, accounts.name::text as "username!", list_sharing.readonly
+               FROM list_sharing
+               JOIN accounts ON accounts.id = list_sharing.shared
+               WHERE list_sharing.list = $1 AND list_sharing.shared = $2"#,
+        id,
+        request.share_with
+    )
+    .fetch_one(&state.0.pool)
+    .await?;
+
+    OkResponse::ok(ShareListResponse {
+        share: ShareInfo {
+            account: share.shared,
+            username: share.username,
+            readonly: share.readonly,
+        },
+    })
+}
+
+/// Maximum number of accounts in a single [`share_many`] call
+const MAX_SHARE_MANY: usize = 100;
+
+#[utoipa::path(
+    post,
+    path = "/api/list/{id}/share-many",
+    responses(
+        (status = 200, description = "Shares applied", body = OkShareManyResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = ShareManyRequest,
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn share_many(
+    state: State,
+    user: User,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ShareManyRequest>,
+) -> Rsp<ShareManyResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+    if request.shares.len() > MAX_SHARE_MANY {
+        return Err(Error::BatchTooLarge);
+    }
+
+    let mut tx = state.0.pool.begin().await?;
+
+    let mut shared = Vec::new();
+    let mut rejected = Vec::new();
+    for share in request.shares {
+        // Only the owner can get here, so this also prevents sharing with oneself
+        if share.share_with == user.id {
+            rejected.push(RejectedShare {
+                account: share.share_with,
+                reason: ShareRejection::Owner,
+            });
+            continue;
+        }
+
+        let account = sqlx::query!(
+            r#"SELECT name::text as "username!" FROM accounts WHERE id = $1"#,
+            share.share_with
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(account) = account else {
+            rejected.push(RejectedShare {
+                account: share.share_with,
+                reason: ShareRejection::UnknownAccount,
+            });
+            continue;
+        };
+
+        sqlx::query!(
+            r#"INSERT INTO list_sharing (list, shared, readonly)
+                   VALUES ($1, $2, $3)
+                   ON CONFLICT (list, shared) DO UPDATE SET readonly = EXCLUDED.readonly"#,
+            id,
+            share.share_with,
+            share.readonly
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        shared.push(ShareInfo {
+            account: share.share_with,
+            username: account.username,
+            readonly: share.readonly,
+        });
+    }
+
+    tx.commit().await?;
+
+    OkResponse::ok(ShareManyResponse { shared, rejected })
 }
 
 #[utoipa::path(
@@ -122,8 +250,9 @@ async fn share_list(
 async fn unshare(
     state: State,
     user: User,
-    extract::Path((list, account)): extract::Path<(Uuid, Uuid)>,
+    Path((list, account)): Path<(Uuid, Uuid)>,
 ) -> Rsp<UnshareResponse> {
+    user.require_write()?;
     is_owner(&state.0.pool, user.id, list).await?;
 
     let mut tx = state.0.pool.begin().await?;
@@ -164,11 +293,8 @@ async fn unshare(
         ("token" = [])
     )
 )]
-async fn delete_shares(
-    state: State,
-    user: User,
-    extract::Path(id): extract::Path<Uuid>,
-) -> Rsp<DeleteShareResponse> {
+async fn delete_shares(state: State, user: User, Path(id): Path<Uuid>) -> Rsp<DeleteShareResponse> {
+    user.require_write()?;
     is_owner(&state.0.pool, user.id, id).await?;
 
     let mut tx = state.0.pool.begin().await?;
@@ -189,3 +315,548 @@ async fn delete_shares(
 
     OkResponse::ok(DeleteShareResponse {})
 }
+
+/// Removes the shares of accounts that no longer exist. The foreign key now drops them with the
+/// account, but databases that predate it can still hold some.
+#[utoipa::path(
+    post,
+    path = "/api/list/{id}/shares/cleanup",
+    responses(
+        (status = 200, description = "Number of shares removed", body = OkAffectedResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn cleanup_shares(
+    state: State,
+    user: User,
+    Path(id): Path<Uuid>,
+) -> Rsp<AffectedResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+
+    let removed = sqlx::query!(
+        "DELETE FROM list_sharing
+            WHERE list = $1 AND NOT EXISTS(SELECT 1 FROM accounts WHERE id = list_sharing.shared)",
+        id
+    )
+    .execute(&state.0.pool)
+    .await?
+    .rows_affected();
+
+    OkResponse::ok(AffectedResponse { affected: removed })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/list/{id}/links",
+    responses(
+        (status = 200, description = "Token of the new link", body = OkCreateShareLinkResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = CreateShareLinkRequest,
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn create_link(
+    state: State,
+    user: User,
+    Path(id): Path<Uuid>,
+    Json(request): Json<CreateShareLinkRequest>,
+) -> Rsp<CreateShareLinkResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+
+    let token = sqlx::query!(
+        "INSERT INTO share_links (list, readonly, expires_at) VALUES ($1, $2, $3) RETURNING token",
+        id,
+        request.readonly,
+        request.expires_at,
+    )
+    .fetch_one(&state.0.pool)
+    .await?
+    .token;
+
+    OkResponse::ok(CreateShareLinkResponse { token })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/list/{id}/links/{token}",
+    responses(
+        (status = 200, description = "Link revoked", body = OkRevokeShareLinkResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 404, description = "No such link", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("id" = Uuid, Path, description = "List ID"),
+        ("token" = Uuid, Path, description = "Share link token"),
+    ),
+    security(
+        ("token" = [])
+    )
+)]
+#[tracing::instrument(skip(state))]
+pub(crate) async fn revoke_link(
+    state: State,
+    user: User,
+    Path((id, token)): Path<(Uuid, Uuid)>,
+) -> Rsp<RevokeShareLinkResponse> {
+    user.require_write()?;
+    is_owner(&state.0.pool, user.id, id).await?;
+
+    let deleted = sqlx::query!(
+        "DELETE FROM share_links WHERE list = $1 AND token = $2",
+        id,
+        token
+    )
+    .execute(&state.0.pool)
+    .await?
+    .rows_affected();
+
+    if deleted == 0 {
+        return Err(Error::NotFound);
+    }
+
+    OkResponse::ok(RevokeShareLinkResponse {})
+}
+
+/// List targeted by a share link
+struct SharedLink {
+    list: Uuid,
+    owner: Uuid,
+    readonly: bool,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+async fn resolve_link(db: &PgPool, token: Uuid) -> Result<SharedLink, Error> {
+    let link = sqlx::query_as!(
+        SharedLink,
+        "SELECT list, owner, readonly, expires_at
+            FROM share_links
+            JOIN lists ON lists.id = share_links.list
+            WHERE token = $1",
+        token
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    match link.expires_at {
+        Some(expires_at) if expires_at <= Utc::now() => Err(Error::LinkExpired),
+        _ => Ok(link),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/share/link/{token}",
+    responses(
+        (status = 200, description = "List Content", body = OkReadListResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    params(
+        ("token" = Uuid, Path, description = "Share link token"),
+    ),
+)]
+#[tracing::instrument(skip(state))]
+async fn read_link(state: State, Path(token): Path<Uuid>) -> Rsp<ReadListResponse> {
+    let link = resolve_link(&state.0.pool, token).await?;
+
+    let mut list = fetch_list(
+        &state.0.pool,
+        link.owner,
+        link.list,
+        false,
+        None,
+        None,
+        None,
+    )
+    .await?;
+    list.readonly = link.readonly;
+    // Links can only add items, never edit existing ones
+    for item in &mut list.items {
+        item.editable = false;
+    }
+
+    OkResponse::ok(list)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/share/link/{token}",
+    responses(
+        (status = 201, description = "New Item", body = OkAddToListResponse),
+        (status = 400, description = "Invalid request", body = ErrResponse),
+        (status = 500, description = "Internal Error", body = ErrResponse),
+    ),
+    request_body = AddToListRequest,
+    params(
+        ("token" = Uuid, Path, description = "Share link token"),
+    ),
+)]
+#[tracing::instrument(skip(state))]
+async fn add_with_link(
+    state: State,
+    Path(token): Path<Uuid>,
+    Json(item): Json<AddToListRequest>,
+) -> Created<AddToListResponse> {
+    let link = resolve_link(&state.0.pool, token).await?;
+    if link.readonly {
+        return Err(Error::NotWritable);
+    }
+
+    if let Some(url) = &item.url {
+        check_url(url)?;
+    }
+    let name = item_name(&state.0.pool, link.list, &item.name).await?;
+    let unit = item
+        .unit
+        .as_deref()
+        .map(str::trim)
+        .filter(|unit| !unit.is_empty());
+    if let Some(unit) = unit {
+        check_unit(&state.0.pool, link.list, unit).await?;
+    }
+    // Link holders don't get the default amount of the list
+    check_amount(&state.0.pool, link.list, item.amount.as_deref(), false).await?;
+
+    // Link holders have no account, so their items are attributed to the owner of the list
+    let item_id = sqlx::query!(
+        r#"INSERT INTO lists_content (list, name, amount, position, url, creator, unit, category)
+               VALUES ($1, $2, $3,
+                   (SELECT COALESCE(MAX(position) + $7, 0) FROM lists_content WHERE list = $1),
+                   $4, $5, $6, NULLIF(TRIM($8), ''))
+               RETURNING id"#,
+        link.list,
+        name,
+        item.amount,
+        item.url,
+        link.owner,
+        unit,
+        POSITION_GAP,
+        item.category,
+    )
+    .fetch_one(&state.0.pool)
+    .await?
+    .id;
+
+    created(
+        format!("/api/list/{}/{}", link.list, item_id),
+        AddToListResponse {
+            id: item_id,
+            duplicate_of: None,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use kabalist_client::RspData;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::tests::{assert_api_error, client, spawn, state};
+
+    #[sqlx::test(migrations = false)]
+    async fn share_returns_the_share(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (friend, _) = client(&state, &url, "Friend").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let expected = ShareInfo {
+            account: friend,
+            username: "Friend".into(),
+            readonly: true,
+        };
+        let shared = owner.share(&list, &friend, true).await.unwrap();
+        assert_eq!(shared.share, expected);
+        // Sharing again keeps the existing share, and reports it
+        let shared = owner.share(&list, &friend, false).await.unwrap();
+        assert_eq!(shared.share, expected);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn share_with_unknown_account(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        assert_api_error(
+            owner.share(&list, &Uuid::new_v4(), true).await,
+            Error::AccountNotFound,
+        );
+        assert!(owner
+            .get_shares(&list)
+            .await
+            .unwrap()
+            .shared_with
+            .is_empty());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn share_counts(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (friend, _) = client(&state, &url, "friend").await;
+        let (family, _) = client(&state, &url, "family").await;
+        let (other, _) = client(&state, &url, "other").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let shares = owner.get_shares(&list).await.unwrap();
+        assert_eq!(
+            (shares.shared_read_count, shares.shared_write_count),
+            (0, 0)
+        );
+
+        owner.share(&list, &friend, true).await.unwrap();
+        owner.share(&list, &family, false).await.unwrap();
+        owner.share(&list, &other, true).await.unwrap();
+        let shares = owner.get_shares(&list).await.unwrap();
+        assert_eq!(shares.shared_with.len(), 3);
+        assert_eq!(shares.shared_read_count, 2);
+        assert_eq!(shares.shared_write_count, 1);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn share_with_several_accounts(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (friend, _) = client(&state, &url, "friend").await;
+        let (family, _) = client(&state, &url, "family").await;
+        let (owner_id, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        owner.share(&list, &friend, true).await.unwrap();
+
+        let unknown = Uuid::new_v4();
+        let share = |share_with, readonly| ShareListRequest {
+            share_with,
+            readonly,
+        };
+        let result = owner
+            .share_many(
+                &list,
+                vec![
+                    share(friend, false),
+                    share(unknown, true),
+                    share(family, true),
+                    share(owner_id, false),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            ShareManyResponse {
+                shared: vec![
+                    ShareInfo {
+                        account: friend,
+                        username: "friend".into(),
+                        readonly: false,
+                    },
+                    ShareInfo {
+                        account: family,
+                        username: "family".into(),
+                        readonly: true,
+                    },
+                ],
+                rejected: vec![
+                    RejectedShare {
+                        account: unknown,
+                        reason: ShareRejection::UnknownAccount,
+                    },
+                    RejectedShare {
+                        account: owner_id,
+                        reason: ShareRejection::Owner,
+                    },
+                ],
+            }
+        );
+
+        let shares = owner.get_shares(&list).await.unwrap().shared_with;
+        assert_eq!(shares.len(), 2);
+        assert!(!shares[&friend]);
+        assert!(shares[&family]);
+    }
+
+    /// Reads the list behind a share link, without an account
+    async fn link_read(url: &str, token: Uuid) -> kabalist_client::Result<ReadListResponse> {
+        let rsp: RspData<ReadListResponse> =
+            reqwest::get(format!("{}/api/share/link/{}", url, token))
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+
+        Result::from(rsp).map_err(Into::into)
+    }
+
+    /// Adds an item through a share link, without an account
+    async fn link_add(url: &str, token: Uuid, name: &str) -> kabalist_client::Result<i32> {
+        let rsp: RspData<AddToListResponse> = reqwest::Client::new()
+            .post(format!("{}/api/share/link/{}", url, token))
+            .json(&AddToListRequest {
+                name: name.into(),
+                amount: None,
+                unit: None,
+                position: None,
+                url: None,
+                merge_duplicates: None,
+                category: None,
+            })
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        Result::from(rsp).map(|added| added.id).map_err(Into::into)
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn share_links(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (owner_id, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        owner.add(&list, "Eggs", None).await.unwrap();
+
+        let readonly = owner
+            .create_share_link(&list, true, None)
+            .await
+            .unwrap()
+            .token;
+        let read = link_read(&url, readonly).await.unwrap();
+        assert!(read.readonly);
+        assert_eq!(read.items.len(), 1);
+        assert_api_error(link_add(&url, readonly, "Milk").await, Error::NotWritable);
+
+        let writable = owner
+            .create_share_link(&list, false, None)
+            .await
+            .unwrap()
+            .token;
+        assert!(!link_read(&url, writable).await.unwrap().readonly);
+        let milk = link_add(&url, writable, "Oat\nmilk").await.unwrap();
+        assert_api_error(link_add(&url, writable, " ").await, Error::InvalidItemName);
+
+        let items = owner.read(&list).await.unwrap().items;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].id, milk);
+        assert_eq!(items[1].name, "Oat milk");
+        assert_eq!(items[1].added_by, owner_id);
+
+        assert_api_error(link_read(&url, Uuid::new_v4()).await, Error::NotFound);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn expired_and_revoked_links(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        let past = Utc::now() - chrono::Duration::hours(1);
+        let expired = owner
+            .create_share_link(&list, false, Some(past))
+            .await
+            .unwrap()
+            .token;
+        assert_api_error(link_read(&url, expired).await, Error::LinkExpired);
+        assert_api_error(link_add(&url, expired, "Milk").await, Error::LinkExpired);
+
+        let future = Utc::now() + chrono::Duration::hours(1);
+        let token = owner
+            .create_share_link(&list, false, Some(future))
+            .await
+            .unwrap()
+            .token;
+        link_add(&url, token, "Milk").await.unwrap();
+
+        owner.revoke_share_link(&list, &token).await.unwrap();
+        assert_api_error(link_read(&url, token).await, Error::NotFound);
+        assert_api_error(
+            owner.revoke_share_link(&list, &token).await,
+            Error::NotFound,
+        );
+        assert_eq!(owner.read(&list).await.unwrap().items.len(), 1);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn deleted_accounts_lose_their_shares(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (owner_id, owner) = client(&state, &url, "owner").await;
+        let (friend_id, friend) = client(&state, &url, "friend").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+
+        owner.share(&list, &friend_id, false).await.unwrap();
+        owner.add_contact(friend_id).await.unwrap();
+        friend.add_contact(owner_id).await.unwrap();
+
+        sqlx::query("DELETE FROM accounts WHERE id = $1")
+            .bind(friend_id)
+            .execute(&state.pool)
+            .await
+            .unwrap();
+
+        assert!(owner
+            .get_shares(&list)
+            .await
+            .unwrap()
+            .shared_with
+            .is_empty());
+        assert!(owner.contacts().await.unwrap().contacts.is_empty());
+        assert_eq!(owner.cleanup_shares(&list).await.unwrap().affected, 0);
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn cleanup_orphaned_shares(db: PgPool) {
+        let state = state(db).await;
+        let url = spawn(state.clone()).await;
+        let (friend_id, friend) = client(&state, &url, "friend").await;
+        let (_, owner) = client(&state, &url, "owner").await;
+        let list = owner.create_list("Groceries").await.unwrap().id;
+        owner.share(&list, &friend_id, true).await.unwrap();
+
+        // Shares made before the foreign key cascaded can point to missing accounts
+        sqlx::query("ALTER TABLE list_sharing DROP CONSTRAINT list_sharing_shared_fkey")
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO list_sharing (list, shared, readonly) VALUES ($1, $2, false)")
+            .bind(list)
+            .bind(Uuid::new_v4())
+            .execute(&state.pool)
+            .await
+            .unwrap();
+
+        assert_api_error(friend.cleanup_shares(&list).await, Error::NoSuchList);
+        assert_eq!(owner.cleanup_shares(&list).await.unwrap().affected, 1);
+
+        let shares = owner.get_shares(&list).await.unwrap().shared_with;
+        assert_eq!(shares.len(), 1);
+        assert!(shares[&friend_id]);
+    }
+}
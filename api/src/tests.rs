@@ -0,0 +1,347 @@
+//! Tests of the items shared by the whole API, and helpers for the tests of the modules. Those run
+//! against a fresh database created by `sqlx::test`, and talk to a server bound to a random local
+//! port through `kabalist_client`
+
+use std::sync::Arc;
+
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use kabalist_client::{Client, RspData};
+use kabalist_types::{HistorySuggestion, ItemUpdate};
+use serde::de::IgnoredAny;
+use sqlx::PgPool;
+use utoipa::OpenApi;
+use uuid::Uuid;
+
+use crate::{api_router, ApiDoc, Error, KabalistState};
+
+/// Password of every account created by [`account`]
+pub(crate) const PASSWORD: &str = "password";
+
+/// Prepares the database like at startup, with the default configuration
+pub(crate) async fn state(db: PgPool) -> Arc<KabalistState> {
+    crate::setup_database(&db).await.unwrap();
+
+    Arc::new(KabalistState {
+        pool: db,
+        config: Default::default(),
+        pantry_alerts: Default::default(),
+    })
+}
+
+/// Serves the API in the background, returning the base URL to give to the client
+pub(crate) async fn spawn(state: Arc<KabalistState>) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let app = axum::Router::new().nest("/api", api_router(state));
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    url
+}
+
+/// Creates an account with [`PASSWORD`], returning its ID
+pub(crate) async fn account(state: &KabalistState, username: &str) -> Uuid {
+    sqlx::query_scalar(
+        "INSERT INTO accounts (id, name, password)
+             VALUES (uuid_generate_v4(), $1::text::citext, crypt($2, gen_salt('bf')))
+             RETURNING id",
+    )
+    .bind(username)
+    .bind(PASSWORD)
+    .fetch_one(&state.pool)
+    .await
+    .unwrap()
+}
+
+/// Creates an account and logs in with it
+pub(crate) async fn client(state: &KabalistState, url: &str, username: &str) -> (Uuid, Client) {
+    let id = account(state, username).await;
+    let token = kabalist_client::login(url, username, PASSWORD)
+        .await
+        .unwrap()
+        .token;
+
+    (id, Client::new(url.into(), token))
+}
+
+/// Asserts that the API answered with `expected`
+#[track_caller]
+pub(crate) fn assert_api_error<T: std::fmt::Debug>(
+    result: kabalist_client::Result<T>,
+    expected: Error,
+) {
+    match result {
+        Err(kabalist_client::Error::Api(e)) => assert_eq!(e.code, expected as usize, "{:?}", e),
+        other => panic!("expected error {}, got {:?}", expected as usize, other),
+    }
+}
+
+/// Sends a GET request below `/api` without the client, which only builds valid paths
+pub(crate) async fn get_raw(
+    url: &str,
+    token: &str,
+    path: &str,
+) -> (StatusCode, kabalist_client::Result<IgnoredAny>) {
+    let response = reqwest::Client::new()
+        .get(format!("{}/api{}", url, path))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap();
+    let status = response.status();
+    let body: RspData<IgnoredAny> = response.json().await.unwrap();
+
+    (status, Result::from(body).map_err(Into::into))
+}
+
+/// Raises an error with the given SQLSTATE from the database
+async fn raise(db: &PgPool, sqlstate: &str) -> sqlx::Error {
+    sqlx::query(&format!(
+        "DO $$ BEGIN RAISE EXCEPTION 'raised' USING ERRCODE = '{}'; END $$",
+        sqlstate
+    ))
+    .execute(db)
+    .await
+    .unwrap_err()
+}
+
+#[track_caller]
+fn assert_unavailable(e: sqlx::Error) {
+    let error = Error::from(e);
+    assert_eq!(error as u16, Error::Unavailable as u16);
+    assert!(error.retryable());
+
+    let response = error.into_response();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[sqlx::test(migrations = false)]
+async fn transient_database_errors(db: PgPool) {
+    assert_unavailable(sqlx::Error::PoolTimedOut);
+    assert_unavailable(sqlx::Error::Io(std::io::ErrorKind::ConnectionReset.into()));
+    // serialization_failure and deadlock_detected
+    assert_unavailable(raise(&db, "40001").await);
+    assert_unavailable(raise(&db, "40P01").await);
+
+    let error = Error::from(raise(&db, "23505").await);
+    assert_eq!(error as u16, Error::Internal as u16);
+    assert!(!error.retryable());
+    let response = error.into_response();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(!response.headers().contains_key(header::RETRY_AFTER));
+
+    let query = sqlx::query("SELECT * FROM no_such_table")
+        .execute(&db)
+        .await
+        .unwrap_err();
+    assert_eq!(Error::from(query) as u16, Error::Internal as u16);
+}
+
+#[sqlx::test(migrations = false)]
+async fn retryable_errors(db: PgPool) {
+    let state = state(db).await;
+    let url = spawn(state.clone()).await;
+    let (_, owner) = client(&state, &url, "owner").await;
+
+    let body = axum::body::to_bytes(Error::Unavailable.into_response().into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: RspData<IgnoredAny> = serde_json::from_slice(&body).unwrap();
+    match body {
+        RspData::Err(err) => assert!(err.retryable),
+        RspData::Ok(_) => panic!("expected an error"),
+    }
+
+    owner.create_list("Groceries").await.unwrap();
+    match owner.create_list("Groceries").await {
+        Err(kabalist_client::Error::Api(e)) => {
+            assert_eq!(e.code, Error::ListAlreadyExists as usize);
+            assert!(!e.retryable);
+        }
+        other => panic!("expected an error, got {:?}", other),
+    }
+}
+
+#[sqlx::test(migrations = false)]
+async fn meta(db: PgPool) {
+    let state = state(db).await;
+    let url = spawn(state.clone()).await;
+
+    // No account is needed
+    let before = chrono::Utc::now();
+    let meta = Client::new(url.clone(), String::new())
+        .meta()
+        .await
+        .unwrap();
+    assert!(meta.server_time >= before - chrono::Duration::seconds(1));
+    assert!(meta.server_time <= chrono::Utc::now() + chrono::Duration::seconds(1));
+    assert_eq!(meta.version, env!("CARGO_PKG_VERSION"));
+}
+
+#[sqlx::test(migrations = false)]
+async fn invalid_paths(db: PgPool) {
+    let state = state(db).await;
+    let url = spawn(state.clone()).await;
+    account(&state, "owner").await;
+    let token = kabalist_client::login(&url, "owner", PASSWORD)
+        .await
+        .unwrap()
+        .token;
+
+    for path in ["/list/not-a-uuid", "/pantry/not-a-uuid", "/account/42/name"] {
+        let (status, body) = get_raw(&url, &token, path).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_api_error(body, Error::InvalidPath);
+    }
+}
+
+#[sqlx::test(migrations = false)]
+async fn unknown_routes(db: PgPool) {
+    let state = state(db).await;
+    let url = spawn(state.clone()).await;
+
+    for path in [
+        "/no/such/route",
+        "/list/00000000-0000-0000-0000-000000000000/a/b",
+    ] {
+        let (status, body) = get_raw(&url, "not-a-token", path).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_api_error(body, Error::NotFound);
+    }
+}
+
+#[sqlx::test(migrations = false)]
+async fn client_round_trip(db: PgPool) {
+    let state = state(db).await;
+    let url = spawn(state.clone()).await;
+    account(&state, "owner").await;
+
+    assert_api_error(
+        kabalist_client::login(&url, "owner", "wrong").await,
+        Error::UnknownAccount,
+    );
+
+    let owner = Client::builder(url.clone())
+        .login("owner", PASSWORD)
+        .await
+        .unwrap();
+    let list = owner.create_list("Groceries").await.unwrap().id;
+    let eggs = owner.add(&list, "Eggs", Some("6")).await.unwrap().id;
+
+    let lists = owner.lists().await.unwrap().results;
+    assert_eq!(lists.len(), 1);
+    assert_eq!(lists[&list].name, "Groceries");
+
+    let content = owner.read(&list).await.unwrap();
+    assert_eq!(content.items.len(), 1);
+    assert_eq!(content.items[0].id, eggs);
+    assert_eq!(content.items[0].amount.as_deref(), Some("6"));
+
+    // A client built from the token of another one acts as the same account
+    let token = kabalist_client::login(&url, "owner", PASSWORD)
+        .await
+        .unwrap()
+        .token;
+    let same = Client::builder(url.clone()).token(token).build().unwrap();
+    assert_eq!(same.read(&list).await.unwrap(), content);
+    assert!(Client::builder(url).build().is_none());
+
+    assert_api_error(owner.read(&Uuid::new_v4()).await, Error::NoSuchList);
+}
+
+#[sqlx::test(migrations = false)]
+async fn large_bodies(db: PgPool) {
+    let state = state(db).await;
+    let url = spawn(state.clone()).await;
+    let (_, owner) = client(&state, &url, "owner").await;
+    let list = owner.create_list("Groceries").await.unwrap().id;
+
+    let name = "a".repeat(crate::BODY_LIMIT);
+    assert_api_error(owner.add(&list, &name, None).await, Error::BodyTooLarge);
+    assert!(owner.read(&list).await.unwrap().items.is_empty());
+
+    // The bulk routes accept larger bodies
+    let updates: Vec<_> = (0..crate::BODY_LIMIT / 32)
+        .map(|_| ItemUpdate {
+            id: -1,
+            name: None,
+            amount: None,
+            checked: Some(true),
+            position: None,
+        })
+        .collect();
+    assert_eq!(owner.bulk_update(&list, updates).await.unwrap().affected, 0);
+}
+
+#[sqlx::test(migrations = false)]
+async fn history_suggests_last_amount(db: PgPool) {
+    let state = state(db).await;
+    let url = spawn(state.clone()).await;
+    let (_, owner) = client(&state, &url, "owner").await;
+    let list = owner.create_list("Groceries").await.unwrap().id;
+
+    owner.add(&list, "Milk", Some("1L")).await.unwrap();
+    owner.add(&list, "Milk", Some("2L")).await.unwrap();
+    owner.add(&list, "Oat milk", None).await.unwrap();
+    owner.add(&list, "Eggs", Some("6")).await.unwrap();
+
+    let history = owner.search_history(&list, "milk").await.unwrap();
+    assert_eq!(history.matches, ["Oat milk", "Milk"]);
+    assert_eq!(
+        history.suggestions,
+        [
+            HistorySuggestion {
+                name: "Oat milk".into(),
+                amount: None,
+            },
+            HistorySuggestion {
+                name: "Milk".into(),
+                amount: Some("2L".into()),
+            },
+        ]
+    );
+}
+
+#[test]
+fn openapi_documents_errors() {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+    let schemas = &spec["components"]["schemas"];
+    for schema in ["ErrResponse", "UserError", "RspErr", "Error"] {
+        assert!(
+            schemas.get(schema).is_some(),
+            "{} is not documented",
+            schema
+        );
+    }
+    assert!(schemas["RspErr"]["properties"].get("code").is_some());
+
+    // Every JSON error response of every route uses the error envelope
+    let mut errors = 0;
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in ["get", "put", "post", "delete", "patch"] {
+            let operation = match item.get(method) {
+                Some(operation) => operation,
+                None => continue,
+            };
+            for (status, response) in operation["responses"].as_object().unwrap() {
+                if status.starts_with('2') || status.starts_with('3') {
+                    continue;
+                }
+                if let Some(json) = response["content"].get("application/json") {
+                    assert_eq!(
+                        json["schema"]["$ref"], "#/components/schemas/ErrResponse",
+                        "{} {} {}",
+                        method, path, status
+                    );
+                    errors += 1;
+                }
+            }
+        }
+    }
+    assert!(errors > 0);
+}
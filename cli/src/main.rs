@@ -74,8 +74,11 @@ impl ListCommands {
                 let results = client
                     .search_history(&list, search.as_ref().map(|s| -> &str { s }).unwrap_or(""))
                     .await?;
-                for result in results.matches {
-                    println!(" - {}", result);
+                for result in results.suggestions {
+                    match result.amount {
+                        Some(amount) => println!(" - {} ({})", result.name, amount),
+                        None => println!(" - {}", result.name),
+                    }
                 }
             }
             Some(ListAction::Pantry { action }) => {
@@ -242,13 +245,15 @@ impl PublicAction {
 enum PantryAction {
     Add {
         name: String,
-        target: i32,
+        target: f64,
     },
     Edit {
         #[clap(short, long)]
-        target: Option<i32>,
+        target: Option<f64>,
+        #[clap(short, long)]
+        amount: Option<f64>,
         #[clap(short, long)]
-        amount: Option<i32>,
+        min: Option<f64>,
         item: i32,
     },
     Delete {
@@ -265,7 +270,7 @@ impl PantryAction {
                 println!("Pantry:");
                 for item in rsp {
                     print!("  - {}", Paint::new(&item.name).underline());
-                    println!(" ({}/{})", item.amount, item.target);
+                    println!(" ({})", item.display_amount);
                 }
             }
             Some(PantryAction::Refill) => {
@@ -280,9 +285,12 @@ impl PantryAction {
             Some(PantryAction::Edit {
                 target,
                 amount,
+                min,
                 item,
             }) => {
-                client.edit_pantry_item(list, item, amount, target).await?;
+                client
+                    .edit_pantry_item(list, item, amount, target, min)
+                    .await?;
             }
         }
 
@@ -1,4 +1,8 @@
-pub use kabalist_types::{uuid::Uuid, *};
+pub use kabalist_types::{
+    chrono::{DateTime, Utc},
+    uuid::Uuid,
+    *,
+};
 use serde::Serialize;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -16,6 +20,19 @@ fn map_res<T>(res: RspData<T>) -> Result<T> {
     res.map_err(Into::into)
 }
 
+/// Percent-encodes `segment` so that it stays a single segment of a URL path
+fn path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
 pub async fn login(url: &str, username: &str, password: &str) -> Result<LoginResponse> {
     #[derive(Serialize)]
     struct LoginRequest<'a> {
@@ -35,6 +52,44 @@ pub async fn login(url: &str, username: &str, password: &str) -> Result<LoginRes
     map_res(token)
 }
 
+/// Same as [`login`], also returning the profile and lists of the account
+pub async fn login_full(url: &str, username: &str, password: &str) -> Result<LoginResponse> {
+    #[derive(Serialize)]
+    struct LoginRequest<'a> {
+        username: &'a str,
+        password: &'a str,
+        full: bool,
+    }
+
+    let client = reqwest::Client::new();
+    let rsp: RspData<LoginResponse> = client
+        .post(format!("{}/api/account/login", url))
+        .json(&LoginRequest {
+            username,
+            password,
+            full: true,
+        })
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    map_res(rsp)
+}
+
+pub async fn validate_token(url: &str, token: &str) -> Result<ValidateTokenResponse> {
+    let client = reqwest::Client::new();
+    let rsp: RspData<ValidateTokenResponse> = client
+        .post(format!("{}/api/account/validate", url))
+        .bearer_auth(token)
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    map_res(rsp)
+}
+
 pub async fn register(
     url: &str,
     token: Uuid,
@@ -95,6 +150,46 @@ pub async fn recover_password(
     map_res(rsp)
 }
 
+/// Builds a [`Client`], either from an existing token or by logging in
+pub struct ClientBuilder {
+    url: String,
+    token: Option<String>,
+    client: Option<reqwest::Client>,
+}
+
+impl ClientBuilder {
+    pub fn token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Use a preconfigured HTTP client, for example to set timeouts or proxies
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the client, returns `None` if no token was set
+    pub fn build(self) -> Option<Client> {
+        Some(Client {
+            client: self.client.unwrap_or_default(),
+            token: self.token?,
+            url: self.url + "/api",
+        })
+    }
+
+    /// Log in to get a token, and build the client using it
+    pub async fn login(self, username: &str, password: &str) -> Result<Client> {
+        let token = login(&self.url, username, password).await?.token;
+
+        Ok(Client {
+            client: self.client.unwrap_or_default(),
+            token,
+            url: self.url + "/api",
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct Client {
     client: reqwest::Client,
@@ -111,6 +206,14 @@ impl Client {
         }
     }
 
+    pub fn builder(url: String) -> ClientBuilder {
+        ClientBuilder {
+            url,
+            token: None,
+            client: None,
+        }
+    }
+
     pub async fn lists(&self) -> Result<GetListsResponse> {
         let lists: RspData<GetListsResponse> = self
             .client
@@ -124,10 +227,11 @@ impl Client {
         map_res(lists)
     }
 
-    pub async fn search(&self, name: &str) -> Result<GetListsResponse> {
+    pub async fn lists_by_status(&self, status: ListStatus) -> Result<GetListsResponse> {
         let lists: RspData<GetListsResponse> = self
             .client
-            .get(&format!("{}/search/list/{}", self.url, name))
+            .get(&format!("{}/list", self.url))
+            .query(&[("status", status)])
             .bearer_auth(&self.token)
             .send()
             .await?
@@ -137,90 +241,72 @@ impl Client {
         map_res(lists)
     }
 
-    pub async fn read(&self, id: &Uuid) -> Result<ReadListResponse> {
-        let rsp: RspData<ReadListResponse> = self
+    pub async fn lists_with_archived(&self) -> Result<GetListsResponse> {
+        let lists: RspData<GetListsResponse> = self
             .client
-            .get(&format!("{}/list/{}", self.url, id))
+            .get(&format!("{}/list", self.url))
+            .query(&[("archived", true)])
             .bearer_auth(&self.token)
             .send()
             .await?
             .json()
             .await?;
 
-        map_res(rsp)
+        map_res(lists)
     }
 
-    pub async fn delete_list(&self, id: &Uuid) -> Result<DeleteListResponse> {
-        let rsp: RspData<DeleteListResponse> = self
+    pub async fn lists_sorted(&self, sort: ListSort) -> Result<GetListsResponse> {
+        let lists: RspData<GetListsResponse> = self
             .client
-            .delete(&format!("{}/list/{}", self.url, id))
+            .get(&format!("{}/list", self.url))
+            .query(&[("sort", sort)])
             .bearer_auth(&self.token)
             .send()
             .await?
             .json()
             .await?;
 
-        map_res(rsp)
+        map_res(lists)
     }
 
-    pub async fn add(
-        &self,
-        list: &Uuid,
-        name: &str,
-        amount: Option<&str>,
-    ) -> Result<AddToListResponse> {
-        #[derive(Serialize)]
-        struct Request<'a> {
-            name: &'a str,
-            amount: Option<&'a str>,
-        }
-
-        let rsp: RspData<AddToListResponse> = self
+    pub async fn lists_modified_since(&self, since: DateTime<Utc>) -> Result<GetListsResponse> {
+        let lists: RspData<GetListsResponse> = self
             .client
-            .post(&format!("{}/list/{}", self.url, list))
+            .get(&format!("{}/list", self.url))
+            .query(&[("modified_since", since.to_rfc3339())])
             .bearer_auth(&self.token)
-            .json(&Request { name, amount })
             .send()
             .await?
             .json()
             .await?;
 
-        map_res(rsp)
+        map_res(lists)
     }
 
-    pub async fn search_account(&self, name: &str) -> Result<SearchAccountResponse> {
-        let rsp: RspData<SearchAccountResponse> = self
+    pub async fn lists_with_tag(&self, tag: &str) -> Result<GetListsResponse> {
+        let lists: RspData<GetListsResponse> = self
             .client
-            .get(&format!("{}/search/account/{}", self.url, name))
+            .get(&format!("{}/list", self.url))
+            .query(&[("tag", tag)])
             .bearer_auth(&self.token)
             .send()
             .await?
             .json()
             .await?;
 
-        map_res(rsp)
+        map_res(lists)
     }
 
-    pub async fn share(
-        &self,
-        list: &Uuid,
-        share_with: &Uuid,
-        readonly: bool,
-    ) -> Result<ShareListResponse> {
-        #[derive(Serialize)]
-        struct Request<'a> {
-            share_with: &'a Uuid,
-            readonly: bool,
-        }
-
-        let rsp: RspData<ShareListResponse> = self
+    pub async fn add_tag(&self, list: &Uuid, tag: &str) -> Result<ListTagsResponse> {
+        let rsp: RspData<ListTagsResponse> = self
             .client
-            .put(&format!("{}/share/{}", self.url, list))
+            .put(&format!(
+                "{}/list/{}/tags/{}",
+                self.url,
+                list,
+                path_segment(tag)
+            ))
             .bearer_auth(&self.token)
-            .json(&Request {
-                share_with,
-                readonly,
-            })
             .send()
             .await?
             .json()
@@ -229,10 +315,15 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn delete_share(&self, list: &Uuid) -> Result<DeleteShareResponse> {
-        let rsp: RspData<DeleteShareResponse> = self
+    pub async fn remove_tag(&self, list: &Uuid, tag: &str) -> Result<ListTagsResponse> {
+        let rsp: RspData<ListTagsResponse> = self
             .client
-            .delete(&format!("{}/share/{}", self.url, list))
+            .delete(&format!(
+                "{}/list/{}/tags/{}",
+                self.url,
+                list,
+                path_segment(tag)
+            ))
             .bearer_auth(&self.token)
             .send()
             .await?
@@ -242,17 +333,24 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn create_list(&self, list_name: &str) -> Result<CreateListResponse> {
-        #[derive(Serialize)]
-        struct Request<'a> {
-            name: &'a str,
-        }
+    pub async fn search(&self, name: &str) -> Result<GetListsResponse> {
+        let lists: RspData<GetListsResponse> = self
+            .client
+            .get(&format!("{}/search/list/{}", self.url, name))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
 
-        let rsp: RspData<CreateListResponse> = self
+        map_res(lists)
+    }
+
+    pub async fn read(&self, id: &Uuid) -> Result<ReadListResponse> {
+        let rsp: RspData<ReadListResponse> = self
             .client
-            .post(&format!("{}/list", self.url))
+            .get(&format!("{}/list/{}", self.url, id))
             .bearer_auth(&self.token)
-            .json(&Request { name: list_name })
             .send()
             .await?
             .json()
@@ -261,10 +359,12 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn delete_item(&self, list: &Uuid, item: i32) -> Result<DeleteItemResponse> {
-        let rsp: RspData<DeleteItemResponse> = self
+    /// Reads a list with only the main fields of the items, for slow connections
+    pub async fn read_minimal(&self, id: &Uuid) -> Result<MinimalReadListResponse> {
+        let rsp: RspData<MinimalReadListResponse> = self
             .client
-            .delete(&format!("{}/list/{}/{}", self.url, list, item))
+            .get(&format!("{}/list/{}", self.url, id))
+            .query(&[("fields", ItemFields::Minimal)])
             .bearer_auth(&self.token)
             .send()
             .await?
@@ -274,24 +374,18 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn update_item(
+    /// Reads the items of a list that were added between `from` and `to`
+    pub async fn read_added_between(
         &self,
-        list: &Uuid,
-        item: i32,
-        name: Option<&str>,
-        amount: Option<&str>,
-    ) -> Result<UpdateItemResponse> {
-        #[derive(Serialize)]
-        struct Request<'a> {
-            name: Option<&'a str>,
-            amount: Option<&'a str>,
-        }
-
-        let rsp: RspData<UpdateItemResponse> = self
+        id: &Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<ReadListResponse> {
+        let rsp: RspData<ReadListResponse> = self
             .client
-            .patch(&format!("{}/list/{}/{}", self.url, list, item))
+            .get(&format!("{}/list/{}", self.url, id))
+            .query(&[("from", from.to_rfc3339()), ("to", to.to_rfc3339())])
             .bearer_auth(&self.token)
-            .json(&Request { name, amount })
             .send()
             .await?
             .json()
@@ -300,10 +394,11 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn get_shares(&self, list: &Uuid) -> Result<GetSharesResponse> {
-        let rsp: RspData<GetSharesResponse> = self
+    pub async fn read_grouped(&self, id: &Uuid) -> Result<ReadListResponse> {
+        let rsp: RspData<ReadListResponse> = self
             .client
-            .get(&format!("{}/share/{}", self.url, list))
+            .get(&format!("{}/list/{}", self.url, id))
+            .query(&[("group", true)])
             .bearer_auth(&self.token)
             .send()
             .await?
@@ -313,10 +408,11 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn unshare_with(&self, list: &Uuid, account: &Uuid) -> Result<UnshareResponse> {
-        let rsp: RspData<UnshareResponse> = self
+    pub async fn read_sorted(&self, id: &Uuid, sort: ItemSort) -> Result<ReadListResponse> {
+        let rsp: RspData<ReadListResponse> = self
             .client
-            .delete(&format!("{}/share/{}/{}", self.url, list, account))
+            .get(&format!("{}/list/{}", self.url, id))
+            .query(&[("sort", sort)])
             .bearer_auth(&self.token)
             .send()
             .await?
@@ -326,10 +422,15 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn account_name(&self, account: &Uuid) -> Result<GetAccountNameResponse> {
-        let rsp: RspData<GetAccountNameResponse> = self
+    pub async fn read_with_status(
+        &self,
+        id: &Uuid,
+        status: ItemStatus,
+    ) -> Result<ReadListResponse> {
+        let rsp: RspData<ReadListResponse> = self
             .client
-            .get(&format!("{}/account/{}/name", self.url, account))
+            .get(&format!("{}/list/{}", self.url, id))
+            .query(&[("status", status)])
             .bearer_auth(&self.token)
             .send()
             .await?
@@ -339,10 +440,12 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn set_public(&self, list: &Uuid) -> Result<SetPublicResponse> {
-        let rsp: RspData<SetPublicResponse> = self
+    /// Reads several lists at once, lists that can't be accessed are left out
+    pub async fn read_lists(&self, ids: Vec<Uuid>) -> Result<MultiReadResponse> {
+        let rsp: RspData<MultiReadResponse> = self
             .client
-            .put(&format!("{}/list/{}/public", self.url, list))
+            .post(&format!("{}/lists/read", self.url))
+            .json(&MultiReadRequest { ids, group: false })
             .bearer_auth(&self.token)
             .send()
             .await?
@@ -352,10 +455,10 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn remove_public(&self, list: &Uuid) -> Result<RemovePublicResponse> {
-        let rsp: RspData<RemovePublicResponse> = self
+    pub async fn list_info(&self, id: &Uuid) -> Result<ListDetail> {
+        let rsp: RspData<ListDetail> = self
             .client
-            .delete(&format!("{}/list/{}/public", self.url, list))
+            .get(&format!("{}/list/{}/info", self.url, id))
             .bearer_auth(&self.token)
             .send()
             .await?
@@ -365,11 +468,10 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn search_history(&self, list: &Uuid, search: &str) -> Result<GetHistoryResponse> {
-        let rsp: RspData<GetHistoryResponse> = self
+    pub async fn delete_list(&self, id: &Uuid) -> Result<DeleteListResponse> {
+        let rsp: RspData<DeleteListResponse> = self
             .client
-            .get(&format!("{}/history/{}", self.url, list))
-            .query(&[("search", search)])
+            .delete(&format!("{}/list/{}", self.url, id))
             .bearer_auth(&self.token)
             .send()
             .await?
@@ -379,11 +481,23 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn pantry(&self, list: Uuid) -> Result<GetPantryResponse> {
-        let rsp: RspData<GetPantryResponse> = self
+    pub async fn add(
+        &self,
+        list: &Uuid,
+        name: &str,
+        amount: Option<&str>,
+    ) -> Result<AddToListResponse> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            name: &'a str,
+            amount: Option<&'a str>,
+        }
+
+        let rsp: RspData<AddToListResponse> = self
             .client
-            .get(&format!("{}/pantry/{}", self.url, list))
+            .post(&format!("{}/list/{}", self.url, list))
             .bearer_auth(&self.token)
+            .json(&Request { name, amount })
             .send()
             .await?
             .json()
@@ -392,11 +506,25 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn refill_pantry(&self, list: Uuid) -> Result<RefillPantryResponse> {
-        let rsp: RspData<RefillPantryResponse> = self
+    pub async fn add_with_unit(
+        &self,
+        list: &Uuid,
+        name: &str,
+        amount: Option<&str>,
+        unit: &str,
+    ) -> Result<AddToListResponse> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            name: &'a str,
+            amount: Option<&'a str>,
+            unit: &'a str,
+        }
+
+        let rsp: RspData<AddToListResponse> = self
             .client
-            .post(&format!("{}/pantry/{}/refill", self.url, list))
+            .post(&format!("{}/list/{}", self.url, list))
             .bearer_auth(&self.token)
+            .json(&Request { name, amount, unit })
             .send()
             .await?
             .json()
@@ -405,17 +533,23 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn add_to_pantry(
+    pub async fn add_with_category(
         &self,
-        list: Uuid,
-        name: String,
-        target: i32,
-    ) -> Result<AddToPantryResponse> {
-        let rsp: RspData<AddToPantryResponse> = self
+        list: &Uuid,
+        name: &str,
+        category: &str,
+    ) -> Result<AddToListResponse> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            name: &'a str,
+            category: &'a str,
+        }
+
+        let rsp: RspData<AddToListResponse> = self
             .client
-            .post(&format!("{}/pantry/{}", self.url, list))
+            .post(&format!("{}/list/{}", self.url, list))
             .bearer_auth(&self.token)
-            .json(&AddToPantryRequest { name, target })
+            .json(&Request { name, category })
             .send()
             .await?
             .json()
@@ -424,18 +558,1069 @@ impl Client {
         map_res(rsp)
     }
 
-    pub async fn edit_pantry_item(
+    pub async fn add_or_merge(
         &self,
-        list: Uuid,
-        item: i32,
-        amount: Option<i32>,
-        target: Option<i32>,
-    ) -> Result<EditPantryItemResponse> {
-        let rsp: RspData<EditPantryItemResponse> = self
+        list: &Uuid,
+        name: &str,
+        amount: Option<&str>,
+    ) -> Result<AddToListResponse> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            name: &'a str,
+            amount: Option<&'a str>,
+            merge_duplicates: bool,
+        }
+
+        let rsp: RspData<AddToListResponse> = self
             .client
-            .patch(&format!("{}/pantry/{}/{}", self.url, list, item))
+            .post(&format!("{}/list/{}", self.url, list))
+            .bearer_auth(&self.token)
+            .json(&Request {
+                name,
+                amount,
+                merge_duplicates: true,
+            })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn add_warn_duplicate(
+        &self,
+        list: &Uuid,
+        name: &str,
+        amount: Option<&str>,
+    ) -> Result<AddToListResponse> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            name: &'a str,
+            amount: Option<&'a str>,
+        }
+
+        let rsp: RspData<AddToListResponse> = self
+            .client
+            .post(&format!("{}/list/{}", self.url, list))
+            .query(&[("warn_duplicate", true)])
+            .bearer_auth(&self.token)
+            .json(&Request { name, amount })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn add_at(
+        &self,
+        list: &Uuid,
+        name: &str,
+        amount: Option<&str>,
+        position: i32,
+    ) -> Result<AddToListResponse> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            name: &'a str,
+            amount: Option<&'a str>,
+            position: i32,
+        }
+
+        let rsp: RspData<AddToListResponse> = self
+            .client
+            .post(&format!("{}/list/{}", self.url, list))
+            .bearer_auth(&self.token)
+            .json(&Request {
+                name,
+                amount,
+                position,
+            })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn search_account(&self, name: &str) -> Result<SearchAccountResponse> {
+        let rsp: RspData<SearchAccountResponse> = self
+            .client
+            .get(&format!("{}/search/account/{}", self.url, name))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn share(
+        &self,
+        list: &Uuid,
+        share_with: &Uuid,
+        readonly: bool,
+    ) -> Result<ShareListResponse> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            share_with: &'a Uuid,
+            readonly: bool,
+        }
+
+        let rsp: RspData<ShareListResponse> = self
+            .client
+            .put(&format!("{}/share/{}", self.url, list))
+            .bearer_auth(&self.token)
+            .json(&Request {
+                share_with,
+                readonly,
+            })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn share_many(
+        &self,
+        list: &Uuid,
+        shares: Vec<ShareListRequest>,
+    ) -> Result<ShareManyResponse> {
+        let rsp: RspData<ShareManyResponse> = self
+            .client
+            .post(&format!("{}/list/{}/share-many", self.url, list))
+            .bearer_auth(&self.token)
+            .json(&ShareManyRequest { shares })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn contacts(&self) -> Result<GetContactsResponse> {
+        let rsp: RspData<GetContactsResponse> = self
+            .client
+            .get(&format!("{}/contacts", self.url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn add_contact(&self, account: Uuid) -> Result<AddContactResponse> {
+        let rsp: RspData<AddContactResponse> = self
+            .client
+            .post(&format!("{}/contacts", self.url))
+            .bearer_auth(&self.token)
+            .json(&AddContactRequest { account })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn delete_contact(&self, account: &Uuid) -> Result<DeleteContactResponse> {
+        let rsp: RspData<DeleteContactResponse> = self
+            .client
+            .delete(&format!("{}/contacts/{}", self.url, account))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    /// Creates a link giving access to the list without an account
+    pub async fn create_share_link(
+        &self,
+        list: &Uuid,
+        readonly: bool,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<CreateShareLinkResponse> {
+        let rsp: RspData<CreateShareLinkResponse> = self
+            .client
+            .post(&format!("{}/list/{}/links", self.url, list))
+            .bearer_auth(&self.token)
+            .json(&CreateShareLinkRequest {
+                readonly,
+                expires_at,
+            })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn revoke_share_link(
+        &self,
+        list: &Uuid,
+        token: &Uuid,
+    ) -> Result<RevokeShareLinkResponse> {
+        let rsp: RspData<RevokeShareLinkResponse> = self
+            .client
+            .delete(&format!("{}/list/{}/links/{}", self.url, list, token))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    /// Creates a token that can only read the list
+    pub async fn create_guest_token(&self, list: &Uuid) -> Result<CreateGuestTokenResponse> {
+        let rsp: RspData<CreateGuestTokenResponse> = self
+            .client
+            .post(&format!("{}/list/{}/guest-token", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn revoke_guest_token(
+        &self,
+        list: &Uuid,
+        token: &Uuid,
+    ) -> Result<RevokeGuestTokenResponse> {
+        let rsp: RspData<RevokeGuestTokenResponse> = self
+            .client
+            .delete(&format!("{}/list/{}/guest-token/{}", self.url, list, token))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn delete_share(&self, list: &Uuid) -> Result<DeleteShareResponse> {
+        let rsp: RspData<DeleteShareResponse> = self
+            .client
+            .delete(&format!("{}/share/{}", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn create_list(&self, list_name: &str) -> Result<CreateListResponse> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            name: &'a str,
+        }
+
+        let rsp: RspData<CreateListResponse> = self
+            .client
+            .post(&format!("{}/list", self.url))
+            .bearer_auth(&self.token)
+            .json(&Request { name: list_name })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn update_list(
+        &self,
+        list: &Uuid,
+        update: &UpdateListRequest,
+    ) -> Result<UpdateListResponse> {
+        let rsp: RspData<UpdateListResponse> = self
+            .client
+            .patch(&format!("{}/list/{}", self.url, list))
+            .bearer_auth(&self.token)
+            .json(update)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn delete_item(&self, list: &Uuid, item: i32) -> Result<DeleteItemResponse> {
+        let rsp: RspData<DeleteItemResponse> = self
+            .client
+            .delete(&format!("{}/list/{}/{}", self.url, list, item))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn item_to_pantry(
+        &self,
+        list: &Uuid,
+        item: i32,
+        target: f64,
+        remove: bool,
+    ) -> Result<ToPantryResponse> {
+        let rsp: RspData<ToPantryResponse> = self
+            .client
+            .post(&format!("{}/list/{}/{}/to-pantry", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&ToPantryRequest { target, remove })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn update_item(
+        &self,
+        list: &Uuid,
+        item: i32,
+        name: Option<&str>,
+        amount: Option<&str>,
+    ) -> Result<UpdateItemResponse> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            name: Option<&'a str>,
+            amount: Option<&'a str>,
+        }
+
+        let rsp: RspData<UpdateItemResponse> = self
+            .client
+            .patch(&format!("{}/list/{}/{}", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&Request { name, amount })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn set_checked(
+        &self,
+        list: &Uuid,
+        item: i32,
+        checked: bool,
+    ) -> Result<UpdateItemResponse> {
+        #[derive(Serialize)]
+        struct Request {
+            checked: bool,
+        }
+
+        let rsp: RspData<UpdateItemResponse> = self
+            .client
+            .patch(&format!("{}/list/{}/{}", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&Request { checked })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    /// Sets the unit of an item, an empty unit removes it
+    pub async fn set_unit(&self, list: &Uuid, item: i32, unit: &str) -> Result<UpdateItemResponse> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            unit: &'a str,
+        }
+
+        let rsp: RspData<UpdateItemResponse> = self
+            .client
+            .patch(&format!("{}/list/{}/{}", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&Request { unit })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn set_status(
+        &self,
+        list: &Uuid,
+        item: i32,
+        status: ItemStatus,
+    ) -> Result<UpdateItemResponse> {
+        #[derive(Serialize)]
+        struct Request {
+            status: ItemStatus,
+        }
+
+        let rsp: RspData<UpdateItemResponse> = self
+            .client
+            .patch(&format!("{}/list/{}/{}", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&Request { status })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn receipt(
+        &self,
+        list: &Uuid,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<ReceiptResponse> {
+        let mut request = self
+            .client
+            .get(&format!("{}/list/{}/receipt", self.url, list))
+            .bearer_auth(&self.token);
+        if let Some(since) = since {
+            request = request.query(&[("since", since.to_rfc3339())]);
+        }
+
+        let rsp: RspData<ReceiptResponse> = request.send().await?.json().await?;
+
+        map_res(rsp)
+    }
+
+    /// Items of the list whose name contains `query`
+    pub async fn search_items(&self, list: &Uuid, query: &str) -> Result<SearchItemsResponse> {
+        let rsp: RspData<SearchItemsResponse> = self
+            .client
+            .get(&format!("{}/list/{}/search", self.url, list))
+            .query(&[("q", query)])
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    /// Items changed since the `next_since` of a previous call, or all of them if `since` is `None`
+    pub async fn list_changes(
+        &self,
+        list: &Uuid,
+        since: Option<i64>,
+    ) -> Result<ListChangesResponse> {
+        let mut request = self
+            .client
+            .get(&format!("{}/list/{}/changes", self.url, list))
+            .bearer_auth(&self.token);
+        if let Some(since) = since {
+            request = request.query(&[("since", since)]);
+        }
+
+        let rsp: RspData<ListChangesResponse> = request.send().await?.json().await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn bulk_update(
+        &self,
+        list: &Uuid,
+        updates: Vec<ItemUpdate>,
+    ) -> Result<AffectedResponse> {
+        let rsp: RspData<AffectedResponse> = self
+            .client
+            .patch(&format!("{}/list/{}/items", self.url, list))
+            .bearer_auth(&self.token)
+            .json(&BulkUpdateRequest { updates })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn import_text(&self, list: &Uuid, text: String) -> Result<ImportResponse> {
+        let rsp: RspData<ImportResponse> = self
+            .client
+            .post(&format!("{}/list/{}/import-text", self.url, list))
+            .bearer_auth(&self.token)
+            .header(reqwest::header::CONTENT_TYPE, "text/plain")
+            .body(text)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn import_text_with_delimiter(
+        &self,
+        list: &Uuid,
+        text: String,
+        delimiter: &str,
+    ) -> Result<ImportResponse> {
+        let rsp: RspData<ImportResponse> = self
+            .client
+            .post(&format!("{}/list/{}/import-text", self.url, list))
+            .query(&[("delimiter", delimiter)])
+            .bearer_auth(&self.token)
+            .header(reqwest::header::CONTENT_TYPE, "text/plain")
+            .body(text)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn check_all(&self, list: &Uuid) -> Result<AffectedResponse> {
+        let rsp: RspData<AffectedResponse> = self
+            .client
+            .post(&format!("{}/list/{}/check-all", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn uncheck_all(&self, list: &Uuid) -> Result<AffectedResponse> {
+        let rsp: RspData<AffectedResponse> = self
+            .client
+            .post(&format!("{}/list/{}/uncheck-all", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn compact(&self, list: &Uuid) -> Result<AffectedResponse> {
+        let rsp: RspData<AffectedResponse> = self
+            .client
+            .post(&format!("{}/list/{}/compact", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn move_item(
+        &self,
+        list: &Uuid,
+        item: i32,
+        position: PositionRequest,
+    ) -> Result<PositionResponse> {
+        let rsp: RspData<PositionResponse> = self
+            .client
+            .post(&format!("{}/list/{}/{}/position", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&position)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn move_pantry_item(
+        &self,
+        list: &Uuid,
+        item: i32,
+        position: PositionRequest,
+    ) -> Result<PositionResponse> {
+        let rsp: RspData<PositionResponse> = self
+            .client
+            .post(&format!("{}/pantry/{}/{}/position", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&position)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn get_shares(&self, list: &Uuid) -> Result<GetSharesResponse> {
+        let rsp: RspData<GetSharesResponse> = self
+            .client
+            .get(&format!("{}/share/{}", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    /// Removes the shares of the list with accounts that no longer exist
+    pub async fn cleanup_shares(&self, list: &Uuid) -> Result<AffectedResponse> {
+        let rsp: RspData<AffectedResponse> = self
+            .client
+            .post(&format!("{}/list/{}/shares/cleanup", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn unshare_with(&self, list: &Uuid, account: &Uuid) -> Result<UnshareResponse> {
+        let rsp: RspData<UnshareResponse> = self
+            .client
+            .delete(&format!("{}/share/{}/{}", self.url, list, account))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn account_name(&self, account: &Uuid) -> Result<GetAccountNameResponse> {
+        let rsp: RspData<GetAccountNameResponse> = self
+            .client
+            .get(&format!("{}/account/{}/name", self.url, account))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn create_api_key(
+        &self,
+        name: String,
+        scope: TokenScope,
+    ) -> Result<CreateApiKeyResponse> {
+        let rsp: RspData<CreateApiKeyResponse> = self
+            .client
+            .post(&format!("{}/account/api-keys", self.url))
+            .bearer_auth(&self.token)
+            .json(&CreateApiKeyRequest { name, scope })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn leave_shares(&self) -> Result<AffectedResponse> {
+        let rsp: RspData<AffectedResponse> = self
+            .client
+            .delete(&format!("{}/account/shares", self.url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn shared_lists(&self, account: &Uuid) -> Result<GetSharedListsResponse> {
+        let rsp: RspData<GetSharedListsResponse> = self
+            .client
+            .get(&format!("{}/account/{}/shared-lists", self.url, account))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn public_lists(&self) -> Result<GetPublicListsResponse> {
+        let rsp: RspData<GetPublicListsResponse> = self
+            .client
+            .get(&format!("{}/account/public-lists", self.url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn export_account(&self) -> Result<AccountExportResponse> {
+        let rsp: RspData<AccountExportResponse> = self
+            .client
+            .get(&format!("{}/account/export", self.url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    /// Lists that changed since this account last read them
+    pub async fn unseen(&self) -> Result<UnseenResponse> {
+        let rsp: RspData<UnseenResponse> = self
+            .client
+            .get(&format!("{}/unseen", self.url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn set_public(&self, list: &Uuid) -> Result<SetPublicResponse> {
+        let rsp: RspData<SetPublicResponse> = self
+            .client
+            .put(&format!("{}/list/{}/public", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn remove_public(&self, list: &Uuid) -> Result<RemovePublicResponse> {
+        let rsp: RspData<RemovePublicResponse> = self
+            .client
+            .delete(&format!("{}/list/{}/public", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn archive_list(&self, list: &Uuid) -> Result<ArchiveListResponse> {
+        let rsp: RspData<ArchiveListResponse> = self
+            .client
+            .put(&format!("{}/list/{}/archive", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn unarchive_list(&self, list: &Uuid) -> Result<UnarchiveListResponse> {
+        let rsp: RspData<UnarchiveListResponse> = self
+            .client
+            .delete(&format!("{}/list/{}/archive", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn search_history(&self, list: &Uuid, search: &str) -> Result<GetHistoryResponse> {
+        let rsp: RspData<GetHistoryResponse> = self
+            .client
+            .get(&format!("{}/history/{}", self.url, list))
+            .query(&[("search", search)])
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn quickadd(&self, limit: Option<i64>) -> Result<QuickAddResponse> {
+        let mut request = self
+            .client
+            .get(&format!("{}/quickadd", self.url))
+            .bearer_auth(&self.token);
+        if let Some(limit) = limit {
+            request = request.query(&[("limit", limit)]);
+        }
+
+        let rsp: RspData<QuickAddResponse> = request.send().await?.json().await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn meta(&self) -> Result<MetaResponse> {
+        let rsp: RspData<MetaResponse> = self
+            .client
+            .get(&format!("{}/meta", self.url))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn pantry(&self, list: Uuid) -> Result<GetPantryResponse> {
+        let rsp: RspData<GetPantryResponse> = self
+            .client
+            .get(&format!("{}/pantry/{}", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn pantry_needing_restock(&self, list: Uuid) -> Result<GetPantryResponse> {
+        let rsp: RspData<GetPantryResponse> = self
+            .client
+            .get(&format!("{}/pantry/{}", self.url, list))
+            .query(&[("needs_restock", true)])
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn refill_pantry(&self, list: Uuid) -> Result<RefillPantryResponse> {
+        let rsp: RspData<RefillPantryResponse> = self
+            .client
+            .post(&format!("{}/pantry/{}/refill", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn preview_refill(&self, list: Uuid) -> Result<RefillPantryResponse> {
+        let rsp: RspData<RefillPantryResponse> = self
+            .client
+            .post(&format!("{}/pantry/{}/refill", self.url, list))
+            .query(&[("dry_run", true)])
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn add_to_pantry(
+        &self,
+        list: Uuid,
+        name: String,
+        target: f64,
+    ) -> Result<AddToPantryResponse> {
+        let rsp: RspData<AddToPantryResponse> = self
+            .client
+            .post(&format!("{}/pantry/{}", self.url, list))
+            .bearer_auth(&self.token)
+            .json(&AddToPantryRequest { name, target })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn pantry_status(&self, list: Uuid) -> Result<PantryStatusResponse> {
+        let rsp: RspData<PantryStatusResponse> = self
+            .client
+            .get(&format!("{}/pantry/{}/status", self.url, list))
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn batch_add_to_pantry(
+        &self,
+        list: Uuid,
+        items: Vec<AddToPantryRequest>,
+    ) -> Result<BatchPantryResponse> {
+        let rsp: RspData<BatchPantryResponse> = self
+            .client
+            .post(&format!("{}/pantry/{}/batch", self.url, list))
+            .bearer_auth(&self.token)
+            .json(&BatchPantryRequest { items })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn edit_pantry_item(
+        &self,
+        list: Uuid,
+        item: i32,
+        amount: Option<f64>,
+        target: Option<f64>,
+        min: Option<f64>,
+    ) -> Result<EditPantryItemResponse> {
+        let rsp: RspData<EditPantryItemResponse> = self
+            .client
+            .patch(&format!("{}/pantry/{}/{}", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&EditPantryItemRequest {
+                name: None,
+                amount,
+                target,
+                min,
+                clear_min: false,
+                unit: None,
+            })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    /// Removes the minimum of the item, so that it is refilled below its target again
+    pub async fn clear_pantry_min(&self, list: Uuid, item: i32) -> Result<EditPantryItemResponse> {
+        let rsp: RspData<EditPantryItemResponse> = self
+            .client
+            .patch(&format!("{}/pantry/{}/{}", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&EditPantryItemRequest {
+                name: None,
+                amount: None,
+                target: None,
+                min: None,
+                clear_min: true,
+                unit: None,
+            })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn set_pantry_unit(
+        &self,
+        list: Uuid,
+        item: i32,
+        unit: String,
+    ) -> Result<EditPantryItemResponse> {
+        let rsp: RspData<EditPantryItemResponse> = self
+            .client
+            .patch(&format!("{}/pantry/{}/{}", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&EditPantryItemRequest {
+                name: None,
+                amount: None,
+                target: None,
+                min: None,
+                clear_min: false,
+                unit: Some(unit),
+            })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn rename_pantry_item(
+        &self,
+        list: Uuid,
+        item: i32,
+        name: String,
+    ) -> Result<EditPantryItemResponse> {
+        let rsp: RspData<EditPantryItemResponse> = self
+            .client
+            .patch(&format!("{}/pantry/{}/{}", self.url, list, item))
+            .bearer_auth(&self.token)
+            .json(&EditPantryItemRequest {
+                name: Some(name),
+                amount: None,
+                target: None,
+                min: None,
+                clear_min: false,
+                unit: None,
+            })
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        map_res(rsp)
+    }
+
+    pub async fn consume_pantry_item(
+        &self,
+        list: Uuid,
+        item: i32,
+        amount: f64,
+    ) -> Result<ConsumeResponse> {
+        let rsp: RspData<ConsumeResponse> = self
+            .client
+            .post(&format!("{}/pantry/{}/{}/consume", self.url, list, item))
             .bearer_auth(&self.token)
-            .json(&EditPantryItemRequest { amount, target })
+            .json(&ConsumeRequest { amount })
             .send()
             .await?
             .json()
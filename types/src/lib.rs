@@ -1,5 +1,10 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+};
 
+pub use chrono;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "openapi")]
 use utoipa::{ToResponse, ToSchema, PartialSchema};
@@ -10,8 +15,12 @@ use uuid::Uuid;
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 #[error("Api returned an error: {description}")]
 pub struct RspErr {
+    /// Numeric error code, see the `Error` schema for the possible values
     pub code: usize,
     pub description: String,
+    /// Whether the same request may succeed if sent again later
+    #[serde(default)]
+    pub retryable: bool,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
@@ -60,27 +69,269 @@ impl ToSchema for SecretString {
     }
 }
 
+/// Replaces control characters (newlines, tabs, ...) in an item name by a single space, so that
+/// names render the same everywhere
+pub fn normalize_item_name(name: &str) -> String {
+    name.split(|c: char| c.is_control())
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Fields of the items returned when reading a list
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub enum ItemFields {
+    /// Only the fields of [`MinimalItem`], answered with a [`MinimalReadListResponse`]
+    Minimal,
+    #[default]
+    Full,
+}
+
+/// Where an item is in its lifecycle
+///
+/// Variants are ordered from the least to the most settled, a grouped item takes the least
+/// settled status of the items it merges
+#[derive(
+    Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Clone, Copy, Default,
+)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub enum ItemStatus {
+    /// To be bought
+    #[default]
+    Active,
+    /// Not committed to yet, kept for later
+    Maybe,
+    Checked,
+}
+
+/// Order of the items when reading a list
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub enum ItemSort {
+    /// Order chosen by the users of the list
+    #[default]
+    Position,
+    /// Following the aisle order of the list, then by name. Items in categories missing from
+    /// the aisle order come last, sorted by category
+    Aisle,
+}
+
+/// Capitalization applied to the names of the items added to a list
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub enum CaseMode {
+    /// Names are kept as typed
+    #[default]
+    None,
+    /// `milk` for `MILK`
+    Lower,
+    /// `Orange Juice` for `orange JUICE`
+    TitleCase,
+}
+
+impl CaseMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CaseMode::None => "none",
+            CaseMode::Lower => "lower",
+            CaseMode::TitleCase => "title_case",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(CaseMode::None),
+            "lower" => Some(CaseMode::Lower),
+            "title_case" => Some(CaseMode::TitleCase),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, name: &str) -> String {
+        match self {
+            CaseMode::None => name.to_string(),
+            CaseMode::Lower => name.to_lowercase(),
+            CaseMode::TitleCase => name
+                .split(' ')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) => first
+                            .to_uppercase()
+                            .chain(chars.flat_map(char::to_lowercase))
+                            .collect(),
+                        None => String::new(),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+/// Serializes timestamps as RFC3339 in UTC with microseconds, for use with `#[serde(with)]` on
+/// every timestamp field so that all endpoints use the same format.
+///
+/// Microseconds match the precision of postgres, so a timestamp sent back to the server (for
+/// example as a `since` filter) compares equal to the stored one.
+pub mod timestamp {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(timestamp: &DateTime<Utc>, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        ser.serialize_str(&timestamp.to_rfc3339_opts(SecondsFormat::Micros, true))
+    }
+
+    pub fn deserialize<'de, D>(de: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        DateTime::parse_from_rfc3339(&s)
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+            .map_err(serde::de::Error::custom)
+    }
+
+    /// Same as the parent module, for optional timestamps
+    pub mod option {
+        use chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S>(timestamp: &Option<DateTime<Utc>>, ser: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match timestamp {
+                Some(timestamp) => super::serialize(timestamp, ser),
+                None => ser.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(de: D) -> Result<Option<DateTime<Utc>>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] DateTime<Utc>);
+
+            Ok(Option::<Wrapper>::deserialize(de)?.map(|Wrapper(timestamp)| timestamp))
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 pub struct Empty {}
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct MetaResponse {
+    /// Current time of the server, to measure the skew with the clock of the client
+    #[serde(with = "timestamp")]
+    pub server_time: DateTime<Utc>,
+    /// Version of the API server
+    pub version: String,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct LoginRequest {
     pub password: SecretString,
     pub username: String,
+    /// Also return the profile and lists of the account, to save requests on startup
+    #[serde(default)]
+    pub full: bool,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 pub struct LoginResponse {
     pub token: String,
+    /// Only present when `full` was set in the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<LoginProfile>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct LoginProfile {
+    pub id: Uuid,
+    pub username: String,
+    /// Same as the default response of the list overview
+    pub lists: GetListsResponse,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ValidateTokenRequest {
+    pub token: String,
+}
+
+impl Debug for ValidateTokenRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValidateTokenRequest")
+            .field("token", &"*********")
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct ValidateTokenResponse {
+    pub valid: bool,
+    pub account: Option<Uuid>,
+    #[serde(default, with = "timestamp::option")]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scope: Option<TokenScope>,
+}
+
+/// What a token is allowed to do, tokens from login have the full scope
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub enum TokenScope {
+    #[default]
+    Full,
+    ReadLists,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::Full => "full",
+            TokenScope::ReadLists => "read_lists",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scope: TokenScope,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct CreateApiKeyResponse {
+    pub id: Uuid,
+    pub token: String,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct CreateListRequest {
     pub name: String,
+    pub description: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
@@ -105,12 +356,62 @@ pub struct ListInfo {
     pub status: ListStatus,
     pub public: bool,
     pub owner: Uuid,
+    pub description: Option<String>,
+    pub archived: bool,
+    #[serde(with = "timestamp")]
+    pub created_at: DateTime<Utc>,
+    /// Tags set by the owner of the list, sorted
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Items of the list must have an amount
+    #[serde(default)]
+    pub require_amount: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ListSort {
+    /// Oldest list first
+    #[default]
+    Created,
+    Name,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Hash)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct ListDetail {
+    pub id: Uuid,
+    pub name: String,
+    pub status: ListStatus,
+    pub owner: Uuid,
+    pub owner_name: String,
+    pub public: bool,
+    pub item_count: i64,
+    pub description: Option<String>,
+    /// Units accepted on items of this list, empty if any unit is accepted
+    #[serde(default)]
+    pub allowed_units: Vec<String>,
+    #[serde(default)]
+    pub normalize_case: CaseMode,
+    /// Order in which the categories are walked through in the store
+    #[serde(default)]
+    pub aisle_order: Vec<String>,
+    /// Amount given to items added without one
+    #[serde(default)]
+    pub default_amount: Option<String>,
+    /// Items of the list must have an amount
+    #[serde(default)]
+    pub require_amount: bool,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 pub struct GetListsResponse {
     pub results: HashMap<Uuid, ListInfo>,
+    /// Keys of `results`, in the requested sort order
+    #[serde(default)]
+    pub order: Vec<Uuid>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
@@ -123,8 +424,63 @@ pub struct SearchAccountResponse {
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct Item {
     pub id: i32,
+    /// Stable opaque identifier, accepted in place of `id` in item routes
+    pub external_id: Uuid,
     pub name: String,
     pub amount: Option<String>,
+    #[serde(default)]
+    pub unit: Option<String>,
+    /// Aisle or section of the store where the item is found
+    #[serde(default)]
+    pub category: Option<String>,
+    pub checked: bool,
+    /// Always [`ItemStatus::Checked`] when `checked` is set
+    #[serde(default)]
+    pub status: ItemStatus,
+    pub position: i32,
+    pub url: Option<String>,
+    pub added_by: Uuid,
+    pub added_by_name: String,
+    /// Whether the caller may edit or delete this item
+    pub editable: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct SearchItemsResponse {
+    pub items: Vec<Item>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct MinimalItem {
+    pub id: i32,
+    pub name: String,
+    pub amount: Option<String>,
+    pub checked: bool,
+}
+
+impl From<Item> for MinimalItem {
+    fn from(item: Item) -> Self {
+        Self {
+            id: item.id,
+            name: item.name,
+            amount: item.amount,
+            checked: item.checked,
+        }
+    }
+}
+
+/// A [`ReadListResponse`] where only the items are trimmed down, the list fields are kept
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct MinimalReadListResponse {
+    pub items: Vec<MinimalItem>,
+    pub readonly: bool,
+    pub public: bool,
+    pub share_count: i64,
+    pub checked_count: i64,
+    pub total_count: i64,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
@@ -132,19 +488,77 @@ pub struct Item {
 pub struct ReadListResponse {
     pub items: Vec<Item>,
     pub readonly: bool,
+    pub public: bool,
+    pub share_count: i64,
+    /// Number of checked items in the list, regardless of grouping
+    pub checked_count: i64,
+    pub total_count: i64,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct MultiReadRequest {
+    pub ids: Vec<Uuid>,
+    /// Merge items with the same name in each list
+    #[serde(default)]
+    pub group: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct MultiReadResponse {
+    /// Lists the caller can't read are omitted
+    pub lists: BTreeMap<Uuid, ReadListResponse>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct UpdateListRequest {
+    /// An empty description removes it
+    pub description: Option<String>,
+    /// Replaces the units accepted on items, an empty set accepts any unit
+    pub allowed_units: Option<Vec<String>>,
+    pub normalize_case: Option<CaseMode>,
+    /// Replaces the order of the categories used by the `aisle` sort
+    pub aisle_order: Option<Vec<String>>,
+    /// Amount given to items added without one, an empty amount removes it
+    #[serde(default)]
+    pub default_amount: Option<String>,
+    /// Reject items without an amount, the default amount counts as one for new items
+    #[serde(default)]
+    pub require_amount: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct UpdateListResponse {}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct AddToListRequest {
     pub name: String,
     pub amount: Option<String>,
+    /// Must be one of the list's allowed units, if it restricts them
+    pub unit: Option<String>,
+    pub category: Option<String>,
+    /// Insert the item at this position instead of appending it, the items from there on are
+    /// moved further down when it is taken
+    pub position: Option<i32>,
+    /// Link to the item, must be an http or https URL
+    pub url: Option<String>,
+    /// Instead of adding a new row, update the amount of an unchecked item with the same name that
+    /// was added by a pantry refill, keeping its link to the pantry
+    pub merge_duplicates: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 pub struct AddToListResponse {
     pub id: i32,
+    /// Unchecked item with the same name that was already in the list, only looked up when
+    /// asked with `warn_duplicate`
+    #[serde(default)]
+    pub duplicate_of: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
@@ -154,9 +568,116 @@ pub struct ShareListRequest {
     pub readonly: bool,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ShareInfo {
+    pub account: Uuid,
+    pub username: String,
+    pub readonly: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ShareListResponse {
+    pub share: ShareInfo,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ShareManyRequest {
+    pub shares: Vec<ShareListRequest>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ShareRejection {
+    /// The account owns the list
+    Owner,
+    UnknownAccount,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct RejectedShare {
+    pub account: Uuid,
+    pub reason: ShareRejection,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct ShareManyResponse {
+    pub shared: Vec<ShareInfo>,
+    pub rejected: Vec<RejectedShare>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct Contact {
+    pub account: Uuid,
+    pub username: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct GetContactsResponse {
+    pub contacts: Vec<Contact>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct AddContactRequest {
+    pub account: Uuid,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct AddContactResponse {
+    pub contact: Contact,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct DeleteContactResponse {}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct CreateShareLinkRequest {
+    /// Holders of a read-only link can see the list but not add items to it. Links are read-only
+    /// unless asked otherwise, as anyone holding them can use them
+    #[serde(default = "default_true")]
+    pub readonly: bool,
+    /// The link stops working after this date, it never expires if absent
+    #[serde(default, with = "timestamp::option")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct CreateShareLinkResponse {
+    pub token: Uuid,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct RevokeShareLinkResponse {}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct CreateGuestTokenResponse {
+    /// Used to revoke the token
+    pub id: Uuid,
+    /// Bearer token only allowed to read the list
+    pub token: String,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
-pub struct ShareListResponse {}
+pub struct RevokeGuestTokenResponse {}
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
@@ -166,6 +687,73 @@ pub struct DeleteItemResponse {}
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct DeleteShareResponse {}
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct SharedList {
+    pub id: Uuid,
+    pub name: String,
+    pub readonly: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct GetSharedListsResponse {
+    pub lists: Vec<SharedList>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct PublicList {
+    pub id: Uuid,
+    pub name: String,
+    /// Path of the public page of the list, relative to the server
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct GetPublicListsResponse {
+    pub lists: Vec<PublicList>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ExportedList {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub public: bool,
+    pub archived: bool,
+    #[serde(with = "timestamp")]
+    pub created_at: DateTime<Utc>,
+    pub items: Vec<Item>,
+    pub pantry: Vec<PantryItem>,
+    /// Accounts the list is shared with, mapped to whether their share is read-only
+    pub shared_with: HashMap<Uuid, bool>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ExportedHistoryEntry {
+    pub list: Uuid,
+    pub name: String,
+    pub amount: Option<String>,
+}
+
+/// Everything stored for an account
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct AccountExportResponse {
+    pub id: Uuid,
+    pub username: String,
+    /// Lists owned by the account
+    pub lists: Vec<ExportedList>,
+    /// Lists of other accounts shared with this one, mapped to whether the share is read-only
+    pub shared_with_me: HashMap<Uuid, bool>,
+    /// Names entered by the account, on any list
+    pub history: Vec<ExportedHistoryEntry>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct DeleteListResponse {}
@@ -181,17 +769,136 @@ pub struct RegisterRequest {
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct RegisterResponse {}
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct AdminRegisterResponse {
+    pub id: Uuid,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct UpdateItemRequest {
     pub name: Option<String>,
     pub amount: Option<String>,
+    /// An empty unit removes it
+    pub unit: Option<String>,
+    /// An empty category removes it
+    pub category: Option<String>,
+    pub checked: Option<bool>,
+    /// Takes precedence over `checked`. Unchecking an item, with either field, puts it back in
+    /// the status it had before being checked
+    pub status: Option<ItemStatus>,
+    /// An empty URL removes the link
+    pub url: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct UpdateItemResponse {}
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ItemUpdate {
+    pub id: i32,
+    pub name: Option<String>,
+    pub amount: Option<String>,
+    pub checked: Option<bool>,
+    pub position: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct BulkUpdateRequest {
+    pub updates: Vec<ItemUpdate>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+/// Returned by the operations that act on several rows at once
+pub struct AffectedResponse {
+    /// Number of rows that were changed
+    pub affected: u64,
+}
+
+/// Maximum number of [`ImportError`]s in an [`ImportResponse`], further skipped rows are only
+/// counted
+pub const MAX_IMPORT_ERRORS: usize = 100;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ImportErrorReason {
+    /// Nothing was left of the name once blanks and control characters were removed
+    EmptyName,
+}
+
+/// A row of an import that was skipped
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ImportError {
+    /// Line of the row in the imported text, starting at 1
+    pub line: usize,
+    pub reason: ImportErrorReason,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct ImportResponse {
+    pub imported: u64,
+    pub skipped: u64,
+    /// The first skipped rows, at most [`MAX_IMPORT_ERRORS`]
+    pub errors: Vec<ImportError>,
+}
+
+/// Moves an item next to another one, exactly one of `before` or `after` must be set
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct PositionRequest {
+    pub before: Option<i32>,
+    pub after: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct PositionResponse {
+    pub position: i32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ReceiptItem {
+    pub id: i32,
+    pub name: String,
+    pub amount: Option<String>,
+    #[serde(with = "timestamp")]
+    pub checked_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct ReceiptResponse {
+    pub items: Vec<ReceiptItem>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct DeletedItem {
+    pub id: i32,
+    pub external_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct ListChangesResponse {
+    /// Items created or modified since `since`. Changes made while the request was answered may
+    /// be returned again by the next request
+    pub items: Vec<Item>,
+    /// Deleted items are only kept for 30 days, a `since` from before that is rejected
+    pub deleted: Vec<DeletedItem>,
+    /// Counter of the last change of the list, to use as `since` in the next request
+    pub next_since: i64,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 pub struct RecoveryInfoResponse {
@@ -213,6 +920,12 @@ pub struct RecoverPasswordResponse {}
 pub struct GetSharesResponse {
     pub shared_with: HashMap<Uuid, bool>,
     pub public_link: Option<String>,
+    /// Number of accounts in `shared_with` that can only read the list
+    #[serde(default)]
+    pub shared_read_count: usize,
+    /// Number of accounts in `shared_with` that can also edit the list
+    #[serde(default)]
+    pub shared_write_count: usize,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone, Copy)]
@@ -227,53 +940,174 @@ pub struct GetAccountNameResponse {
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
-pub struct SetPublicResponse {}
+pub struct SetPublicResponse {
+    /// Code of the list in the short public URL, `/api/p/{short_code}`
+    #[serde(default)]
+    pub short_code: Option<String>,
+}
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 pub struct RemovePublicResponse {}
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct ArchiveListResponse {}
+
+/// A list that changed since the caller last read it
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct UnseenList {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(with = "timestamp")]
+    pub updated_at: DateTime<Utc>,
+    /// Last time the caller read the list, absent if they never did
+    #[serde(default, with = "timestamp::option")]
+    pub seen_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct UnseenResponse {
+    /// Most recently changed first
+    pub lists: Vec<UnseenList>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct ListTagsResponse {
+    /// Tags of the list after the change, sorted
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct UnarchiveListResponse {}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 pub struct GetHistoryResponse {
     pub matches: Vec<String>,
+    /// Same entries as `matches`, along with the amount last used for each name
+    #[serde(default)]
+    pub suggestions: Vec<HistorySuggestion>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct HistorySuggestion {
+    pub name: String,
+    pub amount: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct QuickAddResponse {
+    pub names: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct PantryItem {
     pub name: String,
     pub id: i32,
-    pub amount: i32,
-    pub target: i32,
+    pub amount: f64,
+    pub target: f64,
+    /// Refill is triggered when the amount drops below this, or below `target` when unset
+    pub min: Option<f64>,
+    pub unit: Option<String>,
+    /// Amount and target formatted for display, like `3 / 6 bottles`
+    pub display_amount: String,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 pub struct GetPantryResponse {
     pub items: Vec<PantryItem>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct AddToPantryRequest {
     pub name: String,
-    pub target: i32,
+    pub target: f64,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 pub struct AddToPantryResponse {}
 
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct BatchPantryRequest {
+    pub items: Vec<AddToPantryRequest>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
-pub struct RefillPantryResponse {}
+pub struct BatchPantryResponse {
+    /// IDs of the pantry items, in the order of the request
+    pub ids: Vec<i32>,
+}
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ToPantryRequest {
+    pub target: f64,
+    /// Remove the item from the list once it has been stocked
+    #[serde(default)]
+    pub remove: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct ToPantryResponse {
+    pub pantry_item: i32,
+    /// Amount of the pantry item after the transfer
+    pub amount: f64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct PreviewItem {
+    pub name: String,
+    pub amount: String,
+    pub from_pantry: i32,
+    /// Quantity missing to reach the target, `amount` as a number
+    pub needed: f64,
+    pub target: f64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct RefillPantryResponse {
+    pub added: Vec<PreviewItem>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct PantryStatusResponse {
+    pub total_items: i64,
+    /// Items with an amount below their target
+    pub below_target: i64,
+    /// Sum of the amounts divided by the sum of the targets, absent if all targets are 0
+    pub fill_ratio: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToSchema))]
 pub struct EditPantryItemRequest {
-    pub target: Option<i32>,
-    pub amount: Option<i32>,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub target: Option<f64>,
+    pub amount: Option<f64>,
+    pub min: Option<f64>,
+    /// Removes `min`, so that refills are triggered below `target` again. A `min` given in the
+    /// same request is kept
+    #[serde(default)]
+    pub clear_min: bool,
+    pub unit: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
@@ -283,3 +1117,151 @@ pub struct EditPantryItemResponse {}
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Hash, Clone)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 pub struct DeletePantryItemResponse {}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ConsumeRequest {
+    /// Must be positive
+    pub amount: f64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct ConsumeResponse {
+    /// Amount left in the pantry
+    pub amount: f64,
+}
+
+/// Sent when the amount of a pantry item goes below its refill threshold
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct PantryAlert {
+    pub item: i32,
+    pub name: String,
+    pub amount: f64,
+    /// `min` of the item, or its `target` when unset
+    pub threshold: f64,
+    pub unit: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_names_lose_control_characters() {
+        assert_eq!(normalize_item_name("Eggs"), "Eggs");
+        assert_eq!(normalize_item_name(" Eggs\n"), "Eggs");
+        assert_eq!(normalize_item_name("Brown\t\n eggs"), "Brown eggs");
+        assert_eq!(normalize_item_name("Free  range"), "Free  range");
+        assert_eq!(normalize_item_name("\n\t"), "");
+    }
+
+    #[test]
+    fn case_modes() {
+        assert_eq!(CaseMode::None.apply("bRoWn eggs"), "bRoWn eggs");
+        assert_eq!(CaseMode::Lower.apply("Brown EGGS"), "brown eggs");
+        assert_eq!(CaseMode::TitleCase.apply("brown EGGS"), "Brown Eggs");
+        assert_eq!(CaseMode::TitleCase.apply("élan  vital"), "Élan  Vital");
+        assert_eq!(CaseMode::TitleCase.apply(""), "");
+    }
+
+    #[test]
+    fn case_mode_names() {
+        for mode in [CaseMode::None, CaseMode::Lower, CaseMode::TitleCase] {
+            assert_eq!(CaseMode::from_name(mode.as_str()), Some(mode));
+        }
+        assert_eq!(CaseMode::from_name("upper"), None);
+    }
+
+    fn utc(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn timestamps_are_rfc3339_micros() {
+        let item = |checked_at| ReceiptItem {
+            id: 1,
+            name: "Eggs".into(),
+            amount: None,
+            checked_at,
+        };
+
+        // Converted to UTC and truncated to microseconds
+        let json = serde_json::to_value(item(utc("2024-03-05T07:08:09.123456789+02:00"))).unwrap();
+        assert_eq!(json["checked_at"], "2024-03-05T05:08:09.123456Z");
+        let parsed: ReceiptItem = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.checked_at, utc("2024-03-05T05:08:09.123456Z"));
+
+        let json = serde_json::to_value(item(utc("2024-03-05T05:08:09Z"))).unwrap();
+        assert_eq!(json["checked_at"], "2024-03-05T05:08:09.000000Z");
+    }
+
+    #[test]
+    fn optional_timestamps_are_rfc3339_micros() {
+        let response = |expires_at| ValidateTokenResponse {
+            valid: true,
+            account: None,
+            expires_at,
+            scope: None,
+        };
+
+        let json =
+            serde_json::to_value(response(Some(utc("2024-03-05T07:08:09.5+02:00")))).unwrap();
+        assert_eq!(json["expires_at"], "2024-03-05T05:08:09.500000Z");
+        let parsed: ValidateTokenResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.expires_at, Some(utc("2024-03-05T05:08:09.5Z")));
+
+        let json = serde_json::to_value(response(None)).unwrap();
+        assert_eq!(json["expires_at"], serde_json::Value::Null);
+        let parsed: ValidateTokenResponse =
+            serde_json::from_str(r#"{"valid": false, "account": null, "scope": null}"#).unwrap();
+        assert_eq!(parsed.expires_at, None);
+    }
+
+    #[test]
+    fn import_errors() {
+        let response = ImportResponse {
+            imported: 2,
+            skipped: 2,
+            errors: vec![
+                ImportError {
+                    line: 3,
+                    reason: ImportErrorReason::EmptyName,
+                },
+                ImportError {
+                    line: 7,
+                    reason: ImportErrorReason::EmptyName,
+                },
+            ],
+        };
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "imported": 2,
+                "skipped": 2,
+                "errors": [
+                    {"line": 3, "reason": "empty_name"},
+                    {"line": 7, "reason": "empty_name"},
+                ],
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<ImportResponse>(json).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn share_links_default_to_readonly() {
+        let request: CreateShareLinkRequest = serde_json::from_str("{}").unwrap();
+        assert!(request.readonly);
+        let request: CreateShareLinkRequest =
+            serde_json::from_str(r#"{"readonly": false}"#).unwrap();
+        assert!(!request.readonly);
+    }
+}
@@ -228,7 +228,7 @@ impl Component for List {
                         };
 
                         match c
-                            .edit_pantry_item(id, item_id, Some(amount), Some(target))
+                            .edit_pantry_item(id, item_id, Some(amount), Some(target), None)
                             .await
                         {
                             Ok(_) => match c.pantry(id).await {
@@ -270,7 +270,7 @@ impl Component for List {
             move |(name, target): (String, String)| {
                 let c = c.clone();
                 async move {
-                    let target: i32 = match target.parse() {
+                    let target: f64 = match target.parse() {
                         Ok(t) => t,
                         Err(_) => {
                             return ListMessage::Error("Expected number for target".to_string())
@@ -420,7 +420,7 @@ fn render_pantry_item(
     edit_item: Callback<(i32, String, String)>,
 ) -> Html {
     let item_id = item.id;
-    let text = format!("{} ({}/{})", item.name, item.amount, item.target);
+    let text = format!("{} ({})", item.name, item.display_amount);
 
     let onclick_delete = Callback::from(move |_| on_delete_item.emit(item_id));
 